@@ -0,0 +1,85 @@
+//! Times the expansion of generated inputs, split into parsing and generating the output.
+//!
+//! Run with `cargo bench -p captures-core`, and compare against a run on the previous commit. Out
+//! of the compiler, `proc_macro2` uses its own implementation of the tokens, for which comparing
+//! idents and changing spans are much cheaper than across the compiler's proc macro bridge. The
+//! `profile` feature of `captures` measures expansions inside the compiler.
+
+use std::time::{Duration, Instant};
+
+use captures_core::{Input, Kind};
+use proc_macro2::TokenStream;
+
+const RUNS: usize = 10;
+
+/// `clone` directives for `n` variables, all used by a small body
+fn directives(n: usize) -> String {
+    let dirs: String = (0..n).map(|i| format!("clone a{}, ", i)).collect();
+    let uses: Vec<_> = (0..n).map(|i| format!("a{}.len()", i)).collect();
+    format!("{}move || {}", dirs, uses.join(" + "))
+}
+
+/// A body with `n` statements that bind, shadow, loop over, and format variables
+fn body(n: usize) -> String {
+    let stmts: String = (0..n)
+        .map(|i| {
+            format!(
+                "let x{0} = a.len() + {0}; \
+                 if let Some(y) = b.get(x{0}) {{ v.push(*y + x{0}); }} \
+                 for z in 0..x{0} {{ println!(\"{{}} {{x{0}}}\", z + c); }} \
+                 let c = match x{0} {{ 0 => c, x => x * c }};",
+                i
+            )
+        })
+        .collect();
+    format!("all a, all b, all c, ref mut v, move || {{ {} }}", stmts)
+}
+
+/// The median time of `f` over the runs
+fn median(mut f: impl FnMut() -> Duration) -> Duration {
+    let mut times: Vec<_> = (0..RUNS).map(|_| f()).collect();
+    times.sort();
+    times[RUNS / 2]
+}
+
+fn bench(name: &str, input: &str, kind: Kind) {
+    let tokens: TokenStream = input.parse().unwrap();
+    let parse = median(|| {
+        let start = Instant::now();
+        Input::parse_for(tokens.clone(), kind).unwrap();
+        start.elapsed()
+    });
+    let generate = median(|| {
+        let parsed = Input::parse_for(tokens.clone(), kind).unwrap();
+        let start = Instant::now();
+        captures_core::generate(parsed, kind);
+        start.elapsed()
+    });
+    println!(
+        "{:<36} parse {:>10.3?}   generate {:>10.3?}",
+        name, parse, generate
+    );
+}
+
+fn main() {
+    for n in [10, 100, 500] {
+        bench(
+            &format!("capture!, {} directives", n),
+            &directives(n),
+            Kind::Capture,
+        );
+    }
+    for n in [10, 100, 1000] {
+        let input = body(n);
+        bench(
+            &format!("capture!, {} statements", n),
+            &input,
+            Kind::Capture,
+        );
+        bench(
+            &format!("capture_only!, {} statements", n),
+            &input,
+            Kind::CaptureOnly,
+        );
+    }
+}
@@ -0,0 +1,236 @@
+//! Our macro expands to something vaguely like this:
+//!
+//! ```text
+//! {
+//!     
+//!     let x = captured_by_clone(&x), // for `mut clone x`, with a `T: Clone` bound
+//!     let y = &mut y, // for `ref mut y`
+//!     let z = &z, // for `mut ref z`
+//!     let w = expr, // for `with w = expr`
+//!     let u = ctx.u.clone(), // for `clone u` in `capture_ctx!(ctx => ...)`
+//!     let v = Downgrade::downgrade(&v), // for `weak v`
+//!     assert_capture(&v), // for each of the above, if there is a trailing `; assert Bounds`
+//!     let span = Span::current(); // for the `instrument` flag
+//!
+//!     |old_sig| { // Keep the old closure signature
+//!         let _ = &x; // for those above that the body only uses part of, or not at all
+//!         let v = match Upgrade::upgrade(&v) { // for `weak v`
+//!             Some(v) => v,
+//!             None => return default,
+//!         };
+//!         let _ = &b; // for `all b`
+//!         let _guard = span.enter(); // for the `instrument` flag, with `span` captured above
+//!         old_body // old closure body
+//!     }
+//! }
+//! ```
+
+use proc_macro2::{Delimiter, Group, Ident, Punct, Spacing, Span, TokenStream, TokenTree};
+use quote::{quote, quote_spanned, ToTokens};
+use syn::{Expr, ExprAsync};
+
+use crate::clean::make_locals_mixed;
+use crate::lint;
+use crate::parse::*;
+
+pub struct Changes {
+    pub exterior: TokenStream,
+    pub interior: TokenStream,
+    pub exempt: Vec<Ident>,
+}
+
+impl Changes {
+    pub fn from_input(input: &Input, only: bool) -> Changes {
+        let mut exempt = Vec::new();
+        let mut ext = TokenStream::new();
+        let mut int = TokenStream::new();
+
+        // Each capture is checked against the bounds on its own, so that errors name the capture
+        let assert_capture = Ident::new("assert_capture", Span::mixed_site());
+        if let Some(Bounds { span, bounds }) = &input.bounds {
+            ext.extend(quote_spanned!(*span=> fn #assert_capture<T: #bounds>(_: &T) {}));
+        }
+
+        // The directives are applied in order, so later ones see the variables bound by earlier
+        // ones. Those are `mixed_site` for `capture_only!`, and so must be named that way.
+        let mut bound = Vec::new();
+        // Printed once, since a large body takes a while to print
+        let body = match &input.target {
+            Target::Closure(c) => Some(c.body.to_token_stream()),
+            Target::Async(ExprAsync { block, .. }) | Target::Gen(GenBlock { block, .. }) => {
+                Some(block.to_token_stream())
+            }
+            Target::Block(_) | Target::Macro(_) => None,
+        };
+        for (i, d) in input.assigned.iter().enumerate() {
+            let mu = &d.mu;
+            let mut int_upvar = d.upvar.clone();
+            if only {
+                make_mixed!(int_upvar);
+            }
+            // The weak reference itself is never mutated, only the upgraded value in the interior
+            let ext_mu = match &d.ty {
+                #[cfg(feature = "glib")]
+                DirectiveType::Weak(_) => None,
+                _ => mu.as_ref(),
+            };
+            // Rebinding the variable is the point, so shadowing lints don't apply
+            ext.extend(quote! {
+                #[allow(clippy::shadow_same, clippy::shadow_reuse, clippy::shadow_unrelated)]
+                let #ext_mu #int_upvar =
+            });
+            // For `capture_ctx!`, the variables are fields of the context instead of locals
+            let upvar = &d.upvar;
+            // Located at the variable, so that borrow errors point at the directive. A path like
+            // `ctx` or `self.ctx` only needs the parentheses around it to be located there, and
+            // keeps its own location, so that IDEs can still navigate from it.
+            let ext_upvar = match input.ctx.as_deref() {
+                Some(ctx @ (Expr::Path(_) | Expr::Field(_))) => {
+                    let mut parens = Group::new(Delimiter::Parenthesis, ctx.to_token_stream());
+                    parens.set_span(upvar.span());
+                    quote!(#parens.#upvar)
+                }
+                Some(ctx) => {
+                    let ctx = relocate(ctx.to_token_stream(), upvar.span());
+                    quote_spanned!(upvar.span()=> (#ctx).#upvar)
+                }
+                None => quote!(#upvar),
+            };
+            // The closure or future owns all of the captured values, even those it only uses a
+            // field of, or doesn't use at all, like `with _guard = ...`, so that they are dropped
+            // along with it rather than once it is created. Those used by a later `with` may be
+            // moved there, and are only captured if the body uses them.
+            let used_later = input.assigned[i + 1..].iter().any(|later| match &later.ty {
+                DirectiveType::With(expr) | DirectiveType::Custom(_, expr) => {
+                    mentions(expr.to_token_stream(), upvar)
+                }
+                _ => false,
+            });
+            let partly_used = body.as_ref().map_or(false, |body| {
+                !mentions(body.clone(), upvar) || mentions_field(body.clone(), upvar)
+            });
+            if partly_used && !used_later {
+                int.extend(quote!(let _ = &#int_upvar;));
+            }
+            match &d.ty {
+                DirectiveType::Clone(sp) => {
+                    // A function with the bound, so that a missing `Clone` impl is reported as
+                    // required by the directive, even if it's missing on a type parameter
+                    let f = Ident::new("captured_by_clone", Span::mixed_site());
+                    let call = Ident::new(
+                        "captured_by_clone",
+                        upvar.span().resolved_at(Span::mixed_site()),
+                    );
+                    let bound = quote_spanned!(*sp=> ::core::clone::Clone);
+                    let lint = match input.flags.lint_copy {
+                        Some(_) => lint::clone_of_copy(upvar, &ext_upvar),
+                        None => TokenStream::new(),
+                    };
+                    ext.extend(quote_spanned! {upvar.span()=>
+                        {
+                            fn #f<T: #bound>(value: &T) -> T {
+                                ::core::clone::Clone::clone(value)
+                            }
+                            #lint
+                            #call(&#ext_upvar)
+                        }
+                    });
+                }
+                DirectiveType::With(expr) | DirectiveType::Custom(_, expr) if only => {
+                    ext.extend(make_locals_mixed(
+                        expr.to_token_stream(),
+                        &bound,
+                        Span::mixed_site(),
+                    ));
+                }
+                DirectiveType::With(expr) | DirectiveType::Custom(_, expr) => {
+                    (&expr).to_tokens(&mut ext);
+                }
+                #[cfg(feature = "glib")]
+                DirectiveType::Weak(sp) => {
+                    let sp = *sp;
+                    let glib = match &input.flags.glib {
+                        Some(g) => g.path.to_token_stream(),
+                        None => quote_spanned![sp=> ::glib],
+                    };
+                    ext.extend(
+                        quote_spanned![sp=> #glib::clone::Downgrade::downgrade(&#ext_upvar)],
+                    );
+
+                    let default = input.flags.default_return.as_ref().map(|d| &d.expr);
+                    let val = Ident::new("val", proc_macro2::Span::mixed_site());
+                    int.extend(quote_spanned! {sp=>
+                        #[allow(clippy::shadow_same, clippy::shadow_reuse, clippy::shadow_unrelated)]
+                        let #mu #int_upvar = match #glib::clone::Upgrade::upgrade(&#int_upvar) {
+                            ::core::option::Option::Some(#val) => #val,
+                            ::core::option::Option::None => return #default,
+                        };
+                    });
+                }
+                #[cfg(feature = "pyo3")]
+                DirectiveType::CloneRef(sp) => {
+                    let sp = *sp;
+                    let gil = crate::py::gil();
+                    ext.extend(quote_spanned![sp=> ::pyo3::Py::clone_ref(&#ext_upvar, #gil)]);
+                }
+                DirectiveType::Ref(sp, mu) => {
+                    let mut ref_punc = Punct::new('&', Spacing::Alone);
+                    ref_punc.set_span(*sp);
+                    ext.extend(quote!(#ref_punc #mu #ext_upvar));
+                }
+            }
+            ext.extend(quote!(;));
+            if input.bounds.is_some() {
+                ext.extend(quote_spanned!(upvar.span()=> #assert_capture(&#int_upvar);));
+            }
+            bound.push(unraw_name(upvar));
+        }
+
+        // Closures enter the span in each call. Async blocks are instead wrapped in an
+        // `Instrumented` future once the directives have been applied.
+        #[cfg(feature = "tracing")]
+        if let (Some(span), Target::Closure(_)) = (input.flags.instrument, &input.target) {
+            let (var, guard) = (
+                Ident::new("span", Span::mixed_site()),
+                Ident::new("_guard", Span::mixed_site()),
+            );
+            ext.extend(quote_spanned!(span=> let #var = ::tracing::Span::current();));
+            int.extend(quote_spanned!(span=> let #guard = #var.enter();));
+        }
+
+        #[cfg(feature = "trace")]
+        {
+            let (trace_ext, trace_int) = crate::trace::bindings(input);
+            ext.extend(trace_ext);
+            int.extend(trace_int);
+        }
+
+        for d in &input.all {
+            let upvar = &d.upvar;
+            exempt.push(upvar.clone());
+            int.extend(quote!(let _ = &#upvar;));
+        }
+
+        Changes {
+            exterior: ext,
+            interior: int,
+            exempt,
+        }
+    }
+}
+
+/// Moves the tokens to `span`, keeping their hygiene
+fn relocate(tokens: TokenStream, span: Span) -> TokenStream {
+    tokens
+        .into_iter()
+        .map(|mut tt| {
+            if let TokenTree::Group(g) = &tt {
+                let mut new = Group::new(g.delimiter(), relocate(g.stream(), span));
+                new.set_span(span.resolved_at(g.span()));
+                tt = TokenTree::Group(new);
+            }
+            tt.set_span(span.resolved_at(tt.span()));
+            tt
+        })
+        .collect()
+}
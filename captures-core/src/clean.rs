@@ -0,0 +1,402 @@
+use std::collections::HashSet;
+
+use proc_macro2::{Group, Ident, Span, TokenStream, TokenTree};
+use quote::ToTokens;
+use syn::visit_mut::{self, VisitMut};
+use syn::{Attribute, Block, Expr, ExprClosure, Member, Pat};
+
+use crate::let_else::{let_chain_mut, LetElse};
+use crate::parse::unraw_name;
+use crate::std_macros::StdMacroArgs;
+use crate::MacroRule;
+
+/// Replaces hygiene information in the closure, making all locals `mixed_site` except those in the
+/// exempt list.
+///
+/// The parameters are cleaned along with the body, so that they can still be used in it. This
+/// respects shadowing. The tokens of the macros trusted by `rules` are kept as they are, except
+/// for the locals of the closure.
+pub fn clean(closure: &mut ExprClosure, exempt: &[Ident], rules: &[MacroRule]) {
+    CleaningState::new(exempt, rules).visit_expr_closure_mut(closure);
+}
+
+/// Like [`clean`], but for the body of an async block.
+pub fn clean_block(block: &mut Block, exempt: &[Ident], rules: &[MacroRule]) {
+    CleaningState::new(exempt, rules).visit_block_mut(block);
+}
+
+/// Stores the state for changing hygiene information.
+///
+/// The `exempt` list contains the names of the idents that are *currently* exempt from being
+/// cleaned, without any `r#`, since `r#a` and `a` are the same variable. All of the state holds
+/// names rather than idents, which are slow to compare.
+/// This does not include those idents which are normally exempt but currently shadowed. The
+/// shadowed idents are stored in the `shadowed` stack, and are popped off when their scope ends.
+///
+/// The reason we don't clean shadowed idents is to try and improve interactions with macros called
+/// inside the closure; this way all variables that are local within the closure have `mixed_site`
+/// hygiene.
+///
+/// The `locals` stack holds the names bound within the closure that are in scope, which are the
+/// only tokens of trusted macros that need to be cleaned.
+///
+/// A `let` with a `cfg` attribute may not be compiled, so whether it shadows an exempt ident can't
+/// be known. Its bindings of exempt idents keep their hygiene instead, like the uses after it, so
+/// that those refer to the binding if it exists, and to the captured variable otherwise.
+struct CleaningState<'a> {
+    exempt: HashSet<String>,
+    shadowed: Vec<String>,
+    locals: Vec<String>,
+    rules: &'a [MacroRule],
+    /// The `mixed_site` span, which is costly to get inside the compiler
+    mixed: Span,
+    /// Whether the pattern being visited is that of a `let` with a `cfg` attribute
+    cfg: bool,
+}
+
+/// The lengths of the stacks when a scope starts
+type Mark = (usize, usize);
+
+impl<'a> CleaningState<'a> {
+    fn new(exempt: &[Ident], rules: &'a [MacroRule]) -> Self {
+        CleaningState {
+            exempt: exempt.iter().map(unraw_name).collect(),
+            shadowed: Vec::new(),
+            locals: Vec::new(),
+            rules,
+            mixed: Span::mixed_site(),
+            cfg: false,
+        }
+    }
+
+    fn mark(&self) -> Mark {
+        (self.shadowed.len(), self.locals.len())
+    }
+
+    fn pop(&mut self, (shadowed, locals): Mark) {
+        self.exempt.extend(self.shadowed.drain(shadowed..));
+        self.locals.truncate(locals);
+    }
+
+    fn visit_let_pat(&mut self, attrs: &[Attribute], pat: &mut Pat) {
+        self.cfg = has_cfg(attrs);
+        self.visit_pat_mut(pat);
+        self.cfg = false;
+    }
+
+    fn trusts(&self, mac: &syn::Macro) -> bool {
+        self.rules
+            .iter()
+            .any(|rule| rule.trust && rule.matches(&mac.path))
+    }
+}
+
+macro_rules! wrap_visitors {
+    [$($name:ident , $t:ty);*] => {
+        $(
+            fn $name (&mut self, node: &mut $t) {
+                let mark = self.mark();
+                visit_mut::$name(self, node);
+                self.pop(mark);
+            }
+        )*
+    }
+}
+
+/// Visits a path that may be qualified, whose segments are then never cleaned
+fn visit_qualified(
+    state: &mut CleaningState,
+    attrs: &mut [syn::Attribute],
+    qself: &mut Option<syn::QSelf>,
+    path: &mut syn::Path,
+) {
+    for attr in attrs {
+        state.visit_attribute_mut(attr);
+    }
+    match qself {
+        Some(qself) => {
+            state.visit_qself_mut(qself);
+            for segment in &mut path.segments {
+                state.visit_path_arguments_mut(&mut segment.arguments);
+            }
+        }
+        None => state.visit_path_mut(path),
+    }
+}
+
+/// Whether the attributes include a `cfg`, which may remove what they are on
+pub fn has_cfg(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|attr| attr.path.is_ident("cfg"))
+}
+
+/// Wraps the expression in parentheses
+fn paren(expr: Expr, span: Span) -> Expr {
+    Expr::Paren(syn::ExprParen {
+        attrs: Vec::new(),
+        paren_token: syn::token::Paren(span),
+        expr: Box::new(expr),
+    })
+}
+
+/// Whether the token is the `'` that starts a lifetime or label, like `'a`
+fn is_quote(tt: &TokenTree) -> bool {
+    matches!(tt, TokenTree::Punct(p) if p.as_char() == '\'')
+}
+
+/// Changes the spans of the tokens with `f`, which is also given whether the token follows a `'`
+/// and returns whether it changed the token. The groups are only rebuilt when their tokens
+/// changed, and `None` is returned when nothing did.
+fn respan(s: TokenStream, f: &mut impl FnMut(&mut TokenTree, bool) -> bool) -> Option<TokenStream> {
+    let mut tokens: Vec<TokenTree> = s.into_iter().collect();
+    let mut changed = false;
+    let mut after_quote = false;
+    for tt in &mut tokens {
+        if let TokenTree::Group(g) = tt {
+            if let Some(stream) = respan(g.stream(), f) {
+                let mut out = Group::new(g.delimiter(), stream);
+                out.set_span(g.span());
+                *g = out;
+                changed = true;
+            }
+        }
+        changed |= f(tt, after_quote);
+        after_quote = is_quote(tt);
+    }
+    changed.then(|| tokens.into_iter().collect())
+}
+
+/// Makes all the tokens `mixed_site`, except the idents that are currently exempt, and lifetimes
+/// and labels, since labels are hygienic with `mixed_site` and are never captured anyway
+fn make_stream_mixed(s: TokenStream, exempt: &HashSet<String>, mixed: Span) -> TokenStream {
+    respan(s.clone(), &mut |tt, after_quote| match tt {
+        TokenTree::Group(g) => {
+            make_mixed!(*g, mixed);
+            true
+        }
+        _ if after_quote || is_quote(tt) => false,
+        TokenTree::Ident(i) if exempt.contains(&unraw_name(i)) => false,
+        _ => {
+            make_mixed!(*tt, mixed);
+            true
+        }
+    })
+    .unwrap_or(s)
+}
+
+/// Makes the locals `mixed_site`, leaving the other tokens as they are, including lifetimes and
+/// labels that have the name of a local
+pub fn make_locals_mixed(s: TokenStream, locals: &[String], mixed: Span) -> TokenStream {
+    respan(s.clone(), &mut |tt, after_quote| match tt {
+        TokenTree::Ident(i) if !after_quote && locals.contains(&unraw_name(i)) => {
+            make_mixed!(*i, mixed);
+            true
+        }
+        _ => false,
+    })
+    .unwrap_or(s)
+}
+
+impl VisitMut for CleaningState<'_> {
+    fn visit_pat_ident_mut(&mut self, node: &mut syn::PatIdent) {
+        visit_mut::visit_pat_ident_mut(self, node);
+        let name = unraw_name(&node.ident);
+        if self.cfg && self.exempt.contains(&name) {
+            return;
+        }
+        // This is the only place new idents are introduced. Shadowed exempt idents remain shadowed
+        // until the end of the current scope.
+        if let Some(name) = self.exempt.take(&name) {
+            self.shadowed.push(name);
+        }
+        self.locals.push(name);
+        make_mixed!(node.ident, self.mixed);
+    }
+
+    // Only a plain name like `x` can refer to a local variable, so only those are cleaned. The
+    // segments of longer paths, like `Self::new`, `T::default`, `Self::CONST` or `E::Variant`,
+    // name items, associated items and generic parameters, and are left as they are, as is `Self`.
+    fn visit_path_mut(&mut self, node: &mut syn::Path) {
+        if node.leading_colon.is_none() && node.segments.len() == 1 {
+            let segment = &mut node.segments[0];
+            let name = unraw_name(&segment.ident);
+            if segment.arguments.is_empty() && name != "Self" && !self.exempt.contains(&name) {
+                make_mixed!(segment.ident, self.mixed);
+            }
+        }
+        for segment in &mut node.segments {
+            self.visit_path_arguments_mut(&mut segment.arguments);
+        }
+    }
+
+    // A path with a qualified self type, like `<T>::f` or `<T as Trait>::f`, always names an item
+    fn visit_expr_path_mut(&mut self, node: &mut syn::ExprPath) {
+        visit_qualified(self, &mut node.attrs, &mut node.qself, &mut node.path);
+    }
+
+    fn visit_pat_path_mut(&mut self, node: &mut syn::PatPath) {
+        visit_qualified(self, &mut node.attrs, &mut node.qself, &mut node.path);
+    }
+
+    fn visit_type_path_mut(&mut self, node: &mut syn::TypePath) {
+        visit_qualified(self, &mut [], &mut node.qself, &mut node.path);
+    }
+
+    // A call takes its location from the name of the function, so after cleaning a name like `f`
+    // in `f()`, the location that `#[track_caller]` functions see would be that of the macro.
+    // Parentheses with the span of the name keep it where the call is written.
+    fn visit_expr_call_mut(&mut self, node: &mut syn::ExprCall) {
+        let cleaned = match &*node.func {
+            Expr::Path(func) if func.qself.is_none() => func
+                .path
+                .get_ident()
+                .filter(|i| {
+                    let name = unraw_name(i);
+                    name != "Self" && !self.exempt.contains(&name)
+                })
+                .map(Ident::span),
+            _ => None,
+        };
+        visit_mut::visit_expr_call_mut(self, node);
+        if let Some(span) = cleaned {
+            let func = std::mem::replace(&mut *node.func, Expr::Verbatim(TokenStream::new()));
+            *node.func = paren(func, span);
+        }
+    }
+
+    // With the shorthand `Point { x }`, the member is printed in place of the variable, so it has
+    // to be the cleaned ident. Shorthand patterns print the binding instead, which is cleaned.
+    fn visit_field_value_mut(&mut self, node: &mut syn::FieldValue) {
+        visit_mut::visit_field_value_mut(self, node);
+        if node.colon_token.is_none() {
+            if let (Member::Named(member), Expr::Path(var)) = (&mut node.member, &node.expr) {
+                if let Some(var) = var.path.get_ident() {
+                    *member = var.clone();
+                }
+            }
+        }
+    }
+
+    // Need to re-order here, recursing on the RHS before the pattern, since that is the order in
+    // which things arrive into scope. The default ordering on match arms is right, so no need to
+    // do so there as well.
+    fn visit_expr_let_mut(&mut self, node: &mut syn::ExprLet) {
+        for att in &mut node.attrs {
+            self.visit_attribute_mut(att);
+        }
+        // In a let chain, the conditions after the `let` come after the pattern
+        let mut rest = Vec::new();
+        self.visit_expr_mut(let_chain_mut(&mut node.expr, &mut rest));
+        self.visit_pat_mut(&mut node.pat);
+        for cond in rest {
+            self.visit_expr_mut(cond);
+        }
+        // The compiler only allows let chains if the scrutinee is from a recent enough edition,
+        // which cleaned tokens take from this crate. The outer parentheses give it the edition
+        // of the `let`, and the inner ones keep `unused_parens` from firing on the outer ones.
+        let scrutinee = let_chain_mut(&mut node.expr, &mut Vec::new());
+        let span = node.let_token.span;
+        let inner = std::mem::replace(scrutinee, Expr::Verbatim(TokenStream::new()));
+        let inner = paren(inner, span.resolved_at(Span::mixed_site()));
+        *scrutinee = paren(inner, span);
+    }
+
+    fn visit_local_mut(&mut self, node: &mut syn::Local) {
+        for att in &mut node.attrs {
+            self.visit_attribute_mut(att);
+        }
+        if let Some((_, expr)) = &mut node.init {
+            self.visit_expr_mut(expr);
+        }
+        self.visit_let_pat(&node.attrs, &mut node.pat);
+    }
+
+    // syn only gives `let ... else` as tokens, which are cleaned in the same order as `let`. The
+    // `else` block is a block of its own, so the pattern isn't in scope in it.
+    fn visit_stmt_mut(&mut self, node: &mut syn::Stmt) {
+        if let syn::Stmt::Semi(expr, _) = node {
+            if let Some(mut local) = LetElse::parse(expr) {
+                for att in &mut local.attrs {
+                    self.visit_attribute_mut(att);
+                }
+                self.visit_expr_mut(&mut local.init);
+                self.visit_block_mut(&mut local.diverge);
+                self.visit_let_pat(&local.attrs, &mut local.pat);
+                *expr = Expr::Verbatim(local.into_token_stream());
+                return;
+            }
+        }
+        visit_mut::visit_stmt_mut(self, node);
+    }
+
+    // The arguments of the well-known std macros are cleaned like the rest of the closure. For
+    // other macros, we make sure all tokens passed to them are `mixed_site`, except for the exempt
+    // idents, so that those can still be used by the macro. In particular, this lets a nested
+    // invocation of one of our macros see the same variables as the code around it.
+    // FIXME: this is not strictly correct, but is the best possible approximation we can get
+    // without eager macro expansion
+    // The tokens of trusted macros keep their hygiene, except for the locals of the closure, which
+    // the macro could otherwise not see.
+    // The name of the macro is never a local, so it is left as written, which keeps `line!()` and
+    // the location of a panic from `assert!` or `unreachable!` where they are written.
+    fn visit_macro_mut(&mut self, node: &mut syn::Macro) {
+        if self.trusts(node) {
+            let tokens = std::mem::take(&mut node.tokens);
+            node.tokens = make_locals_mixed(tokens, &self.locals, self.mixed);
+            return;
+        }
+        let args = StdMacroArgs::parse(node);
+        node.tokens = match args {
+            Some(mut args) => {
+                let mark = self.mark();
+                let (exprs, matched) = args.parts_mut();
+                for expr in exprs {
+                    self.visit_expr_mut(expr);
+                }
+                if let Some((pat, guard)) = matched {
+                    self.visit_pat_mut(pat);
+                    if let Some(guard) = guard {
+                        self.visit_expr_mut(guard);
+                    }
+                }
+                self.pop(mark);
+                args.into_token_stream()
+            }
+            None => make_stream_mixed(std::mem::take(&mut node.tokens), &self.exempt, self.mixed),
+        };
+    }
+
+    // Items can't use the locals of the closure, so they keep their own hygiene, which also keeps
+    // `use` items importing what they name. Brace-style macro calls like `foo! { a }` also parse
+    // as items, and are cleaned like other macro calls.
+    fn visit_item_mut(&mut self, node: &mut syn::Item) {
+        if let syn::Item::Macro(syn::ItemMacro { ident: None, .. }) = node {
+            visit_mut::visit_item_mut(self, node);
+        }
+    }
+
+    // Attributes like `cfg`, `allow` and tool attributes never refer to local variables, so they
+    // are left as written
+    fn visit_attribute_mut(&mut self, _: &mut Attribute) {}
+
+    // Cant just `wrap_visitors!` for `ExprIf`, since the `else` block is excluded
+    fn visit_expr_if_mut(&mut self, node: &mut syn::ExprIf) {
+        let mark = self.mark();
+        for att in &mut node.attrs {
+            self.visit_attribute_mut(att);
+        }
+        self.visit_expr_mut(&mut node.cond);
+        self.visit_block_mut(&mut node.then_branch);
+        self.pop(mark);
+        if let Some((_, expr)) = &mut node.else_branch {
+            self.visit_expr_mut(expr);
+        }
+    }
+
+    wrap_visitors!(
+        visit_block_mut, syn::Block;
+        visit_expr_closure_mut, syn::ExprClosure;
+        visit_expr_for_loop_mut, syn::ExprForLoop;
+        visit_expr_while_mut, syn::ExprWhile;
+        visit_arm_mut, syn::Arm
+    );
+}
@@ -0,0 +1,65 @@
+//! Directives defined by the macros built on this crate, like a project-specific `db_handle x`.
+//!
+//! The directives are parsed through syn's `Parse` trait, which can't be given any state, so the
+//! directives of the current [`expand_with`](crate::expand_with) are kept in a thread local while
+//! it runs.
+
+use std::cell::RefCell;
+
+use proc_macro2::{Ident, TokenStream};
+
+/// A directive with its own keyword, which binds the variable to a new value like `with` does.
+///
+/// For example, this makes `db_handle x` capture `x.handle()` as `x`:
+/// ```
+/// use captures_core::{CustomDirective, Kind};
+/// use proc_macro2::{Ident, TokenStream};
+/// use quote::quote;
+///
+/// struct DbHandle;
+///
+/// impl CustomDirective for DbHandle {
+///     fn keyword(&self) -> &str {
+///         "db_handle"
+///     }
+///
+///     fn value(&self, var: &Ident) -> TokenStream {
+///         quote!(#var.handle())
+///     }
+/// }
+///
+/// static DB_HANDLE: DbHandle = DbHandle;
+///
+/// // The implementation of `my_capture!(db_handle db, move || ...)`
+/// fn my_capture(input: TokenStream) -> TokenStream {
+///     captures_core::expand_with(input, Kind::Capture, &[&DB_HANDLE])
+/// }
+/// # let expanded = my_capture(quote!(db_handle db, move || db.query()));
+/// # assert!(expanded.to_string().contains("let db = db . handle ()"));
+/// ```
+pub trait CustomDirective {
+    /// The keyword, like `db_handle`. The keywords of this crate take precedence.
+    fn keyword(&self) -> &str;
+
+    /// The expression that the variable is bound to, given the variable as it is written in the
+    /// directive
+    fn value(&self, var: &Ident) -> TokenStream;
+}
+
+thread_local! {
+    /// The directives of the `expand_with` that is currently running
+    static DIRECTIVES: RefCell<Vec<&'static dyn CustomDirective>> = RefCell::new(Vec::new());
+}
+
+/// Runs `f` with the directives available to the parser
+pub fn scoped<T>(directives: &[&'static dyn CustomDirective], f: impl FnOnce() -> T) -> T {
+    let outer = DIRECTIVES.with(|d| d.replace(directives.to_vec()));
+    let out = f();
+    DIRECTIVES.with(|d| *d.borrow_mut() = outer);
+    out
+}
+
+/// The directive with the keyword, if the current expansion has one
+pub fn find(keyword: &str) -> Option<&'static dyn CustomDirective> {
+    DIRECTIVES.with(|d| d.borrow().iter().copied().find(|d| d.keyword() == keyword))
+}
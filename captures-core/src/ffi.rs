@@ -0,0 +1,80 @@
+//! Expansion for `capture_extern!`.
+
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::Pat;
+
+use crate::Target;
+
+/// The argument types of the closure. The return type is left to inference.
+pub struct Signature {
+    inputs: Vec<TokenStream>,
+}
+
+impl Signature {
+    pub fn new(target: &Target) -> Self {
+        let closure = match target {
+            Target::Closure(c) => c,
+            _ => panic!("Bug: `capture_extern!` without a closure"),
+        };
+        let inputs = closure
+            .inputs
+            .iter()
+            .map(|arg| match arg {
+                Pat::Type(t) => {
+                    let ty = &t.ty;
+                    quote!(#ty)
+                }
+                _ => panic!("Bug: untyped argument in `capture_extern!`"),
+            })
+            .collect();
+        Signature { inputs }
+    }
+}
+
+/// Boxes the closure, and returns the pointer to the box along with the `extern "C"` functions
+/// that call and drop it.
+///
+/// The functions take the pointer to the box as their last argument, after those of the closure.
+pub fn trampoline(signature: Signature, expanded: TokenStream) -> TokenStream {
+    let Signature { inputs } = signature;
+    let mixed = |name: &str| Ident::new(name, Span::mixed_site());
+    let (make, call, destroy) = (mixed("make"), mixed("call"), mixed("destroy"));
+    let (f, data) = (mixed("f"), mixed("data"));
+    let (ty, ret) = (mixed("F"), mixed("R"));
+    let args: Vec<_> = (0..inputs.len())
+        .map(|i| format_ident!("arg{}", i, span = Span::mixed_site()))
+        .collect();
+    let c_void = quote!(::core::ffi::c_void);
+    let (krate, path) = crate::alloc_box();
+
+    quote! {
+        {
+            #krate
+            #[allow(clippy::type_complexity)]
+            fn #make<#ty: ::core::ops::FnMut(#(#inputs),*) -> #ret + 'static, #ret>(
+                #f: #ty,
+            ) -> (
+                *mut #c_void,
+                unsafe extern "C" fn(#(#inputs,)* *mut #c_void) -> #ret,
+                unsafe extern "C" fn(*mut #c_void),
+            ) {
+                unsafe extern "C" fn #call<#ty: ::core::ops::FnMut(#(#inputs),*) -> #ret, #ret>(
+                    #(#args: #inputs,)*
+                    #data: *mut #c_void,
+                ) -> #ret {
+                    (*(#data as *mut #ty))(#(#args),*)
+                }
+                unsafe extern "C" fn #destroy<#ty>(#data: *mut #c_void) {
+                    ::core::mem::drop(#path::from_raw(#data as *mut #ty));
+                }
+                (
+                    #path::into_raw(#path::new(#f)) as *mut #c_void,
+                    #call::<#ty, #ret>,
+                    #destroy::<#ty>,
+                )
+            }
+            #make(#expanded)
+        }
+    }
+}
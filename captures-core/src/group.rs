@@ -0,0 +1,118 @@
+//! Directive groups, declared with `define_captures!`.
+//!
+//! A proc macro can't remember anything between invocations, so a group is declared as a
+//! `macro_rules!` macro of the same name, which hands its directives back to us. An invocation like
+//! ```text
+//! capture!(clone a, use handler_env, clone b, || ...)
+//! ```
+//! is turned into
+//! ```text
+//! handler_env! { capture [clone a,] use , clone b, || ... }
+//! ```
+//! which the group then expands to
+//! ```text
+//! ::captures::capture! { clone a, use { clone db, clone cfg }, clone b, || ... }
+//! ```
+//! The directives in the braces are then parsed as if they had been written next to the `use`.
+
+use proc_macro2::{Delimiter, Ident, TokenStream, TokenTree};
+use quote::quote;
+use syn::parse::{ParseStream, Parser};
+use syn::{Attribute, Error, Token};
+
+use crate::parse::Directives;
+
+/// If the input refers to a directive group by name, hands it off to the group's macro, which will
+/// call the macro `name` again with the group's directives spliced in.
+pub fn redirect(name: &str, inp: &TokenStream) -> Option<TokenStream> {
+    let mut tokens: Vec<TokenTree> = Vec::new();
+    for tt in inp.clone() {
+        // Directives in brackets, which are the first argument or follow the `=>` of
+        // `capture_ctx!`, can `use` a group too, so the brackets are left out around it
+        let bracketed = match &tt {
+            TokenTree::Group(g)
+                if g.delimiter() == Delimiter::Bracket
+                    && (tokens.is_empty() || follows_arrow(&tokens)) =>
+            {
+                Some(g.stream().into_iter().collect::<Vec<_>>())
+            }
+            _ => None,
+        };
+        match bracketed {
+            Some(mut inner) if inner.windows(2).any(is_use) => {
+                if matches!(inner.last(), Some(TokenTree::Punct(p)) if p.as_char() == ',') {
+                    inner.pop();
+                }
+                tokens.extend(inner);
+            }
+            _ => tokens.push(tt),
+        }
+    }
+    let pos = tokens.windows(2).position(is_use)?;
+    let mac = Ident::new(name, proc_macro2::Span::call_site());
+    let before = &tokens[..pos];
+    let kw = &tokens[pos];
+    let group = &tokens[pos + 1];
+    let after = &tokens[pos + 2..];
+    Some(quote!(#group! { #mac [#(#before)*] #kw #(#after)* }))
+}
+
+/// Whether the tokens are `use` followed by the name of a group
+fn is_use(tokens: &[TokenTree]) -> bool {
+    match tokens {
+        [TokenTree::Ident(kw), TokenTree::Ident(_)] => kw == "use",
+        _ => false,
+    }
+}
+
+/// Whether the tokens end with `=>`
+fn follows_arrow(tokens: &[TokenTree]) -> bool {
+    match tokens {
+        [.., TokenTree::Punct(eq), TokenTree::Punct(gt)] => {
+            eq.as_char() == '=' && gt.as_char() == '>'
+        }
+        _ => false,
+    }
+}
+
+/// Expands `define_captures!(name = directives...)` to the group's macro.
+pub fn define(inp: TokenStream) -> TokenStream {
+    if let Some(out) = redirect("define_captures", &inp) {
+        return out;
+    }
+    let parser = |input: ParseStream| {
+        let attrs = input.call(Attribute::parse_outer)?;
+        let name = input.parse::<Ident>()?;
+        input.parse::<Token![=]>()?;
+        // The definition may end with a `;`, and the `;` that ends a `with` directive is part of
+        // the directives
+        let mut directives: Vec<TokenTree> = input.parse::<TokenStream>()?.into_iter().collect();
+        if matches!(directives.last(), Some(TokenTree::Punct(p)) if p.as_char() == ';') {
+            directives.pop();
+        }
+        let directives: TokenStream = directives.into_iter().collect();
+        // Check the directives now, so that mistakes are reported here and not at every use
+        let check = |input: ParseStream| {
+            let mut err = None;
+            Directives::parse(input, &mut err);
+            if !input.is_empty() {
+                let e = input.error("expected a directive");
+                match &mut err {
+                    Some(err) => err.combine(e),
+                    None => err = Some(e),
+                }
+            }
+            err.map_or(Ok(()), Err)
+        };
+        check.parse2(directives.clone())?;
+        Ok(quote! {
+            #(#attrs)*
+            macro_rules! #name {
+                ($mac:ident [$($before:tt)*] $kw:tt $($after:tt)*) => {
+                    ::captures::$mac! { $($before)* $kw { #directives } $($after)* }
+                };
+            }
+        })
+    };
+    parser.parse2(inp).unwrap_or_else(Error::into_compile_error)
+}
@@ -0,0 +1,110 @@
+//! The `let`s that syn doesn't give in the order in which their variables come into scope.
+//!
+//! `let ... else` statements are only given as a verbatim expression, followed by the `;`. The
+//! pattern binds its variables only after the statement, so they aren't in scope in the
+//! initializer or the `else` block.
+//!
+//! In a let chain like `if let Some(a) = x && a > 0 && let Some(b) = y`, syn takes the rest of the
+//! chain as part of the first scrutinee, as in `let Some(a) = (x && a > 0 && ...)`, while the
+//! pattern is in scope in the conditions after it.
+
+use proc_macro2::TokenStream;
+use quote::ToTokens;
+use syn::parse::{Parse, ParseStream};
+use syn::{Attribute, BinOp, Block, Expr, ExprBinary, Pat, PatType, Token};
+
+use crate::std_macros::parse_pat;
+
+pub struct LetElse {
+    pub attrs: Vec<Attribute>,
+    let_token: Token![let],
+    pub pat: Pat,
+    eq_token: Token![=],
+    pub init: Expr,
+    else_token: Token![else],
+    pub diverge: Block,
+}
+
+impl LetElse {
+    /// Parses the expression of a statement if it is a `let ... else`
+    pub fn parse(expr: &Expr) -> Option<Self> {
+        match expr {
+            Expr::Verbatim(tokens) => syn::parse2(tokens.clone()).ok(),
+            _ => None,
+        }
+    }
+}
+
+impl Parse for LetElse {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
+        let let_token = input.parse()?;
+        let mut pat = parse_pat(input)?;
+        if input.peek(Token![:]) {
+            pat = Pat::Type(PatType {
+                attrs: Vec::new(),
+                pat: Box::new(pat),
+                colon_token: input.parse()?,
+                ty: input.parse()?,
+            });
+        }
+        Ok(LetElse {
+            attrs,
+            let_token,
+            pat,
+            eq_token: input.parse()?,
+            init: input.parse()?,
+            else_token: input.parse()?,
+            diverge: input.parse()?,
+        })
+    }
+}
+
+impl ToTokens for LetElse {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        for attr in &self.attrs {
+            attr.to_tokens(tokens);
+        }
+        self.let_token.to_tokens(tokens);
+        self.pat.to_tokens(tokens);
+        self.eq_token.to_tokens(tokens);
+        self.init.to_tokens(tokens);
+        self.else_token.to_tokens(tokens);
+        self.diverge.to_tokens(tokens);
+    }
+}
+
+/// Splits the expression that syn gives as the scrutinee of a `let` into the actual scrutinee and
+/// the conditions of the let chain after it, in order
+pub fn let_chain<'a>(expr: &'a Expr, rest: &mut Vec<&'a Expr>) -> &'a Expr {
+    match expr {
+        Expr::Binary(ExprBinary {
+            left,
+            op: BinOp::And(_),
+            right,
+            ..
+        }) => {
+            let scrutinee = let_chain(left, rest);
+            rest.push(right);
+            scrutinee
+        }
+        expr => expr,
+    }
+}
+
+/// Like [`let_chain`]
+pub fn let_chain_mut<'a>(expr: &'a mut Expr, rest: &mut Vec<&'a mut Expr>) -> &'a mut Expr {
+    match expr {
+        Expr::Binary(ExprBinary {
+            left,
+            op: BinOp::And(_),
+            right,
+            ..
+        }) => {
+            let scrutinee = let_chain_mut(left, rest);
+            rest.push(right);
+            scrutinee
+        }
+        expr => expr,
+    }
+}
@@ -0,0 +1,514 @@
+//! The directive parser and code generator behind the [`captures`] macros.
+//!
+//! This crate is meant for authors of other procedural macros who want to accept the same capture
+//! directives, for example in a wrapper that spawns onto a custom runtime. The input can be handed
+//! to [`expand`] as a whole, or parsed into an [`Input`] first, inspected, and then passed to
+//! [`generate`]:
+//! ```
+//! use captures_core::Kind;
+//! use proc_macro2::TokenStream;
+//! use quote::quote;
+//!
+//! // The implementation of `my_spawn!(clone a, move || ...)`
+//! fn my_spawn(input: TokenStream) -> TokenStream {
+//!     let closure = captures_core::expand(input, Kind::Capture);
+//!     quote!(::my_runtime::spawn(#closure))
+//! }
+//! ```
+//! The generated code refers to `::captures` when directive groups are used, so crates using such
+//! a macro must depend on `captures` as well in that case.
+//!
+//! Such a macro can define directives with keywords of its own through [`CustomDirective`], which
+//! are accepted by [`expand_with`].
+//!
+//! [`captures`]: https://docs.rs/captures
+#![cfg_attr(feature = "nightly-diagnostics", feature(proc_macro_diagnostic))]
+
+#[cfg(feature = "nightly-diagnostics")]
+extern crate proc_macro;
+
+use proc_macro2::{Delimiter, Group, Ident, Span, TokenStream, TokenTree};
+use quote::{quote, quote_spanned, ToTokens};
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::{Expr, Token};
+
+/// Takes a place with type having `.set_span(_)` and `.span()` methods, and optionally the
+/// `mixed_site` span, for callers that change many spans and get it once
+macro_rules! make_mixed {
+    ($i:expr) => {
+        make_mixed!($i, ::proc_macro2::Span::mixed_site())
+    };
+    ($i:expr, $mixed:expr) => {
+        let e = &mut $i;
+        e.set_span(e.span().resolved_at($mixed));
+    };
+}
+
+mod changes;
+mod clean;
+mod custom;
+#[cfg(feature = "alloc")]
+mod ffi;
+mod group;
+mod let_else;
+mod lint;
+mod literals;
+mod parse;
+#[cfg(feature = "profile")]
+pub mod profile;
+#[cfg(feature = "pyo3")]
+mod py;
+#[cfg(any(
+    feature = "thread",
+    feature = "tokio",
+    feature = "async-std",
+    feature = "smol"
+))]
+mod spawn;
+mod std_macros;
+#[cfg(feature = "trace")]
+mod trace;
+#[cfg(feature = "wasm-bindgen")]
+mod wasm;
+
+use changes::*;
+pub use custom::CustomDirective;
+pub use parse::*;
+
+/// Expands `define_captures!(name = directives...)` to the `macro_rules!` macro for the group
+pub fn define_group(inp: TokenStream) -> TokenStream {
+    group::define(inp)
+}
+
+/// Expands the input to the macro of the given kind.
+///
+/// This is the entire implementation of the function-like macros in `captures`, including the
+/// handling of directive groups.
+///
+/// If only the directives have syntax errors, the target is still expanded next to the errors, so
+/// that IDEs keep working inside of it.
+pub fn expand(inp: TokenStream, kind: Kind) -> TokenStream {
+    hiding_literals(inp, |inp| {
+        let expand = || match shortcut(&inp, kind) {
+            Some(out) => out,
+            None => expand_recovering(inp, kind, |_| ()),
+        };
+        #[cfg(feature = "profile")]
+        let expand = || profile::expansion(kind.name(), expand);
+        expand()
+    })
+}
+
+/// Like [`expand`], but returns errors in the input instead of turning them into
+/// `compile_error!` invocations.
+pub fn try_expand(inp: TokenStream, kind: Kind) -> syn::Result<TokenStream> {
+    let mut hidden = literals::Hidden::default();
+    let inp = literals::hide(inp, &mut hidden);
+    let expand = || match shortcut(&inp, kind) {
+        Some(out) => Ok(out),
+        None => Input::parse_for(inp, kind).map(|parsed| generate(parsed, kind)),
+    };
+    #[cfg(feature = "profile")]
+    let expand = || profile::expansion(kind.name(), expand);
+    expand().map(|out| hidden.restore(out))
+}
+
+/// Runs `expand` on the tokens with the literals that syn can't parse, like `c"..."`, replaced by
+/// placeholders, and puts the literals back into its output.
+///
+/// syn panics on these literals, so code that may contain them has to be parsed through this.
+/// [`expand`] and [`try_expand`] already do so.
+pub fn hiding_literals(
+    tokens: TokenStream,
+    expand: impl FnOnce(TokenStream) -> TokenStream,
+) -> TokenStream {
+    let mut hidden = literals::Hidden::default();
+    let out = expand(literals::hide(tokens, &mut hidden));
+    hidden.restore(out)
+}
+
+/// Like [`expand`], but also accepts the given directives of other crates.
+///
+/// These can't be used along with directive groups, since those hand the invocation to the macros
+/// of `captures`, which don't know them.
+pub fn expand_with(
+    inp: TokenStream,
+    kind: Kind,
+    directives: &[&'static dyn CustomDirective],
+) -> TokenStream {
+    custom::scoped(directives, || expand(inp, kind))
+}
+
+/// The expansions that don't parse the input as a whole: redirection through directive groups,
+/// and tuples of targets
+fn shortcut(inp: &TokenStream, kind: Kind) -> Option<TokenStream> {
+    group::redirect(kind.name().trim_end_matches('!'), inp).or_else(|| expand_tuple(inp, kind))
+}
+
+/// Parses and expands the input, with the errors as `compile_error!` invocations
+fn expand_recovering(inp: TokenStream, kind: Kind, adjust: impl FnOnce(&mut Input)) -> TokenStream {
+    match Input::parse_recovering(inp, kind) {
+        Ok((mut parsed, err)) => {
+            adjust(&mut parsed);
+            let expanded = generate(parsed, kind);
+            match err {
+                Some(err) => {
+                    let err = err.into_compile_error();
+                    quote!({ #err #expanded })
+                }
+                None => expanded,
+            }
+        }
+        // With several errors, the `compile_error!`s need a block to be an expression together
+        Err(err) => {
+            let err = err.into_compile_error();
+            quote!({ #err })
+        }
+    }
+}
+
+/// Expands `capture!(directives, (closure, closure))` to a tuple, with the directives applied to
+/// each of the closures separately.
+fn expand_tuple(inp: &TokenStream, kind: Kind) -> Option<TokenStream> {
+    let tokens: Vec<_> = inp.clone().into_iter().collect();
+    let is_punct =
+        |i: usize, c: char| matches!(&tokens[i], TokenTree::Punct(p) if p.as_char() == c);
+    // The tuple is the last thing before the end of the input, which may have a trailing comma,
+    // or before a trailing `; assert ...`. A `;` may also end a `with` directive.
+    let pos = match tokens.windows(2).position(|w| match w {
+        [TokenTree::Punct(p), TokenTree::Ident(kw)] => p.as_char() == ';' && kw == "assert",
+        _ => false,
+    }) {
+        Some(pos) => pos,
+        None if tokens.len() > 1 && is_punct(tokens.len() - 1, ',') => tokens.len() - 1,
+        None => tokens.len(),
+    };
+    let pos = pos.checked_sub(1)?;
+    let group = match &tokens[pos] {
+        TokenTree::Group(g) if g.delimiter() == Delimiter::Parenthesis => g,
+        _ => return None,
+    };
+    // After the directives, which may end with `=>` or the `;` of a `with` directive, or the `=>`
+    // of `capture_ctx!`, but not after a turbofish as in the body `|| iter.sum::<i32>()`
+    match pos.checked_sub(1) {
+        None => (),
+        Some(i) if is_punct(i, ',') || is_punct(i, ';') => (),
+        Some(i) if is_punct(i, '>') && i > 0 && is_punct(i - 1, '=') => (),
+        _ => return None,
+    }
+    let targets = Punctuated::<Expr, Token![,]>::parse_terminated
+        .parse2(group.stream())
+        .ok()?;
+    if targets.is_empty()
+        || !targets
+            .iter()
+            .all(|t| matches!(t, Expr::Closure(_) | Expr::Async(_) | Expr::Macro(_)))
+    {
+        return None;
+    }
+
+    let (before, after) = (&tokens[..pos], &tokens[pos + 1..]);
+    let expanded = targets.pairs().map(|pair| {
+        let (target, comma) = pair.into_tuple();
+        // Each closure gets all of the directives, so they need not all be used by every one
+        let target = expand_recovering(quote!(#(#before)* #target #(#after)*), kind, |parsed| {
+            parsed.unused.clear()
+        });
+        quote!(#target #comma)
+    });
+    let mut out = Group::new(Delimiter::Parenthesis, expanded.collect());
+    out.set_span(group.span());
+    Some(quote!(#out))
+}
+
+/// Produces the full expansion of the macro of the given kind from its already parsed input
+pub fn generate(mut parsed: Input, kind: Kind) -> TokenStream {
+    // Before the flags that are handled here are taken
+    #[cfg(feature = "pedantic")]
+    let pedantic = lint::no_directives(&parsed, kind);
+    let debug = parsed.flags.debug.take();
+    let introspect = parsed
+        .flags
+        .introspect
+        .take()
+        .map(|span| (span, capture_list(&parsed)));
+    #[cfg(feature = "alloc")]
+    let boxed = parsed.flags.boxed.take();
+    #[cfg(feature = "thread")]
+    let name = parsed.flags.name.take();
+    #[cfg(any(feature = "tokio", feature = "async-std", feature = "smol"))]
+    let span = parsed.target.span();
+    #[cfg(feature = "wasm-bindgen")]
+    let arity = match &parsed.target {
+        Target::Closure(c) => c.inputs.len(),
+        // Macro invocations are rejected for the wasm macros
+        Target::Async(_) | Target::Gen(_) | Target::Block(_) | Target::Macro(_) => 0,
+    };
+    #[cfg(feature = "pyo3")]
+    let gil = parsed.py.take();
+    let mut lints = match kind {
+        Kind::CaptureLint => lint::undeclared(&parsed),
+        Kind::CaptureOnly => lint::blocked(&parsed),
+        _ => TokenStream::new(),
+    };
+    lints.extend(lint::unused(&parsed));
+    lints.extend(lint::unverified_macros(&parsed));
+    lints.extend(lint::made_move(&parsed));
+    lints.extend(lint::shadowed(&parsed));
+    lints.extend(lint::report(&parsed, kind));
+    lints.extend(lint::borrowed_by_static(&parsed, kind));
+    #[cfg(feature = "pedantic")]
+    lints.extend(pedantic);
+    #[cfg(feature = "alloc")]
+    let signature = (kind == Kind::CaptureExtern).then(|| ffi::Signature::new(&parsed.target));
+    #[cfg(feature = "tracing")]
+    let instrument = match parsed.target {
+        Target::Async(_) => parsed.flags.instrument,
+        _ => None,
+    };
+    let bounds = parsed
+        .bounds
+        .as_ref()
+        .map(|b| (b.span, b.bounds.to_token_stream()));
+    let mut expanded = rewrite(parsed, kind == Kind::CaptureOnly);
+    #[cfg(feature = "tracing")]
+    if let Some(span) = instrument {
+        expanded = quote_spanned! {span=>
+            ::tracing::Instrument::instrument(#expanded, ::tracing::Span::current())
+        };
+    }
+
+    // Check the target as a whole too, to catch the captures that have no directive
+    if let Some((span, bounds)) = bounds {
+        let assert_bounds = Ident::new("assert_bounds", Span::mixed_site());
+        expanded = quote_spanned! {span=>
+            {
+                fn #assert_bounds<T: #bounds>(t: T) -> T {
+                    t
+                }
+                #assert_bounds(#expanded)
+            }
+        };
+    }
+
+    #[cfg(feature = "alloc")]
+    if let Some(Boxed { ty, .. }) = boxed {
+        let (krate, path) = alloc_box();
+        expanded = match ty {
+            Some(ty) => quote!({ #krate #path::new(#expanded) as #path<#ty> }),
+            None => quote!({ #krate #path::new(#expanded) }),
+        };
+    }
+
+    if let Some((span, captures)) = introspect {
+        expanded = quote_spanned!(span=> ::captures_lite::Captured::new(#expanded, &[#captures]));
+    }
+
+    let expanded = match kind {
+        Kind::Capture | Kind::CaptureOnly | Kind::CaptureCtx | Kind::CaptureLint | Kind::Bind => {
+            expanded
+        }
+        #[cfg(feature = "alloc")]
+        Kind::CaptureAsync => {
+            let (krate, path) = alloc_box();
+            quote!({ #krate #path::pin(#expanded) })
+        }
+        #[cfg(feature = "alloc")]
+        Kind::CaptureExtern => ffi::trampoline(signature.unwrap(), expanded),
+        #[cfg(feature = "thread")]
+        Kind::Spawn => spawn::thread(name.map(|n| n.expr), expanded),
+        #[cfg(feature = "tokio")]
+        Kind::SpawnTask => spawn::task(span, quote!(::tokio::spawn), expanded),
+        #[cfg(feature = "tokio")]
+        Kind::SpawnBlocking => spawn::tokio_blocking(span, expanded),
+        #[cfg(feature = "async-std")]
+        Kind::SpawnAsyncStd => spawn::task(span, quote!(::async_std::task::spawn), expanded),
+        #[cfg(feature = "smol")]
+        Kind::SpawnSmol => spawn::task(span, quote!(::smol::spawn), expanded),
+        #[cfg(feature = "wasm-bindgen")]
+        Kind::WasmClosure => wasm::wrap(arity, expanded),
+        #[cfg(feature = "wasm-bindgen")]
+        Kind::WasmClosureOnce => wasm::once(expanded),
+        #[cfg(feature = "pyo3")]
+        Kind::PyClosure => py::wrap(&gil.unwrap(), expanded),
+    };
+
+    let expanded = if lints.is_empty() {
+        expanded
+    } else {
+        quote!({ #lints #expanded })
+    };
+
+    match debug {
+        Some(span) => show_expansion(span, expanded),
+        None => expanded,
+    }
+}
+
+/// The directives as a list of `captures_lite::Capture`s, for the `introspect` flag
+fn capture_list(parsed: &Input) -> TokenStream {
+    let (names, kinds): (Vec<_>, Vec<_>) = parsed.directive_kinds().into_iter().unzip();
+    quote!(#(::captures_lite::Capture { name: #names, kind: #kinds },)*)
+}
+
+/// The item declaring the `alloc` crate, and the path to `Box` through it.
+///
+/// Declaring the crate in the expansion means that `#![no_std]` crates don't need to declare it
+/// themselves, and that none of the expansions name `std`.
+#[cfg(feature = "alloc")]
+fn alloc_box() -> (TokenStream, TokenStream) {
+    let alloc = Ident::new("alloc", Span::mixed_site());
+    (
+        quote!(extern crate alloc as #alloc;),
+        quote!(#alloc::boxed::Box),
+    )
+}
+
+/// Adds a warning showing the expansion, pointing at `span`.
+///
+/// There is no stable way for a proc macro to emit a note, so this uses the deprecation warning of
+/// a function with the expansion as its deprecation note.
+fn show_expansion(span: Span, expanded: TokenStream) -> TokenStream {
+    let note = format!("the expansion is: {}", expanded);
+    let f = Ident::new("captures_debug", span.resolved_at(Span::mixed_site()));
+    quote! {
+        {
+            #[deprecated(note = #note)]
+            fn #f() {}
+            #f();
+            #expanded
+        }
+    }
+}
+
+/// Applies the directives to the closure or async block
+fn rewrite(parsed: Input, only: bool) -> TokenStream {
+    let Changes {
+        exterior,
+        interior,
+        exempt,
+    } = Changes::from_input(&parsed, only);
+    let Input { target, flags, .. } = parsed;
+
+    match target {
+        Target::Closure(mut closure) => {
+            if only {
+                clean::clean(&mut closure, &exempt, &flags.macros);
+            }
+            let syn::ExprClosure {
+                attrs,
+                asyncness,
+                movability,
+                capture,
+                or1_token,
+                inputs,
+                or2_token,
+                output,
+                body,
+            } = closure;
+
+            quote! {
+                {
+                    #exterior
+                    #(#attrs)*
+                    #asyncness
+                    #movability
+                    #capture
+                    #or1_token
+                    #inputs
+                    #or2_token
+                    #output
+                    {
+                        #interior
+                        #body
+                    }
+                }
+            }
+        }
+        Target::Async(block) => {
+            let syn::ExprAsync {
+                attrs,
+                async_token,
+                capture,
+                mut block,
+            } = block;
+
+            assert!(attrs.is_empty());
+            if only {
+                clean::clean_block(&mut block, &exempt, &flags.macros);
+            }
+            let stmts = &block.stmts;
+
+            quote! {
+                {
+                    #exterior
+                    #async_token
+                    #capture
+                    {
+                        #interior
+                        #(#stmts)*
+                    }
+                }
+            }
+        }
+        Target::Gen(block) => {
+            let GenBlock {
+                attrs,
+                asyncness,
+                gen_token,
+                capture,
+                mut block,
+            } = block;
+
+            assert!(attrs.is_empty());
+            if only {
+                clean::clean_block(&mut block, &exempt, &flags.macros);
+            }
+            let stmts = &block.stmts;
+
+            quote! {
+                {
+                    #exterior
+                    #asyncness
+                    #gen_token
+                    #capture
+                    {
+                        #interior
+                        #(#stmts)*
+                    }
+                }
+            }
+        }
+        Target::Block(block) => {
+            let syn::ExprBlock {
+                attrs,
+                label,
+                block,
+            } = block;
+
+            assert!(attrs.is_empty());
+            let stmts = &block.stmts;
+
+            quote! {
+                {
+                    #exterior
+                    #label
+                    {
+                        #interior
+                        #(#stmts)*
+                    }
+                }
+            }
+        }
+        Target::Macro(mac) => {
+            assert!(interior.is_empty());
+            quote! {
+                {
+                    #exterior
+                    #mac
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,752 @@
+//! The warnings about unused directives, those of `capture_lint!`, and those explaining errors in
+//! `capture_only!` or the `move` that was inserted, those about parameters shadowing directives,
+//! the report of the `report` flag, the check for `clone` directives on `Copy` values, and the
+//! warning of the `pedantic` feature about invocations without directives, those about `ref`
+//! directives on futures that must be `'static`, and the errors of `strict_macros` and of `deny`
+//! flags.
+//!
+//! A directive is unused if its variable doesn't appear anywhere in the target's tokens.
+//!
+//! Whether a name in the closure refers to a local variable is only known after name resolution,
+//! so this is a syntactic approximation: the candidates are the names that don't start with an
+//! uppercase letter, like `a` or `数量`, that are used as values but not bound within the closure,
+//! excluding names that are only called, since those are usually functions. Each is reported
+//! through the deprecation warning of a generated function, which is the only way to emit a warning
+//! on stable, or as a compiler warning with the `nightly-diagnostics` feature.
+
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::quote_spanned;
+use syn::ext::IdentExt;
+use syn::parse::{ParseStream, Parser};
+use syn::punctuated::Punctuated;
+use syn::visit::{self, Visit};
+use syn::{Attribute, Error, Expr, Token};
+
+use crate::clean::has_cfg;
+use crate::let_else::{let_chain, LetElse};
+use crate::parse::unraw_name;
+use crate::std_macros::StdMacroArgs;
+use crate::{DirectiveType, Input, Kind, MacroRule, Target};
+
+/// Warnings for the directives whose variable is never used, or errors with `deny_unused`
+pub fn unused(input: &Input) -> TokenStream {
+    let note = |var: &Ident| format!("`{}` has a directive, but is never used", var);
+    if input.flags.deny_unused.is_some() {
+        let errors = input
+            .unused
+            .iter()
+            .map(|var| Error::new(var.span(), note(var)));
+        return errors.map(Error::into_compile_error).collect();
+    }
+    warnings(&input.unused, "unused_directive", |var| {
+        Note::new(note(var))
+    })
+}
+
+/// With `explain_move`, a warning naming the directive that the `move` was inserted for
+pub fn made_move(input: &Input) -> TokenStream {
+    match (&input.made_move, input.flags.explain_move) {
+        (Some((var, kind)), Some(_)) => warnings(std::slice::from_ref(var), "made_move", |var| {
+            Note::new(format!(
+                "the closure is made `move` for `{} {}`, so the variables it uses without a \
+                 directive are moved into it too",
+                kind, var
+            ))
+        }),
+        _ => TokenStream::new(),
+    }
+}
+
+/// With `lint_copy`, a warning if the variable of a `clone` directive turns out to be `Copy`.
+///
+/// Method resolution prefers the trait implemented for the wrapper itself over the one that is
+/// only implemented for a reference to it, so the deprecated method is only picked if the bound
+/// holds.
+pub fn clone_of_copy(var: &Ident, value: &TokenStream) -> TokenStream {
+    let note = format!(
+        "`{0}` is `Copy`, so `clone {0}` only copies it; a `move` closure copies it without a \
+         directive",
+        var
+    );
+    let wrapper = Ident::new("IsCopy", Span::mixed_site());
+    let not_copy = Ident::new("NotCopy", Span::mixed_site());
+    let copy = Ident::new("CopyValue", Span::mixed_site());
+    let method = Ident::new("clone_of_copy", Span::mixed_site());
+    let call = Ident::new("clone_of_copy", var.span().resolved_at(Span::mixed_site()));
+    quote_spanned! {var.span()=>
+        #[allow(dead_code)]
+        struct #wrapper<'a, T>(&'a T);
+        #[allow(dead_code)]
+        trait #not_copy {
+            fn #method(&self) {}
+        }
+        impl<T> #not_copy for &#wrapper<'_, T> {}
+        #[allow(dead_code)]
+        trait #copy {
+            #[deprecated(note = #note)]
+            fn #method(&self) {}
+        }
+        impl<T: ::core::marker::Copy> #copy for #wrapper<'_, T> {}
+        (&#wrapper(&#value)).#call();
+    }
+}
+
+/// With the `pedantic` feature, a warning for a `capture!` or `capture_ctx!` without any
+/// directives, flags or bounds, which returns the closure unchanged
+#[cfg(feature = "pedantic")]
+pub fn no_directives(input: &Input, kind: Kind) -> TokenStream {
+    let empty = input.assigned.is_empty()
+        && input.all.is_empty()
+        && input.bounds.is_none()
+        && input.flags.is_empty();
+    if !empty || !matches!(kind, Kind::Capture | Kind::CaptureCtx) {
+        return TokenStream::new();
+    }
+    warnings(
+        &[Ident::new("capture", Span::call_site())],
+        "no_directives",
+        |_| {
+            let mut note = Note::new(format!(
+                "`{}` has no directives, so it doesn't change the closure",
+                kind.name()
+            ));
+            note.help = Some(
+                "remove the macro, or add directives like `clone x` or `all x` for the variables \
+                 it should capture"
+                    .to_string(),
+            );
+            note
+        },
+    )
+}
+
+/// Warnings for the `ref` directives of a future that has to be `'static`, because of an
+/// `assert 'static` or because it is spawned, which the borrow checker reports without
+/// mentioning the directive
+pub fn borrowed_by_static(input: &Input, kind: Kind) -> TokenStream {
+    if !matches!(input.target, Target::Async(_)) || input.ctx.is_some() {
+        return TokenStream::new();
+    }
+    let asserted = input
+        .bounds
+        .iter()
+        .flat_map(|b| &b.bounds)
+        .any(|bound| matches!(bound, syn::TypeParamBound::Lifetime(l) if l.ident == "static"));
+    let required_by = match kind {
+        _ if asserted => "the `'static` bound requires".to_string(),
+        #[cfg(feature = "tokio")]
+        Kind::SpawnTask => format!("`{}` requires", kind.name()),
+        #[cfg(feature = "async-std")]
+        Kind::SpawnAsyncStd => format!("`{}` requires", kind.name()),
+        #[cfg(feature = "smol")]
+        Kind::SpawnSmol => format!("`{}` requires", kind.name()),
+        _ => return TokenStream::new(),
+    };
+    let refs: Vec<_> = input
+        .assigned
+        .iter()
+        .filter(|d| matches!(d.ty, DirectiveType::Ref(..)))
+        .map(|d| d.upvar.clone())
+        .collect();
+    warnings(&refs, "borrowed_by_future", |var| {
+        let mut note = Note::new(format!(
+            "`ref {0}` makes the future borrow `{0}`, so it can't be `'static` as {1}",
+            var, required_by
+        ));
+        note.help = Some(format!(
+            "use `clone {0}` to give the future its own `{0}`, or put it in an `Arc` to share it",
+            var
+        ));
+        note
+    })
+}
+
+/// With `report`, a warning at the flag that lists the directives
+pub fn report(input: &Input, kind: Kind) -> TokenStream {
+    let span = match input.flags.report {
+        Some(span) => span,
+        None => return TokenStream::new(),
+    };
+    let directives: Vec<_> = input
+        .directive_kinds()
+        .into_iter()
+        .map(|(name, kind)| format!("{} {}", kind, name))
+        .collect();
+    let directives = if directives.is_empty() {
+        "no directives".to_string()
+    } else {
+        directives.join(", ")
+    };
+    let others = match kind {
+        Kind::CaptureOnly => "no other variables can be used",
+        _ => "other variables are used as usual",
+    };
+    warnings(&[Ident::new("report", span)], "capture_report", |_| {
+        Note::new(format!("`{}` with {}; {}", kind.name(), directives, others))
+    })
+}
+
+/// Warnings for the closure parameters that shadow a variable with a directive, which then can't
+/// be used in the closure
+pub fn shadowed(input: &Input) -> TokenStream {
+    let closure = match &input.target {
+        Target::Closure(c) => c,
+        _ => return TokenStream::new(),
+    };
+    let mut params = Params(Vec::new());
+    for pat in &closure.inputs {
+        params.visit_pat(pat);
+    }
+    let directives: Vec<_> = input
+        .assigned
+        .iter()
+        .map(|d| (&d.upvar, d.kind()))
+        .chain(input.all.iter().map(|d| (&d.upvar, "all".to_string())))
+        .collect();
+    params
+        .0
+        .retain(|p| directives.iter().any(|(var, _)| var.unraw() == p.unraw()));
+    warnings(&params.0, "shadowed_directive", |param| {
+        let (var, kind) = directives
+            .iter()
+            .find(|(var, _)| var.unraw() == param.unraw())
+            .unwrap();
+        let mut note = Note::new(format!(
+            "the parameter `{0}` shadows the variable of `{1} {0}`, which can't be used in the \
+             closure",
+            param, kind
+        ));
+        note.related = Some((
+            var.span(),
+            format!("`{}` has a directive here, but a parameter shadows it", var),
+        ));
+        note
+    })
+}
+
+/// Collects the names bound by the closure's parameters
+struct Params(Vec<Ident>);
+
+impl<'ast> Visit<'ast> for Params {
+    fn visit_pat_ident(&mut self, node: &'ast syn::PatIdent) {
+        visit::visit_pat_ident(self, node);
+        self.0.push(node.ident.clone());
+    }
+}
+
+/// Warnings for the variables that seem to be captured without a directive
+pub fn undeclared(input: &Input) -> TokenStream {
+    let vars = free_vars(input);
+    warnings(&vars.found, "undeclared_capture", |var| {
+        Note::new(format!("`{}` is captured without a directive", var))
+    })
+}
+
+/// The macros that are passed no variables, whose tokens therefore don't need to be checked
+const TOKEN_MACROS: &[&str] = &[
+    "cfg",
+    "column",
+    "concat",
+    "env",
+    "file",
+    "include",
+    "include_bytes",
+    "include_str",
+    "line",
+    "module_path",
+    "option_env",
+    "stringify",
+];
+
+/// Errors for the macros in the body of a `capture_only!` that a `deny` flag names, and with
+/// `strict_macros`, for those whose arguments aren't known to be expressions, and that could
+/// therefore use variables without a directive.
+///
+/// This crate's own macros are trusted, since they only refer to variables that they are given,
+/// as are those named by a `trust` flag.
+pub fn unverified_macros(input: &Input) -> TokenStream {
+    let mut macros = Macros {
+        errors: Vec::new(),
+        strict: input.flags.strict_macros.is_some(),
+        rules: &input.flags.macros,
+    };
+    if !macros.strict && macros.rules.iter().all(|rule| rule.trust) {
+        return TokenStream::new();
+    }
+    match &input.target {
+        Target::Closure(c) => macros.visit_expr(&c.body),
+        Target::Async(a) => macros.visit_block(&a.block),
+        Target::Gen(g) => macros.visit_block(&g.block),
+        Target::Block(_) | Target::Macro(_) => (),
+    }
+    macros
+        .errors
+        .into_iter()
+        .map(Error::into_compile_error)
+        .collect()
+}
+
+/// Visits a `let ... else` statement, which syn doesn't parse, in the order that its parts come
+/// into scope
+fn visit_let_else<V: for<'a> Visit<'a>>(v: &mut V, local: &LetElse) {
+    v.visit_expr(&local.init);
+    v.visit_block(&local.diverge);
+    v.visit_pat(&local.pat);
+}
+
+/// Whether an expression statement has a `cfg` attribute, which syn keeps in the expression.
+///
+/// syn puts the attributes of the statement on the leftmost operand of an assignment or binary
+/// operator. Printing the statement to look at its first tokens would be simpler, but takes time
+/// proportional to the size of the statement, for every statement of every nested block.
+fn starts_with_cfg(expr: &Expr) -> bool {
+    match expr {
+        Expr::Assign(syn::ExprAssign { attrs, left, .. })
+        | Expr::AssignOp(syn::ExprAssignOp { attrs, left, .. })
+        | Expr::Binary(syn::ExprBinary { attrs, left, .. }) => {
+            has_cfg(attrs) || starts_with_cfg(left)
+        }
+        Expr::Box(syn::ExprBox { attrs, .. })
+        | Expr::Array(syn::ExprArray { attrs, .. })
+        | Expr::Call(syn::ExprCall { attrs, .. })
+        | Expr::MethodCall(syn::ExprMethodCall { attrs, .. })
+        | Expr::Tuple(syn::ExprTuple { attrs, .. })
+        | Expr::Unary(syn::ExprUnary { attrs, .. })
+        | Expr::Lit(syn::ExprLit { attrs, .. })
+        | Expr::Cast(syn::ExprCast { attrs, .. })
+        | Expr::Type(syn::ExprType { attrs, .. })
+        | Expr::Let(syn::ExprLet { attrs, .. })
+        | Expr::If(syn::ExprIf { attrs, .. })
+        | Expr::While(syn::ExprWhile { attrs, .. })
+        | Expr::ForLoop(syn::ExprForLoop { attrs, .. })
+        | Expr::Loop(syn::ExprLoop { attrs, .. })
+        | Expr::Match(syn::ExprMatch { attrs, .. })
+        | Expr::Closure(syn::ExprClosure { attrs, .. })
+        | Expr::Unsafe(syn::ExprUnsafe { attrs, .. })
+        | Expr::Block(syn::ExprBlock { attrs, .. })
+        | Expr::Field(syn::ExprField { attrs, .. })
+        | Expr::Index(syn::ExprIndex { attrs, .. })
+        | Expr::Range(syn::ExprRange { attrs, .. })
+        | Expr::Path(syn::ExprPath { attrs, .. })
+        | Expr::Reference(syn::ExprReference { attrs, .. })
+        | Expr::Break(syn::ExprBreak { attrs, .. })
+        | Expr::Continue(syn::ExprContinue { attrs, .. })
+        | Expr::Return(syn::ExprReturn { attrs, .. })
+        | Expr::Macro(syn::ExprMacro { attrs, .. })
+        | Expr::Struct(syn::ExprStruct { attrs, .. })
+        | Expr::Repeat(syn::ExprRepeat { attrs, .. })
+        | Expr::Paren(syn::ExprParen { attrs, .. })
+        | Expr::Group(syn::ExprGroup { attrs, .. })
+        | Expr::Try(syn::ExprTry { attrs, .. })
+        | Expr::Async(syn::ExprAsync { attrs, .. })
+        | Expr::Await(syn::ExprAwait { attrs, .. })
+        | Expr::TryBlock(syn::ExprTryBlock { attrs, .. })
+        | Expr::Yield(syn::ExprYield { attrs, .. }) => has_cfg(attrs),
+        // What syn can't parse keeps its attributes in the tokens
+        Expr::Verbatim(tokens) => {
+            let attrs = |input: ParseStream| {
+                let attrs = input.call(Attribute::parse_outer)?;
+                input.parse::<TokenStream>()?;
+                Ok(attrs)
+            };
+            attrs
+                .parse2(tokens.clone())
+                .map_or(false, |attrs| has_cfg(&attrs))
+        }
+        _ => false,
+    }
+}
+
+/// Collects the errors for the macros that [`unverified_macros`] reports
+struct Macros<'a> {
+    errors: Vec<Error>,
+    strict: bool,
+    rules: &'a [MacroRule],
+}
+
+impl<'ast> Visit<'ast> for Macros<'_> {
+    fn visit_macro(&mut self, node: &'ast syn::Macro) {
+        match self.rules.iter().find(|rule| rule.matches(&node.path)) {
+            Some(rule) if rule.trust => return,
+            Some(rule) => {
+                self.errors.push(Error::new_spanned(
+                    &node.path,
+                    format!(
+                        "`{0}` can't be used in this closure, since `deny {0}` was given",
+                        rule.name()
+                    ),
+                ));
+                return;
+            }
+            None => (),
+        }
+        if let Some(args) = StdMacroArgs::parse(node) {
+            let (exprs, matched) = args.parts();
+            for expr in exprs {
+                self.visit_expr(expr);
+            }
+            if let Some((pat, guard)) = matched {
+                self.visit_pat(pat);
+                if let Some(guard) = guard {
+                    self.visit_expr(guard);
+                }
+            }
+            return;
+        }
+        let name = node.path.segments.last().unwrap().ident.to_string();
+        if self.strict && Kind::from_name(&name).is_none() && !TOKEN_MACROS.contains(&&*name) {
+            self.errors.push(Error::new_spanned(
+                &node.path,
+                format!(
+                    "the arguments of `{}!` can't be checked by `capture_only!`, which \
+                     `strict_macros` requires; call it outside of the closure",
+                    name
+                ),
+            ));
+        }
+    }
+
+    fn visit_stmt(&mut self, node: &'ast syn::Stmt) {
+        if let syn::Stmt::Semi(expr, _) = node {
+            if let Some(local) = LetElse::parse(expr) {
+                return visit_let_else(self, &local);
+            }
+        }
+        visit::visit_stmt(self, node);
+    }
+
+    // Like in `FreeVars`, items can't use the variables of the closure
+    fn visit_item(&mut self, node: &'ast syn::Item) {
+        if let syn::Item::Macro(syn::ItemMacro { ident: None, .. }) = node {
+            visit::visit_item(self, node);
+        }
+    }
+}
+
+/// A warning for the variables that seem to be used in a `capture_only!` closure without a
+/// directive, which is an error that the compiler reports as not finding the variable.
+///
+/// Several variables are listed in a single warning at the first one, with the directives to add
+/// for all of them, instead of one warning next to each of the compiler's errors.
+///
+/// Names that are only passed as arguments are skipped, since `map(parse)` is more likely to pass
+/// a function than a variable, and this warning would then be the only diagnostic.
+pub fn blocked(input: &Input) -> TokenStream {
+    let vars = free_vars(input);
+    let blocked: Vec<_> = vars
+        .found
+        .into_iter()
+        .filter(|var| !vars.arguments.contains(&unraw_name(var)))
+        .collect();
+    let (first, rest) = match blocked.split_first() {
+        Some(split) => split,
+        None => return TokenStream::new(),
+    };
+    warnings(std::slice::from_ref(first), "not_captured", |var| {
+        if rest.is_empty() {
+            let mut note = Note::new(format!("`{}` is not captured by `capture_only!`", var));
+            note.help = Some(format!(
+                "give it a directive, like `all {}`, to use it in the closure",
+                var
+            ));
+            return note;
+        }
+        let names: Vec<_> = blocked.iter().map(|var| format!("`{}`", var)).collect();
+        let directives: Vec<_> = blocked.iter().map(|var| format!("all {}", var)).collect();
+        let mut note = Note::new(format!(
+            "{} and {} are not captured by `capture_only!`",
+            names[..names.len() - 1].join(", "),
+            names[names.len() - 1]
+        ));
+        note.help = Some(format!(
+            "give them directives, like `{}`, to use them in the closure",
+            directives.join(", ")
+        ));
+        note
+    })
+}
+
+fn free_vars(input: &Input) -> FreeVars<'_> {
+    let declared = input
+        .assigned
+        .iter()
+        .map(|d| unraw_name(&d.upvar))
+        .chain(input.all.iter().map(|d| unraw_name(&d.upvar)))
+        .collect();
+    let mut vars = FreeVars {
+        bound: declared,
+        found: Vec::new(),
+        arguments: Vec::new(),
+        rules: &input.flags.macros,
+    };
+    match &input.target {
+        Target::Closure(c) => vars.visit_expr_closure(c),
+        Target::Async(a) => vars.visit_block(&a.block),
+        Target::Gen(g) => vars.visit_block(&g.block),
+        Target::Block(_) | Target::Macro(_) => (),
+    }
+    vars
+}
+
+/// What a warning about a variable says
+struct Note {
+    message: String,
+    /// A suggestion for fixing it
+    help: Option<String>,
+    /// Another place that the warning is about, with what to say about it
+    related: Option<(Span, String)>,
+}
+
+impl Note {
+    fn new(message: String) -> Self {
+        Note {
+            message,
+            help: None,
+            related: None,
+        }
+    }
+}
+
+/// A deprecation warning for each of the variables, spanned at the variable. The suggestion is
+/// appended to the message, and the related place gets a warning of its own.
+#[cfg(not(feature = "nightly-diagnostics"))]
+fn warnings(vars: &[Ident], name: &str, note: impl Fn(&Ident) -> Note) -> TokenStream {
+    let f = Ident::new(name, Span::mixed_site());
+    let warning = |span: Span, note: String| {
+        let call = Ident::new(name, span.resolved_at(Span::mixed_site()));
+        quote_spanned! {span=>
+            {
+                #[deprecated(note = #note)]
+                fn #f() {}
+                #call();
+            }
+        }
+    };
+    let mut out = TokenStream::new();
+    for var in vars {
+        let note = note(var);
+        out.extend(warning(
+            var.span(),
+            match note.help {
+                Some(help) => format!("{}; {}", note.message, help),
+                None => note.message,
+            },
+        ));
+        if let Some((span, related)) = note.related {
+            out.extend(warning(span, related));
+        }
+    }
+    out
+}
+
+/// A compiler warning for each of the variables, with the suggestion as a help message and the
+/// related place as a note
+#[cfg(feature = "nightly-diagnostics")]
+fn warnings(vars: &[Ident], _name: &str, note: impl Fn(&Ident) -> Note) -> TokenStream {
+    use proc_macro::{Diagnostic, Level};
+
+    for var in vars {
+        let note = note(var);
+        let mut warning = Diagnostic::spanned(var.span().unwrap(), Level::Warning, note.message);
+        if let Some(help) = note.help {
+            warning = warning.help(help);
+        }
+        if let Some((span, related)) = note.related {
+            warning = warning.span_note(span.unwrap(), related);
+        }
+        warning.emit();
+    }
+    TokenStream::new()
+}
+
+/// Collects the names used in the closure that aren't bound in it, in order of first use
+struct FreeVars<'a> {
+    /// The names in scope, starting with the directives, without any `r#` so that `r#a` matches `a`
+    bound: Vec<String>,
+    found: Vec<Ident>,
+    /// The names that are passed directly as an argument somewhere
+    arguments: Vec<String>,
+    /// The `trust` and `deny` flags, since trusted macros can use any variable
+    rules: &'a [MacroRule],
+}
+
+impl FreeVars<'_> {
+    fn argument(&mut self, arg: &Expr) {
+        if let Expr::Path(p) = arg {
+            if let Some(ident) = p.path.get_ident() {
+                self.arguments.push(unraw_name(ident));
+            }
+        }
+    }
+
+    fn scoped(&mut self, f: impl FnOnce(&mut Self)) {
+        let len = self.bound.len();
+        f(self);
+        self.bound.truncate(len);
+    }
+}
+
+impl<'ast> Visit<'ast> for FreeVars<'_> {
+    fn visit_pat_ident(&mut self, node: &'ast syn::PatIdent) {
+        visit::visit_pat_ident(self, node);
+        self.bound.push(unraw_name(&node.ident));
+    }
+
+    fn visit_expr_path(&mut self, node: &'ast syn::ExprPath) {
+        let ident = match node.path.get_ident() {
+            Some(ident) if node.qself.is_none() => ident,
+            _ => return visit::visit_expr_path(self, node),
+        };
+        let name = unraw_name(ident);
+        // Names in scripts without case, like `数量`, can be variables as well
+        let variable_like = !name.starts_with(char::is_uppercase);
+        if variable_like
+            && !self.bound.contains(&name)
+            && !self.found.iter().any(|found| unraw_name(found) == name)
+        {
+            self.found.push(ident.clone());
+        }
+    }
+
+    fn visit_expr_call(&mut self, node: &'ast syn::ExprCall) {
+        // A plain name that is called is most likely a function
+        if !matches!(&*node.func, Expr::Path(p) if p.path.get_ident().is_some()) {
+            self.visit_expr(&node.func);
+        }
+        for arg in &node.args {
+            self.argument(arg);
+            self.visit_expr(arg);
+        }
+    }
+
+    fn visit_expr_method_call(&mut self, node: &'ast syn::ExprMethodCall) {
+        for arg in &node.args {
+            self.argument(arg);
+        }
+        visit::visit_expr_method_call(self, node);
+    }
+
+    // The arguments of macros like `println!` are usually expressions
+    fn visit_macro(&mut self, node: &'ast syn::Macro) {
+        if self
+            .rules
+            .iter()
+            .any(|rule| rule.trust && rule.matches(&node.path))
+        {
+            return;
+        }
+        if let Some(args) = StdMacroArgs::parse(node) {
+            let (exprs, matched) = args.parts();
+            for expr in exprs {
+                self.visit_expr(expr);
+            }
+            if let Some((pat, guard)) = matched {
+                self.scoped(|v| {
+                    v.visit_pat(pat);
+                    if let Some(guard) = guard {
+                        v.visit_expr(guard);
+                    }
+                });
+            }
+        } else if let Ok(args) =
+            node.parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated)
+        {
+            for arg in &args {
+                self.visit_expr(arg);
+            }
+        }
+    }
+
+    // Items can't use the locals around them, except for brace-style macro calls like `foo! { a }`
+    fn visit_item(&mut self, node: &'ast syn::Item) {
+        if let syn::Item::Macro(syn::ItemMacro { ident: None, .. }) = node {
+            visit::visit_item(self, node);
+        }
+    }
+
+    // In a let chain, the pattern is in scope in the conditions after it
+    fn visit_expr_let(&mut self, node: &'ast syn::ExprLet) {
+        let mut rest = Vec::new();
+        self.visit_expr(let_chain(&node.expr, &mut rest));
+        self.visit_pat(&node.pat);
+        for cond in rest {
+            self.visit_expr(cond);
+        }
+    }
+
+    // The value is in scope for the rest of the block, but not in its own initializer
+    // Code with a `cfg` attribute may not be compiled, so only the names that it binds are used,
+    // to not report the variables after it that have the same names
+    fn visit_local(&mut self, node: &'ast syn::Local) {
+        match &node.init {
+            Some((_, init)) if !has_cfg(&node.attrs) => self.visit_expr(init),
+            _ => (),
+        }
+        self.visit_pat(&node.pat);
+    }
+
+    // Like `let`, but syn only gives the tokens
+    fn visit_stmt(&mut self, node: &'ast syn::Stmt) {
+        match node {
+            syn::Stmt::Semi(expr, _) => match LetElse::parse(expr) {
+                Some(local) if has_cfg(&local.attrs) => return self.visit_pat(&local.pat),
+                Some(local) => return visit_let_else(self, &local),
+                None if starts_with_cfg(expr) => return,
+                None => (),
+            },
+            syn::Stmt::Expr(expr) if starts_with_cfg(expr) => return,
+            _ => (),
+        }
+        visit::visit_stmt(self, node);
+    }
+
+    // The names imported by `use` items are in scope in the whole block
+    fn visit_block(&mut self, node: &'ast syn::Block) {
+        self.scoped(|v| {
+            for stmt in &node.stmts {
+                if let syn::Stmt::Item(syn::Item::Use(item)) = stmt {
+                    v.visit_use_tree(&item.tree);
+                }
+            }
+            visit::visit_block(v, node);
+        });
+    }
+
+    fn visit_use_tree(&mut self, node: &'ast syn::UseTree) {
+        match node {
+            syn::UseTree::Name(name) => self.bound.push(unraw_name(&name.ident)),
+            syn::UseTree::Rename(rename) => self.bound.push(unraw_name(&rename.rename)),
+            _ => visit::visit_use_tree(self, node),
+        }
+    }
+
+    fn visit_expr_closure(&mut self, node: &'ast syn::ExprClosure) {
+        self.scoped(|v| visit::visit_expr_closure(v, node));
+    }
+
+    fn visit_arm(&mut self, node: &'ast syn::Arm) {
+        self.scoped(|v| visit::visit_arm(v, node));
+    }
+
+    fn visit_expr_for_loop(&mut self, node: &'ast syn::ExprForLoop) {
+        self.visit_expr(&node.expr);
+        self.scoped(|v| {
+            v.visit_pat(&node.pat);
+            v.visit_block(&node.body);
+        });
+    }
+
+    fn visit_expr_if(&mut self, node: &'ast syn::ExprIf) {
+        self.scoped(|v| {
+            v.visit_expr(&node.cond);
+            v.visit_block(&node.then_branch);
+        });
+        if let Some((_, els)) = &node.else_branch {
+            self.visit_expr(els);
+        }
+    }
+
+    fn visit_expr_while(&mut self, node: &'ast syn::ExprWhile) {
+        self.scoped(|v| visit::visit_expr_while(v, node));
+    }
+}
@@ -0,0 +1,80 @@
+//! The literals that syn doesn't know, like the C string `c"..."`.
+//!
+//! syn panics when it parses one of these, so they are replaced by placeholder string literals
+//! before the input is parsed, and put back into the output. The placeholders keep the spans of the
+//! literals, and the literals that are put back take the spans of the placeholders, which the
+//! expansion may have changed.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use proc_macro2::{Group, Literal, TokenStream, TokenTree};
+
+/// The number of the next placeholder, which is unique so that the placeholders of nested calls
+/// to [`hide`] can't be mistaken for each other
+static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+/// The literals that were replaced, along with their placeholders as printed
+#[derive(Default)]
+pub struct Hidden(Vec<(String, Literal)>);
+
+/// Whether syn can parse the literal, which it decides by the first characters
+fn known(repr: &str) -> bool {
+    match repr.as_bytes() {
+        [b'"' | b'r' | b'\'' | b'0'..=b'9' | b'-', ..] => true,
+        [b'b', b'"' | b'r' | b'\'', ..] => true,
+        _ => repr == "true" || repr == "false",
+    }
+}
+
+/// Replaces the literals that syn can't parse with placeholders
+pub fn hide(tokens: TokenStream, hidden: &mut Hidden) -> TokenStream {
+    tokens
+        .into_iter()
+        .map(|tt| match tt {
+            TokenTree::Group(g) => {
+                let mut out = Group::new(g.delimiter(), hide(g.stream(), hidden));
+                out.set_span(g.span());
+                TokenTree::Group(out)
+            }
+            TokenTree::Literal(lit) if !known(&lit.to_string()) => {
+                let n = NEXT.fetch_add(1, Ordering::Relaxed);
+                let mut placeholder = Literal::string(&format!("\0captures literal {}", n));
+                placeholder.set_span(lit.span());
+                hidden.0.push((placeholder.to_string(), lit));
+                TokenTree::Literal(placeholder)
+            }
+            tt => tt,
+        })
+        .collect()
+}
+
+impl Hidden {
+    /// Puts the literals back in place of their placeholders
+    pub fn restore(&self, tokens: TokenStream) -> TokenStream {
+        if self.0.is_empty() {
+            return tokens;
+        }
+        tokens
+            .into_iter()
+            .map(|tt| match tt {
+                TokenTree::Group(g) => {
+                    let mut out = Group::new(g.delimiter(), self.restore(g.stream()));
+                    out.set_span(g.span());
+                    TokenTree::Group(out)
+                }
+                TokenTree::Literal(lit) => {
+                    let repr = lit.to_string();
+                    match self.0.iter().find(|(placeholder, _)| *placeholder == repr) {
+                        Some((_, original)) => {
+                            let mut original = original.clone();
+                            original.set_span(lit.span());
+                            TokenTree::Literal(original)
+                        }
+                        None => TokenTree::Literal(lit),
+                    }
+                }
+                tt => tt,
+            })
+            .collect()
+    }
+}
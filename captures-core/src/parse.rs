@@ -0,0 +1,1889 @@
+use std::collections::HashSet;
+
+use proc_macro2::{Delimiter, Group, Ident, Spacing, Span, TokenStream, TokenTree};
+use quote::{quote, ToTokens};
+use syn::{
+    buffer::Cursor,
+    ext::IdentExt,
+    parse::{discouraged::Speculative, Parse, ParseStream, Parser},
+    punctuated::Punctuated,
+    token, Attribute, Block, Error, Expr, ExprAsync, ExprBlock, ExprClosure, ExprMacro, Lit, Token,
+    TypeParamBound,
+};
+
+use crate::std_macros::inline_args;
+
+/// Represents the entire parsed input to the macro
+pub struct Input {
+    /// The context in `capture_ctx!(ctx => ...)`
+    pub ctx: Option<Box<Expr>>,
+    /// The GIL token in `py_closure!(py => ...)`
+    #[cfg(feature = "pyo3")]
+    pub py: Option<Box<Expr>>,
+    pub assigned: Vec<AssignedDirective>,
+    pub all: Vec<AllDirective>,
+    pub flags: Flags,
+    pub target: Target,
+    /// The bounds in a trailing `; assert Send + 'static`
+    pub bounds: Option<Bounds>,
+    /// The variables with a directive that the target never mentions, which are warned about
+    pub unused: Vec<Ident>,
+    /// The variable and kind of the first directive that needed the `move` that was inserted
+    pub made_move: Option<(Ident, String)>,
+}
+
+/// The macro that is being expanded
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Capture,
+    CaptureOnly,
+    #[cfg(feature = "alloc")]
+    CaptureAsync,
+    CaptureCtx,
+    CaptureLint,
+    Bind,
+    #[cfg(feature = "alloc")]
+    CaptureExtern,
+    #[cfg(feature = "thread")]
+    Spawn,
+    #[cfg(feature = "tokio")]
+    SpawnTask,
+    #[cfg(feature = "tokio")]
+    SpawnBlocking,
+    #[cfg(feature = "async-std")]
+    SpawnAsyncStd,
+    #[cfg(feature = "smol")]
+    SpawnSmol,
+    #[cfg(feature = "wasm-bindgen")]
+    WasmClosure,
+    #[cfg(feature = "wasm-bindgen")]
+    WasmClosureOnce,
+    #[cfg(feature = "pyo3")]
+    PyClosure,
+}
+
+/// All the macros, for looking them up by name
+const KINDS: &[Kind] = &[
+    Kind::Capture,
+    Kind::CaptureOnly,
+    #[cfg(feature = "alloc")]
+    Kind::CaptureAsync,
+    Kind::CaptureCtx,
+    Kind::CaptureLint,
+    Kind::Bind,
+    #[cfg(feature = "alloc")]
+    Kind::CaptureExtern,
+    #[cfg(feature = "thread")]
+    Kind::Spawn,
+    #[cfg(feature = "tokio")]
+    Kind::SpawnTask,
+    #[cfg(feature = "tokio")]
+    Kind::SpawnBlocking,
+    #[cfg(feature = "async-std")]
+    Kind::SpawnAsyncStd,
+    #[cfg(feature = "smol")]
+    Kind::SpawnSmol,
+    #[cfg(feature = "wasm-bindgen")]
+    Kind::WasmClosure,
+    #[cfg(feature = "wasm-bindgen")]
+    Kind::WasmClosureOnce,
+    #[cfg(feature = "pyo3")]
+    Kind::PyClosure,
+];
+
+impl Kind {
+    /// The macro with the given name, written without the `!`
+    pub fn from_name(name: &str) -> Option<Kind> {
+        KINDS
+            .iter()
+            .copied()
+            .find(|k| k.name().trim_end_matches('!') == name)
+    }
+
+    /// The name of the macro, for use in error messages
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::Capture => "capture!",
+            Kind::CaptureOnly => "capture_only!",
+            #[cfg(feature = "alloc")]
+            Kind::CaptureAsync => "capture_async!",
+            Kind::CaptureCtx => "capture_ctx!",
+            Kind::CaptureLint => "capture_lint!",
+            Kind::Bind => "bind!",
+            #[cfg(feature = "alloc")]
+            Kind::CaptureExtern => "capture_extern!",
+            #[cfg(feature = "thread")]
+            Kind::Spawn => "spawn!",
+            #[cfg(feature = "tokio")]
+            Kind::SpawnTask => "spawn_task!",
+            #[cfg(feature = "tokio")]
+            Kind::SpawnBlocking => "spawn_blocking!",
+            #[cfg(feature = "async-std")]
+            Kind::SpawnAsyncStd => "spawn_async_std!",
+            #[cfg(feature = "smol")]
+            Kind::SpawnSmol => "spawn_smol!",
+            #[cfg(feature = "wasm-bindgen")]
+            Kind::WasmClosure => "wasm_closure!",
+            #[cfg(feature = "wasm-bindgen")]
+            Kind::WasmClosureOnce => "wasm_closure_once!",
+            #[cfg(feature = "pyo3")]
+            Kind::PyClosure => "py_closure!",
+        }
+    }
+
+    /// Whether the macro needs to see the arguments of the closure
+    fn needs_arguments(self) -> bool {
+        match self {
+            #[cfg(feature = "alloc")]
+            Kind::CaptureExtern => true,
+            #[cfg(feature = "wasm-bindgen")]
+            Kind::WasmClosure | Kind::WasmClosureOnce => true,
+            _ => false,
+        }
+    }
+
+    /// What the macro is applied to, like "a closure or an async block"
+    fn expects(self) -> &'static str {
+        match self.requires() {
+            Some(TargetKind::Async) => "an async block",
+            Some(TargetKind::Closure) => "a closure",
+            Some(TargetKind::Block) => "a block",
+            Some(TargetKind::Gen | TargetKind::Macro) | None => "a closure or an async block",
+        }
+    }
+
+    /// Whether the macro can only be applied to async blocks or only to closures
+    fn requires(self) -> Option<TargetKind> {
+        match self {
+            Kind::Capture | Kind::CaptureOnly | Kind::CaptureCtx | Kind::CaptureLint => None,
+            #[cfg(feature = "alloc")]
+            Kind::CaptureAsync => Some(TargetKind::Async),
+            Kind::Bind => Some(TargetKind::Block),
+            #[cfg(feature = "alloc")]
+            Kind::CaptureExtern => Some(TargetKind::Closure),
+            #[cfg(feature = "thread")]
+            Kind::Spawn => Some(TargetKind::Closure),
+            #[cfg(feature = "tokio")]
+            Kind::SpawnTask => Some(TargetKind::Async),
+            #[cfg(feature = "tokio")]
+            Kind::SpawnBlocking => Some(TargetKind::Closure),
+            #[cfg(feature = "async-std")]
+            Kind::SpawnAsyncStd => Some(TargetKind::Async),
+            #[cfg(feature = "smol")]
+            Kind::SpawnSmol => Some(TargetKind::Async),
+            #[cfg(feature = "wasm-bindgen")]
+            Kind::WasmClosure | Kind::WasmClosureOnce => Some(TargetKind::Closure),
+            #[cfg(feature = "pyo3")]
+            Kind::PyClosure => Some(TargetKind::Closure),
+        }
+    }
+}
+
+/// Options that apply to the expansion as a whole instead of to a single variable
+#[derive(Default)]
+pub struct Flags {
+    #[cfg(feature = "alloc")]
+    pub boxed: Option<Boxed>,
+    pub name: Option<Name>,
+    /// The span of the `debug` flag
+    pub debug: Option<Span>,
+    /// The span of the `introspect` flag
+    pub introspect: Option<Span>,
+    /// The span of the `explicit_move` flag
+    pub explicit_move: Option<Span>,
+    /// The span of the `move` flag
+    pub make_move: Option<Span>,
+    /// The span of the `deny_unused` flag
+    pub deny_unused: Option<Span>,
+    /// The span of the `explain_move` flag
+    pub explain_move: Option<Span>,
+    /// The span of the `lint_copy` flag
+    pub lint_copy: Option<Span>,
+    /// The span of the `report` flag
+    pub report: Option<Span>,
+    /// The span of the `strict_macros` flag
+    pub strict_macros: Option<Span>,
+    /// The `trust path!` and `deny path!` flags
+    pub macros: Vec<MacroRule>,
+    /// The span of the `instrument` flag
+    #[cfg(feature = "tracing")]
+    pub instrument: Option<Span>,
+    #[cfg(feature = "glib")]
+    pub default_return: Option<DefaultReturn>,
+    #[cfg(feature = "glib")]
+    pub glib: Option<GlibCrate>,
+}
+
+/// The trait bounds that the captures and the result must satisfy
+pub struct Bounds {
+    /// The span of the `assert` keyword
+    pub span: Span,
+    pub bounds: Punctuated<TypeParamBound, Token![+]>,
+}
+
+/// The `boxed` flag, optionally with the `dyn Trait` type to coerce to
+#[cfg(feature = "alloc")]
+pub struct Boxed {
+    pub span: Span,
+    pub ty: Option<syn::TypeTraitObject>,
+}
+
+/// The `name = expr` flag, which names the spawned thread
+pub struct Name {
+    pub span: Span,
+    #[cfg_attr(not(feature = "thread"), allow(dead_code))]
+    pub expr: Box<Expr>,
+}
+
+/// The `trust path!` or `deny path!` flag, which changes how `capture_only!` treats the macro
+pub struct MacroRule {
+    /// The span of the `trust` or `deny` keyword
+    pub span: Span,
+    /// Whether the macro is trusted instead of denied
+    pub trust: bool,
+    pub path: syn::Path,
+}
+
+impl MacroRule {
+    /// Whether the macro called as `path!` is the one that the rule is for.
+    ///
+    /// Paths match if one ends with the other, so that the rule also applies to a macro that was
+    /// imported, or that is called with a longer path than the rule gives.
+    pub fn matches(&self, path: &syn::Path) -> bool {
+        let rule = self.path.segments.iter().rev();
+        let called = path.segments.iter().rev();
+        rule.zip(called)
+            .all(|(a, b)| a.ident.unraw() == b.ident.unraw())
+    }
+
+    /// The name of the macro, for use in error messages
+    pub fn name(&self) -> String {
+        let segments: Vec<_> = self
+            .path
+            .segments
+            .iter()
+            .map(|s| s.ident.to_string())
+            .collect();
+        format!("{}!", segments.join("::"))
+    }
+
+    fn keyword(&self) -> &'static str {
+        if self.trust {
+            "trust"
+        } else {
+            "deny"
+        }
+    }
+}
+
+/// The `default_return = expr` flag, giving the value to return when upgrading a `weak` directive
+/// fails
+#[cfg(feature = "glib")]
+pub struct DefaultReturn {
+    pub span: Span,
+    pub expr: Box<Expr>,
+}
+
+/// The `glib = path` flag, giving the path of the `glib` crate, like `gtk::glib`
+#[cfg(feature = "glib")]
+pub struct GlibCrate {
+    pub span: Span,
+    pub path: syn::Path,
+}
+
+/// The expression that the directives are applied to
+pub enum Target {
+    Closure(ExprClosure),
+    Async(ExprAsync),
+    /// A `gen` block, which creates an iterator
+    Gen(GenBlock),
+    /// The block in `bind!`
+    Block(ExprBlock),
+    /// A macro invocation that expands to the closure or async block
+    Macro(ExprMacro),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum TargetKind {
+    Closure,
+    Async,
+    Gen,
+    Block,
+    Macro,
+}
+
+/// A `gen` block, like `gen move { ... }` or `async gen { ... }`, which syn doesn't know
+pub struct GenBlock {
+    pub attrs: Vec<Attribute>,
+    pub asyncness: Option<Token![async]>,
+    pub gen_token: Ident,
+    pub capture: Option<Token![move]>,
+    pub block: Block,
+}
+
+impl Parse for GenBlock {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        Ok(GenBlock {
+            attrs: Vec::new(),
+            asyncness: input.parse()?,
+            gen_token: input.parse()?,
+            capture: input.parse()?,
+            block: input.parse()?,
+        })
+    }
+}
+
+/// Whether the input starts with a `gen` block
+fn starts_gen(input: ParseStream) -> bool {
+    let fork = input.fork();
+    let _ = fork.parse::<Option<Token![async]>>();
+    matches!(fork.parse::<Ident>(), Ok(gen) if gen == "gen")
+        && (fork.peek(token::Brace) || (fork.peek(Token![move]) && fork.peek2(token::Brace)))
+}
+
+/// Whether the tokens use a field of `id`, like `id.a` or `id.0`, which a closure then captures
+/// instead of all of `id`
+pub fn mentions_field(tokens: TokenStream, id: &Ident) -> bool {
+    let tokens: Vec<_> = tokens.into_iter().collect();
+    tokens.iter().enumerate().any(|(i, tt)| match tt {
+        TokenTree::Group(g) => mentions_field(g.stream(), id),
+        TokenTree::Ident(var) if var.unraw() == id.unraw() => match &tokens[i + 1..] {
+            [TokenTree::Punct(dot), TokenTree::Literal(_), ..] => dot.as_char() == '.',
+            [TokenTree::Punct(dot), TokenTree::Ident(field), rest @ ..] => {
+                // Not a method call, like `id.len()` or `id.get::<T>()`, or an `.await`
+                let call = match rest.first() {
+                    Some(TokenTree::Group(g)) => g.delimiter() == Delimiter::Parenthesis,
+                    Some(TokenTree::Punct(p)) => p.as_char() == ':',
+                    _ => false,
+                };
+                dot.as_char() == '.' && !call && field != "await"
+            }
+            _ => false,
+        },
+        _ => false,
+    })
+}
+
+/// The name of the ident without any `r#`, so that `r#a` and `a` give the same name.
+///
+/// Inside the compiler, comparing or hashing an `Ident` converts it to a string every time, so
+/// code that compares many idents, like the scopes of a closure body, compares their names.
+pub fn unraw_name(ident: &Ident) -> String {
+    let name = ident.to_string();
+    match name.strip_prefix("r#") {
+        Some(unraw) => unraw.to_string(),
+        None => name,
+    }
+}
+
+/// Whether the tokens use the name `id`
+pub fn mentions(tokens: TokenStream, id: &Ident) -> bool {
+    mentions_name(tokens, &unraw_name(id))
+}
+
+/// Adds the names that the tokens use to `names`, the same ones that [`mentions`] finds
+fn add_names(tokens: TokenStream, names: &mut HashSet<String>) {
+    for tt in tokens {
+        match tt {
+            TokenTree::Ident(i) => {
+                names.insert(unraw_name(&i));
+            }
+            TokenTree::Group(g) => add_names(g.stream(), names),
+            TokenTree::Literal(l) if l.to_string().starts_with(&['"', 'r'][..]) => {
+                if let Lit::Str(s) = Lit::new(l) {
+                    names.extend(inline_args(&s.value()));
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+fn mentions_name(tokens: TokenStream, id: &str) -> bool {
+    tokens.into_iter().any(|tt| match tt {
+        TokenTree::Ident(i) => unraw_name(&i) == id,
+        TokenTree::Group(g) => mentions_name(g.stream(), id),
+        // Format strings can use the variable inline, as in `"{x}"`. syn panics on the kinds of
+        // literals that it doesn't know, like `c"..."`, so only strings are parsed.
+        TokenTree::Literal(l) if l.to_string().starts_with(&['"', 'r'][..]) => match Lit::new(l) {
+            Lit::Str(s) => inline_args(&s.value()).iter().any(|name| id == name),
+            _ => false,
+        },
+        _ => false,
+    })
+}
+
+impl Target {
+    fn kind(&self) -> TargetKind {
+        match self {
+            Target::Closure(_) => TargetKind::Closure,
+            Target::Async(_) => TargetKind::Async,
+            Target::Gen(_) => TargetKind::Gen,
+            Target::Block(_) => TargetKind::Block,
+            Target::Macro(_) => TargetKind::Macro,
+        }
+    }
+
+    /// Whether the body refers to something named `id`.
+    ///
+    /// This only looks at the tokens, so it also finds fields, methods, and shadowed variables of
+    /// the same name.
+    pub fn mentions(&self, id: &Ident) -> bool {
+        mentions(self.body(), id)
+    }
+
+    /// The names that [`Target::mentions`] finds, for checking many variables against one body
+    fn names(&self) -> HashSet<String> {
+        let mut names = HashSet::new();
+        add_names(self.body(), &mut names);
+        names
+    }
+
+    fn body(&self) -> TokenStream {
+        match self {
+            Target::Closure(c) => c.body.to_token_stream(),
+            Target::Async(a) => a.block.to_token_stream(),
+            Target::Gen(g) => g.block.to_token_stream(),
+            Target::Block(b) => b.block.to_token_stream(),
+            Target::Macro(m) => m.mac.tokens.clone(),
+        }
+    }
+
+    /// The span of the first token of the target, ignoring any attributes
+    #[cfg_attr(
+        not(any(feature = "tokio", feature = "async-std", feature = "smol")),
+        allow(dead_code)
+    )]
+    pub fn span(&self) -> Span {
+        match self {
+            Target::Closure(c) => match (&c.asyncness, &c.movability, &c.capture) {
+                (Some(a), _, _) => a.span,
+                (_, Some(s), _) => s.span,
+                (_, _, Some(m)) => m.span,
+                _ => c.or1_token.span,
+            },
+            Target::Async(a) => a.async_token.span,
+            Target::Gen(g) => g.asyncness.as_ref().map_or(g.gen_token.span(), |a| a.span),
+            Target::Block(b) => b.block.brace_token.span,
+            Target::Macro(m) => m.mac.bang_token.span,
+        }
+    }
+
+    /// The tokens to point at when complaining about the target as a whole
+    fn header(&self) -> TokenStream {
+        match self {
+            Target::Closure(c) => {
+                let (or1, inputs, or2) = (&c.or1_token, &c.inputs, &c.or2_token);
+                quote!(#or1 #inputs #or2)
+            }
+            Target::Async(a) => {
+                let (async_token, capture) = (&a.async_token, &a.capture);
+                quote!(#async_token #capture)
+            }
+            Target::Gen(g) => {
+                let (asyncness, gen_token, capture) = (&g.asyncness, &g.gen_token, &g.capture);
+                quote!(#asyncness #gen_token #capture)
+            }
+            Target::Block(b) => {
+                let mut braces = Group::new(Delimiter::Brace, TokenStream::new());
+                braces.set_span(b.block.brace_token.span);
+                quote!(#braces)
+            }
+            Target::Macro(m) => {
+                let (path, bang) = (&m.mac.path, &m.mac.bang_token);
+                quote!(#path #bang)
+            }
+        }
+    }
+
+    /// The `move` keyword, if the target can have one
+    fn capture_mut(&mut self) -> Option<&mut Option<Token![move]>> {
+        match self {
+            Target::Closure(c) => Some(&mut c.capture),
+            Target::Async(a) => Some(&mut a.capture),
+            Target::Gen(g) => Some(&mut g.capture),
+            Target::Block(_) | Target::Macro(_) => None,
+        }
+    }
+
+    fn attrs_mut(&mut self) -> &mut Vec<Attribute> {
+        match self {
+            Target::Closure(c) => &mut c.attrs,
+            Target::Async(a) => &mut a.attrs,
+            Target::Gen(g) => &mut g.attrs,
+            Target::Block(b) => &mut b.attrs,
+            Target::Macro(m) => &mut m.attrs,
+        }
+    }
+}
+
+impl Parse for Bounds {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        input.parse::<Token![;]>()?;
+        let kw = input.parse::<Ident>()?;
+        if kw != "assert" {
+            return Err(Error::new(kw.span(), "expected `assert`"));
+        }
+        Ok(Bounds {
+            span: kw.span(),
+            bounds: Punctuated::parse_separated_nonempty(input)?,
+        })
+    }
+}
+
+impl Parse for Target {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        Target::parse_with(input, false)
+    }
+}
+
+impl Target {
+    /// Parses the target, keeping the body of a closure as the tokens that were written if
+    /// `verbatim` is set. `capture!` doesn't need to look into the body, which can then use
+    /// syntax that syn doesn't know.
+    fn parse_with(input: ParseStream, verbatim: bool) -> syn::Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
+        let mut target = if input.peek(token::Brace) || input.peek(syn::Lifetime) {
+            Target::Block(input.parse()?)
+        } else if starts_macro(input) {
+            Target::Macro(input.parse()?)
+        } else if input.peek(Token![async])
+            && (input.peek2(token::Brace)
+                || (input.peek2(Token![move]) && input.peek3(token::Brace)))
+        {
+            Target::Async(input.parse()?)
+        } else if starts_gen(input) {
+            Target::Gen(input.parse()?)
+        } else if verbatim {
+            // Anything that isn't a closure with a body gets syn's error
+            let fork = input.fork();
+            match closure_verbatim(&fork) {
+                Ok(closure) => {
+                    input.advance_to(&fork);
+                    Target::Closure(closure)
+                }
+                Err(_) => Target::Closure(input.parse()?),
+            }
+        } else {
+            Target::Closure(input.parse()?)
+        };
+        *target.attrs_mut() = attrs;
+        Ok(target)
+    }
+}
+
+/// Parses a closure, but only gives its parameters and return type to syn, and keeps the body as
+/// the tokens up to the end of the input or a trailing `; assert ...`
+fn closure_verbatim(input: ParseStream) -> syn::Result<ExprClosure> {
+    let movability = input.parse()?;
+    let asyncness = input.parse()?;
+    let capture = input.parse()?;
+    // The parameters can only contain a `|` within a group, so they end at the second one
+    let params = input.step(|cursor| {
+        let mut params = TokenStream::new();
+        let mut pipes = 0;
+        let mut rest = *cursor;
+        while let Some((tt, next)) = rest.token_tree() {
+            let pipe = matches!(&tt, TokenTree::Punct(p) if p.as_char() == '|');
+            if !pipe && pipes == 0 {
+                break;
+            }
+            params.extend(Some(tt));
+            rest = next;
+            pipes += pipe as usize;
+            if pipes == 2 {
+                return Ok((params, rest));
+            }
+        }
+        Err(cursor.error("expected a closure"))
+    })?;
+    let header: ExprClosure = syn::parse2(quote!(#params ()))?;
+    let output = input.parse()?;
+    let body = input.step(|cursor| {
+        let mut body = TokenStream::new();
+        let mut rest = *cursor;
+        while let Some((tt, next)) = rest.token_tree() {
+            match &tt {
+                TokenTree::Punct(p) if p.as_char() == ';' => break,
+                // Left for the error about the tokens after the target, as in `|| a, || b`
+                TokenTree::Punct(p) if p.as_char() == ',' && ends_body(next) => break,
+                _ => (),
+            }
+            body.extend(Some(tt));
+            rest = next;
+        }
+        if body.is_empty() {
+            return Err(cursor.error("expected the body of the closure"));
+        }
+        Ok((body, rest))
+    })?;
+    Ok(ExprClosure {
+        attrs: Vec::new(),
+        asyncness,
+        movability,
+        capture,
+        or1_token: header.or1_token,
+        inputs: header.inputs,
+        or2_token: header.or2_token,
+        output,
+        body: Box::new(Expr::Verbatim(body)),
+    })
+}
+
+/// Whether a `,` that is followed by `cursor` can't be part of the body of a closure, since it
+/// is the last token or followed by what looks like another closure
+fn ends_body(cursor: Cursor) -> bool {
+    match cursor.token_tree() {
+        None => true,
+        Some((TokenTree::Punct(p), _)) => matches!(p.as_char(), '|' | ';'),
+        Some((TokenTree::Ident(i), _)) => i == "move" || i == "async" || i == "static",
+        Some(_) => false,
+    }
+}
+
+enum Directive {
+    All(AllDirective),
+    Assigned(AssignedDirective),
+    Flag(Flag),
+    /// The contents of a directive group, spliced in by the group's macro as `use { ... }`, or of
+    /// brackets around directives
+    Group(Vec<Directive>),
+    /// Directives inserted by `capture_defaults!` as `default { ... }`
+    Defaults(Vec<AssignedDirective>),
+}
+
+enum Flag {
+    #[cfg(feature = "alloc")]
+    Boxed(Boxed),
+    Name(Name),
+    Debug(Span),
+    Introspect(Span),
+    ExplicitMove(Span),
+    Move(Span),
+    DenyUnused(Span),
+    ExplainMove(Span),
+    LintCopy(Span),
+    Report(Span),
+    StrictMacros(Span),
+    Macro(MacroRule),
+    #[cfg(feature = "tracing")]
+    Instrument(Span),
+    #[cfg(feature = "glib")]
+    DefaultReturn(DefaultReturn),
+    #[cfg(feature = "glib")]
+    Glib(GlibCrate),
+}
+
+pub struct AllDirective {
+    pub upvar: Ident,
+}
+
+pub struct AssignedDirective {
+    /// `x` in `clone x`
+    pub upvar: Ident,
+    pub mu: Option<Token![mut]>,
+    pub ty: DirectiveType,
+}
+impl AssignedDirective {
+    /// The directive without its variable, like `clone mut`
+    pub fn kind(&self) -> String {
+        let custom;
+        let (kind, mu) = match &self.ty {
+            DirectiveType::Ref(_, mu) => ("ref", mu.is_some()),
+            DirectiveType::Clone(_) => ("clone", self.mu.is_some()),
+            DirectiveType::With(_) => ("with", self.mu.is_some()),
+            #[cfg(feature = "glib")]
+            DirectiveType::Weak(_) => ("weak", self.mu.is_some()),
+            #[cfg(feature = "pyo3")]
+            DirectiveType::CloneRef(_) => ("clone_ref", self.mu.is_some()),
+            DirectiveType::Custom(keyword, _) => {
+                custom = keyword.to_string();
+                (&*custom, self.mu.is_some())
+            }
+        };
+        if mu {
+            format!("{} mut", kind)
+        } else {
+            kind.to_string()
+        }
+    }
+}
+
+pub enum DirectiveType {
+    Ref(Span, Option<Token![mut]>),
+    Clone(Span),
+    With(Box<Expr>),
+    #[cfg(feature = "glib")]
+    Weak(Span),
+    #[cfg(feature = "pyo3")]
+    CloneRef(Span),
+    /// A [`CustomDirective`](crate::CustomDirective), with its keyword and the value it gave
+    Custom(Ident, Box<Expr>),
+}
+
+impl Flags {
+    fn set(&mut self, flag: Flag) -> syn::Result<()> {
+        match flag {
+            #[cfg(feature = "alloc")]
+            Flag::Boxed(boxed) => set_once(&mut self.boxed, boxed.span, boxed, "boxed"),
+            Flag::Name(name) => set_once(&mut self.name, name.span, name, "name"),
+            Flag::Debug(span) => set_once(&mut self.debug, span, span, "debug"),
+            Flag::Introspect(span) => set_once(&mut self.introspect, span, span, "introspect"),
+            Flag::ExplicitMove(span) => {
+                set_once(&mut self.explicit_move, span, span, "explicit_move")
+            }
+            Flag::Move(span) => set_once(&mut self.make_move, span, span, "move"),
+            Flag::DenyUnused(span) => set_once(&mut self.deny_unused, span, span, "deny_unused"),
+            Flag::ExplainMove(span) => set_once(&mut self.explain_move, span, span, "explain_move"),
+            Flag::LintCopy(span) => set_once(&mut self.lint_copy, span, span, "lint_copy"),
+            Flag::Report(span) => set_once(&mut self.report, span, span, "report"),
+            Flag::StrictMacros(span) => {
+                set_once(&mut self.strict_macros, span, span, "strict_macros")
+            }
+            Flag::Macro(rule) => {
+                if self.macros.iter().any(|r| r.matches(&rule.path)) {
+                    return Err(Error::new(
+                        rule.span,
+                        format!("`{}` may only be trusted or denied once", rule.name()),
+                    ));
+                }
+                self.macros.push(rule);
+                Ok(())
+            }
+            #[cfg(feature = "tracing")]
+            Flag::Instrument(span) => set_once(&mut self.instrument, span, span, "instrument"),
+            #[cfg(feature = "glib")]
+            Flag::DefaultReturn(d) => {
+                set_once(&mut self.default_return, d.span, d, "default_return")
+            }
+            #[cfg(feature = "glib")]
+            Flag::Glib(g) => set_once(&mut self.glib, g.span, g, "glib"),
+        }
+    }
+
+    /// Whether no flag was specified
+    #[cfg(feature = "pedantic")]
+    pub fn is_empty(&self) -> bool {
+        #[cfg(feature = "alloc")]
+        if self.boxed.is_some() {
+            return false;
+        }
+        #[cfg(feature = "tracing")]
+        if self.instrument.is_some() {
+            return false;
+        }
+        #[cfg(feature = "glib")]
+        if self.default_return.is_some() || self.glib.is_some() {
+            return false;
+        }
+        self.name.is_none()
+            && self.debug.is_none()
+            && self.introspect.is_none()
+            && self.explicit_move.is_none()
+            && self.make_move.is_none()
+            && self.deny_unused.is_none()
+            && self.explain_move.is_none()
+            && self.lint_copy.is_none()
+            && self.report.is_none()
+            && self.strict_macros.is_none()
+            && self.macros.is_empty()
+    }
+
+    /// Checks that all the flags that were specified are supported by the macro
+    fn check(&self, kind: Kind, err: &mut Option<Error>) {
+        let mut reject = |span: Span, flag: &str| {
+            combine(
+                err,
+                Error::new(
+                    span,
+                    format!("`{}` cannot be used with `{}`", flag, kind.name()),
+                ),
+            )
+        };
+        // `boxed` and `introspect` wrap the closure, which only these macros return as it is
+        let plain = matches!(
+            kind,
+            Kind::Capture | Kind::CaptureOnly | Kind::CaptureCtx | Kind::CaptureLint
+        );
+        #[cfg(feature = "alloc")]
+        match &self.boxed {
+            Some(b) if !plain => reject(b.span, "boxed"),
+            _ => (),
+        }
+        match self.introspect {
+            Some(span) if !plain => reject(span, "introspect"),
+            _ => (),
+        }
+        match (&self.name, kind) {
+            #[cfg(feature = "thread")]
+            (Some(_), Kind::Spawn) => (),
+            (Some(n), _) => reject(n.span, "name"),
+            _ => (),
+        }
+        // Only `capture_only!` restricts what the body can use
+        match self.strict_macros {
+            Some(span) if kind != Kind::CaptureOnly => reject(span, "strict_macros"),
+            _ => (),
+        }
+        if kind != Kind::CaptureOnly {
+            for rule in &self.macros {
+                reject(rule.span, rule.keyword());
+            }
+        }
+    }
+}
+
+fn set_once<T>(slot: &mut Option<T>, span: Span, val: T, name: &str) -> syn::Result<()> {
+    if slot.is_some() {
+        Err(Error::new(
+            span,
+            format!("`{}` may only be specified once", name),
+        ))
+    } else {
+        *slot = Some(val);
+        Ok(())
+    }
+}
+
+const EXPECTED_MSG: &str = "expected `ref`, `clone`, `with`, or `all`";
+
+impl Parse for Directive {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(Token![use]) {
+            let use_span = input.parse::<Token![use]>().unwrap().span;
+            if !input.peek(token::Brace) {
+                return Err(Error::new(
+                    use_span,
+                    "directive groups can only be used in function-like macros",
+                ));
+            }
+            let content;
+            syn::braced!(content in input);
+            // The directives were written where the group was defined, but must refer to the
+            // variables at the place where the group is used
+            let tokens = respan(content.parse()?, use_span);
+            Ok(Directive::Group(parse_list.parse2(tokens)?))
+        } else if input.peek(token::Bracket) {
+            // The directives set apart from the target, as in `[clone a, ref b], || ...`
+            let content;
+            syn::bracketed!(content in input);
+            // Parsed on their own, since syn replaces the error with its own if the brackets
+            // aren't parsed to the end
+            let tokens = content.parse()?;
+            Ok(Directive::Group(parse_list.parse2(tokens)?))
+        } else if is_move_flag(input) {
+            let move_span = input.parse::<Token![move]>().unwrap().span;
+            Ok(Directive::Flag(Flag::Move(move_span)))
+        } else if input.peek(Token![ref]) {
+            let ref_span = input.parse::<Token![ref]>().unwrap().span;
+            let sec_mu = input.parse::<Option<Token![mut]>>().unwrap();
+            Ok(Directive::Assigned(AssignedDirective {
+                upvar: parse_upvar(input, false)?,
+                mu: None,
+                ty: DirectiveType::Ref(ref_span, sec_mu),
+            }))
+        } else if input.peek(syn::Ident) {
+            let next = input.parse::<Ident>().unwrap();
+            let mu = input.parse::<Option<Token![mut]>>().unwrap();
+            match &*next.to_string() {
+                "clone" => Ok(Directive::Assigned(AssignedDirective {
+                    upvar: parse_upvar(input, false)?,
+                    mu,
+                    ty: DirectiveType::Clone(next.span()),
+                })),
+                "with" => {
+                    let upvar = parse_upvar(input, false)?;
+                    input.parse::<Token![=]>()?;
+                    Ok(Directive::Assigned(AssignedDirective {
+                        upvar,
+                        mu,
+                        ty: DirectiveType::With(Box::new(input.parse::<Expr>()?)),
+                    }))
+                }
+                "default" => {
+                    no_mut(mu, "`default`")?;
+                    let content;
+                    syn::braced!(content in input);
+                    let mut dirs = Vec::new();
+                    while !content.is_empty() {
+                        let span = content.span();
+                        let dir = content.parse()?;
+                        let with = ends_with_expr(&dir);
+                        match dir {
+                            Directive::Assigned(dir) => dirs.push(dir),
+                            _ => {
+                                return Err(Error::new(
+                                    span,
+                                    "only `ref`, `clone`, and `with` directives may be defaults",
+                                ))
+                            }
+                        }
+                        if !content.is_empty() {
+                            parse_separator(&content, with)?;
+                        }
+                    }
+                    Ok(Directive::Defaults(dirs))
+                }
+                "all" => {
+                    no_mut(mu, "`all` directive")?;
+                    Ok(Directive::All(AllDirective {
+                        upvar: parse_upvar(input, true)?,
+                    }))
+                }
+                "debug" => {
+                    no_mut(mu, "`debug`")?;
+                    Ok(Directive::Flag(Flag::Debug(next.span())))
+                }
+                #[cfg(feature = "tracing")]
+                "instrument" => {
+                    no_mut(mu, "`instrument`")?;
+                    Ok(Directive::Flag(Flag::Instrument(next.span())))
+                }
+                "introspect" => {
+                    no_mut(mu, "`introspect`")?;
+                    Ok(Directive::Flag(Flag::Introspect(next.span())))
+                }
+                "explicit_move" => {
+                    no_mut(mu, "`explicit_move`")?;
+                    Ok(Directive::Flag(Flag::ExplicitMove(next.span())))
+                }
+                "deny_unused" => {
+                    no_mut(mu, "`deny_unused`")?;
+                    Ok(Directive::Flag(Flag::DenyUnused(next.span())))
+                }
+                "explain_move" => {
+                    no_mut(mu, "`explain_move`")?;
+                    Ok(Directive::Flag(Flag::ExplainMove(next.span())))
+                }
+                "lint_copy" => {
+                    no_mut(mu, "`lint_copy`")?;
+                    Ok(Directive::Flag(Flag::LintCopy(next.span())))
+                }
+                "report" => {
+                    no_mut(mu, "`report`")?;
+                    Ok(Directive::Flag(Flag::Report(next.span())))
+                }
+                "strict_macros" => {
+                    no_mut(mu, "`strict_macros`")?;
+                    Ok(Directive::Flag(Flag::StrictMacros(next.span())))
+                }
+                "trust" | "deny" => {
+                    no_mut(mu, &format!("`{}`", next))?;
+                    let path = input.call(syn::Path::parse_mod_style)?;
+                    input.parse::<Token![!]>()?;
+                    Ok(Directive::Flag(Flag::Macro(MacroRule {
+                        span: next.span(),
+                        trust: next == "trust",
+                        path,
+                    })))
+                }
+                #[cfg(feature = "alloc")]
+                "boxed" => {
+                    no_mut(mu, "`boxed`")?;
+                    let ty = if input.peek(Token![dyn]) {
+                        Some(input.parse()?)
+                    } else {
+                        None
+                    };
+                    Ok(Directive::Flag(Flag::Boxed(Boxed {
+                        span: next.span(),
+                        ty,
+                    })))
+                }
+                #[cfg(feature = "glib")]
+                "strong" => Ok(Directive::Assigned(AssignedDirective {
+                    upvar: parse_upvar(input, false)?,
+                    mu,
+                    ty: DirectiveType::Clone(next.span()),
+                })),
+                #[cfg(feature = "glib")]
+                "weak" => Ok(Directive::Assigned(AssignedDirective {
+                    upvar: parse_upvar(input, false)?,
+                    mu,
+                    ty: DirectiveType::Weak(next.span()),
+                })),
+                #[cfg(feature = "pyo3")]
+                "clone_ref" => Ok(Directive::Assigned(AssignedDirective {
+                    upvar: parse_upvar(input, false)?,
+                    mu,
+                    ty: DirectiveType::CloneRef(next.span()),
+                })),
+                #[cfg(feature = "glib")]
+                "default_return" => {
+                    no_mut(mu, "`default_return`")?;
+                    input.parse::<Token![=]>()?;
+                    Ok(Directive::Flag(Flag::DefaultReturn(DefaultReturn {
+                        span: next.span(),
+                        expr: input.parse()?,
+                    })))
+                }
+                #[cfg(feature = "glib")]
+                "glib" => {
+                    no_mut(mu, "`glib`")?;
+                    input.parse::<Token![=]>()?;
+                    Ok(Directive::Flag(Flag::Glib(GlibCrate {
+                        span: next.span(),
+                        path: input.call(syn::Path::parse_mod_style)?,
+                    })))
+                }
+                "name" => {
+                    no_mut(mu, "`name`")?;
+                    input.parse::<Token![=]>()?;
+                    Ok(Directive::Flag(Flag::Name(Name {
+                        span: next.span(),
+                        expr: input.parse()?,
+                    })))
+                }
+                keyword => match crate::custom::find(keyword) {
+                    Some(custom) => {
+                        let upvar = parse_upvar(input, false)?;
+                        let value = syn::parse2(custom.value(&upvar)).map_err(|e| {
+                            let msg =
+                                format!("the value of `{} {}` is invalid: {}", next, upvar, e);
+                            Error::new(next.span(), msg)
+                        })?;
+                        Ok(Directive::Assigned(AssignedDirective {
+                            upvar,
+                            mu,
+                            ty: DirectiveType::Custom(next, Box::new(value)),
+                        }))
+                    }
+                    None => Err(syn::Error::new(next.span(), EXPECTED_MSG)),
+                },
+            }
+        } else {
+            Err(input.error(EXPECTED_MSG))
+        }
+    }
+}
+
+/// Whether the input starts with the `move` flag, which is `move` on its own rather than the start
+/// of a target, like `move ||`
+fn is_move_flag(input: ParseStream) -> bool {
+    let fork = input.fork();
+    fork.parse::<Token![move]>().is_ok()
+        && (fork.is_empty() || fork.peek(Token![,]) || fork.peek(Token![=>]))
+}
+
+/// Parses directives separated by commas, as in a directive group or brackets
+fn parse_list(input: ParseStream) -> syn::Result<Vec<Directive>> {
+    let mut dirs = Vec::new();
+    while !input.is_empty() {
+        let dir = input.parse()?;
+        let with = ends_with_expr(&dir);
+        dirs.push(dir);
+        if !input.is_empty() {
+            parse_separator(input, with)?;
+        }
+    }
+    Ok(dirs)
+}
+
+/// Whether the directive ends with an expression, as a `with` directive does. Such a directive
+/// may be ended by a `;` instead of a comma, which sets a long expression apart from what follows.
+fn ends_with_expr(dir: &Directive) -> bool {
+    matches!(
+        dir,
+        Directive::Assigned(AssignedDirective {
+            ty: DirectiveType::With(_),
+            ..
+        })
+    )
+}
+
+/// Parses the `,` after a directive, or the `;` after one that ends with an expression
+fn parse_separator(input: ParseStream, with: bool) -> syn::Result<()> {
+    if !(with && input.parse::<Option<Token![;]>>()?.is_some()) {
+        input.parse::<Token![,]>()?;
+    }
+    Ok(())
+}
+
+/// Parses the variable that a directive is about. `self` can't be rebound by a `let`, so only the
+/// directives that use the variable in place, like `all`, accept it.
+fn parse_upvar(input: ParseStream, allow_self: bool) -> syn::Result<Ident> {
+    if !input.peek(Token![self]) {
+        return input.parse();
+    }
+    let upvar = input.call(Ident::parse_any)?;
+    if allow_self {
+        Ok(upvar)
+    } else {
+        Err(Error::new(
+            upvar.span(),
+            "`self` can't be rebound, so only `all self` may be used with it",
+        ))
+    }
+}
+
+fn no_mut(mu: Option<Token![mut]>, what: &str) -> syn::Result<()> {
+    match mu {
+        Some(mu) => Err(syn::Error::new(
+            mu.span,
+            format!("may not use mutability specifier with {}", what),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Consumes token trees in the input up to and including the next comma that is followed by what
+/// looks like another directive or the target, or up to the `=>` before the target.
+///
+/// Commas inside of delimiters are already skipped as part of their group, but a broken `with`
+/// directive can also contain commas between closure parameters, as in `|a, b|`, or generic
+/// arguments, as in `HashMap<K, V>`, which aren't followed by anything that starts a directive.
+///
+/// Returns whether such a comma or `=>` was found.
+fn skip_past_comma(input: ParseStream) -> bool {
+    input
+        .step(|cursor| {
+            let mut rest = *cursor;
+            while let Some((tt, next)) = rest.token_tree() {
+                match tt {
+                    TokenTree::Punct(p) if p.as_char() == ',' && resumes_at(next) => {
+                        return Ok((true, next))
+                    }
+                    TokenTree::Punct(p) if p.as_char() == '=' && p.spacing() == Spacing::Joint => {
+                        if let Some((TokenTree::Punct(gt), _)) = next.token_tree() {
+                            if gt.as_char() == '>' {
+                                return Ok((true, rest));
+                            }
+                        }
+                    }
+                    _ => (),
+                }
+                rest = next;
+            }
+            Ok((false, rest))
+        })
+        .unwrap()
+}
+
+/// Whether the input looks like the target rather than another directive
+fn starts_target(input: ParseStream) -> bool {
+    input.peek(Token![#])
+        || input.peek(token::Brace)
+        || input.peek(syn::Lifetime)
+        || starts_macro(input)
+        || input.peek(Token![async])
+        || starts_gen(input)
+        || input.peek(Token![static])
+        || input.peek(Token![|])
+        || (input.peek(Token![move]) && input.peek2(Token![|]))
+}
+
+/// Whether the tokens after a comma can start a directive or the target
+fn resumes_at(cursor: Cursor) -> bool {
+    let (ident, next) = match cursor.ident() {
+        Some(ident) => ident,
+        // A closure, an attribute on it, a block, a label, directives in brackets, or the end of
+        // the input
+        None => {
+            return match cursor.token_tree() {
+                Some((TokenTree::Punct(p), _)) => matches!(p.as_char(), '|' | '#' | '\''),
+                Some((TokenTree::Group(g), _)) => {
+                    matches!(g.delimiter(), Delimiter::Brace | Delimiter::Bracket)
+                }
+                Some(_) => false,
+                None => true,
+            }
+        }
+    };
+    match next.token_tree() {
+        // The rest of a closure parameter, like `b|` or `b: i32`, or of generic arguments
+        Some((TokenTree::Punct(p), _)) => match p.as_char() {
+            '|' => ident == "move" || ident == "async" || ident == "static",
+            ':' => p.spacing() == Spacing::Joint,
+            '>' | '.' => false,
+            _ => true,
+        },
+        // `use { ... }` and `default { ... }`, but not a pattern like `Some(b)`
+        Some((TokenTree::Group(g), _)) => g.delimiter() == Delimiter::Brace,
+        // `clone a`, `ref mut a`, `move ||`, `async {`, or the end of the input
+        _ => true,
+    }
+}
+
+/// Makes all the tokens resolve at `span`, while keeping their location for error messages
+fn respan(tokens: TokenStream, span: Span) -> TokenStream {
+    tokens
+        .into_iter()
+        .map(|mut tt| {
+            if let TokenTree::Group(g) = &tt {
+                let mut new = Group::new(g.delimiter(), respan(g.stream(), span));
+                new.set_span(g.span());
+                tt = TokenTree::Group(new);
+            }
+            tt.set_span(tt.span().resolved_at(span));
+            tt
+        })
+        .collect()
+}
+
+/// Whether the input starts with a macro invocation like `a!` or `a::b!`
+fn starts_macro(input: ParseStream) -> bool {
+    let fork = input.fork();
+    fork.call(syn::Path::parse_mod_style).is_ok() && fork.peek(Token![!])
+}
+
+fn combine(opt: &mut Option<Error>, e: Error) {
+    match opt {
+        Some(err) => err.combine(e),
+        None => *opt = Some(e),
+    }
+}
+
+/// The list of directives and flags, before the target has been parsed
+#[derive(Default)]
+pub struct Directives {
+    assigned: Vec<AssignedDirective>,
+    all: Vec<AllDirective>,
+    flags: Flags,
+    defaults: Vec<AssignedDirective>,
+    needs_move: bool,
+    /// The last argument, if it isn't a valid directive, since it is probably the target instead
+    last_invalid: Option<(Span, Error)>,
+}
+
+impl Directives {
+    /// Parses directives until the input ends or we reach something that looks like the target.
+    ///
+    /// Errors are stored in `err` instead of being returned, so that we can continue parsing to
+    /// emit as many errors as possible.
+    pub fn parse(input: ParseStream, err: &mut Option<Error>) -> Self {
+        let mut directives = Directives::parse_before_target(input, err);
+        if let Some((_, e)) = directives.last_invalid.take() {
+            combine(err, e);
+        }
+        directives
+    }
+
+    /// Like [`Directives::parse`], but if the last argument isn't a valid directive, its error is
+    /// kept in `last_invalid`, since a target is expected after it.
+    fn parse_before_target(input: ParseStream, err: &mut Option<Error>) -> Self {
+        let mut directives = Directives::default();
+        let mut found = Vec::new();
+        // Figure out if we should be parsing a further directive or the closure
+        while !input.is_empty() && !starts_target(input) {
+            let start = input.span();
+            let mut with = false;
+            match input.parse::<Directive>() {
+                Ok(dir) => {
+                    with = ends_with_expr(&dir);
+                    directives.add(dir, &mut found, err);
+                }
+                Err(e) => {
+                    if !skip_past_comma(input) {
+                        directives.last_invalid = Some((start, e));
+                    } else {
+                        combine(err, e);
+                    }
+                    if !input.peek(Token![=>]) {
+                        continue;
+                    }
+                }
+            }
+            // The trailing comma may be omitted if the list ends here, as it does in attributes
+            if input.is_empty() {
+                break;
+            }
+            // A `=>` ends the directives, as in glib's `clone!`
+            if input.peek(Token![=>]) {
+                input.parse::<Token![=>]>().unwrap();
+                break;
+            }
+            if with && input.peek(Token![;]) {
+                input.parse::<Token![;]>().unwrap();
+                continue;
+            }
+            if let Err(e) = input.parse::<Token![,]>() {
+                // Parsing continues either way, so the directive after it still applies
+                let e = if let Some(semi) = input.parse::<Option<Token![;]>>().unwrap() {
+                    Error::new(semi.span, "only a `with` directive may end with `;`")
+                } else if starts_target(input) {
+                    input.error("missing `,` after the directives")
+                } else if input.fork().parse::<Directive>().is_ok() {
+                    input.error("missing `,` between directives")
+                } else {
+                    e
+                };
+                combine(err, e);
+            }
+        }
+        directives
+    }
+
+    /// `found` holds the name of each variable seen so far, along with the span of its directive
+    /// and the index of that in `assigned`, `None` for `all`. There are only a few directives, so
+    /// it is searched by name rather than hashed.
+    fn add(
+        &mut self,
+        dir: Directive,
+        found: &mut Vec<(String, Span, Option<usize>)>,
+        err: &mut Option<Error>,
+    ) {
+        let index = match dir {
+            Directive::All(dir) => {
+                self.all.push(dir);
+                None
+            }
+            Directive::Assigned(dir) => {
+                self.needs_move |= !matches!(&dir.ty, DirectiveType::Ref(..));
+                self.assigned.push(dir);
+                Some(self.assigned.len() - 1)
+            }
+            Directive::Flag(flag) => {
+                if let Err(e) = self.flags.set(flag) {
+                    combine(err, e);
+                }
+                return;
+            }
+            Directive::Group(dirs) => {
+                for dir in dirs {
+                    self.add(dir, found, err);
+                }
+                return;
+            }
+            Directive::Defaults(dirs) => {
+                self.defaults.extend(dirs);
+                return;
+            }
+        };
+        let id = match index {
+            Some(i) => &self.assigned[i].upvar,
+            None => &self.all[self.all.len() - 1].upvar,
+        };
+        // Compared without any `r#`, since `r#a` and `a` are the same variable
+        let name = unraw_name(id);
+        match found.iter().find(|(first, ..)| *first == name) {
+            // `all` keeps the variable itself, which the other directive replaces
+            Some((_, first, first_index)) if first_index.is_none() != index.is_none() => {
+                let assigned = format!(
+                    "{} {}",
+                    self.assigned[first_index.or(index).unwrap()].kind(),
+                    id
+                );
+                combine(
+                    err,
+                    Error::new(
+                        id.span(),
+                        format!(
+                            "`all {0}` can't be combined with `{1}`, which replaces `{0}` with a \
+                             new value",
+                            id, assigned
+                        ),
+                    ),
+                );
+                combine(
+                    err,
+                    Error::new(
+                        *first,
+                        format!(
+                            "the other directive for `{}` is here; keep `{}` to capture the new \
+                             value, or `all {0}` to capture the variable itself",
+                            id, assigned
+                        ),
+                    ),
+                );
+            }
+            Some((_, first, _)) => {
+                combine(
+                    err,
+                    Error::new(
+                        id.span(),
+                        format!("cannot supply multiple directives for `{}`", id),
+                    ),
+                );
+                combine(
+                    err,
+                    Error::new(*first, format!("the first directive for `{}` is here", id)),
+                );
+            }
+            None => found.push((name, id.span(), index)),
+        }
+    }
+}
+
+impl Input {
+    /// Parses the input to the macro of the given kind
+    pub fn parse_for(tokens: TokenStream, kind: Kind) -> syn::Result<Self> {
+        match Input::parse_recovering(tokens, kind)? {
+            (_, Some(err)) => Err(err),
+            (parsed, None) => Ok(parsed),
+        }
+    }
+
+    /// Like [`Input::parse_for`], but if the only errors are syntax errors in the directives, they
+    /// are returned along with the input without the broken directives, so that the target can
+    /// still be expanded.
+    pub fn parse_recovering(tokens: TokenStream, kind: Kind) -> syn::Result<(Self, Option<Error>)> {
+        let parse = || (|input: ParseStream| Input::parse(input, kind)).parse2(tokens);
+        #[cfg(feature = "profile")]
+        let parse = || crate::profile::parsing(parse);
+        parse()
+    }
+
+    /// The names of the variables with a directive, along with the directive, like `clone mut`
+    pub fn directive_kinds(&self) -> Vec<(String, String)> {
+        let assigned = self
+            .assigned
+            .iter()
+            .map(|d| (d.upvar.to_string(), d.kind()));
+        let all = self
+            .all
+            .iter()
+            .map(|d| (d.upvar.to_string(), "all".to_string()));
+        assigned.chain(all).collect()
+    }
+
+    /// Builds the input from the directives given as arguments to an attribute on `target`
+    pub fn from_attribute(tokens: TokenStream, target: Target, kind: Kind) -> syn::Result<Self> {
+        let parse = || {
+            (|input: ParseStream| {
+                let mut err = None;
+                let directives = Directives::parse(input, &mut err);
+                if !input.is_empty() {
+                    combine(&mut err, input.error("expected a directive"));
+                }
+                Input::finish(directives, target, kind, err)
+            })
+            .parse2(tokens)
+        };
+        #[cfg(feature = "profile")]
+        let parse = || crate::profile::parsing(parse);
+        parse()
+    }
+
+    fn parse(input: ParseStream, kind: Kind) -> syn::Result<(Self, Option<Error>)> {
+        // If we encounter an error while parsing, store it here. We'll continue parsing to be able
+        // to emit as many errors as possible.
+        let mut err: Option<syn::Error> = None;
+        // `capture_ctx!` and `py_closure!` start with an expression followed by `=>`
+        #[cfg(feature = "pyo3")]
+        let leading = matches!(kind, Kind::CaptureCtx | Kind::PyClosure);
+        #[cfg(not(feature = "pyo3"))]
+        let leading = kind == Kind::CaptureCtx;
+        let lead = if leading {
+            let lead = input.parse::<Expr>()?;
+            input.parse::<Token![=>]>()?;
+            Some(Box::new(lead))
+        } else {
+            None
+        };
+        let mut directives = Directives::parse_before_target(input, &mut err);
+        if input.is_empty() {
+            let (name, expects) = (kind.name(), kind.expects());
+            let (span, message) = match directives.last_invalid.take() {
+                Some((span, _)) => (
+                    span,
+                    format!("the last argument to `{}` must be {}", name, expects),
+                ),
+                None => (
+                    Span::call_site(),
+                    format!("`{}` needs {} after the directives", name, expects),
+                ),
+            };
+            combine(&mut err, Error::new(span, message));
+            return Err(err.unwrap());
+        }
+
+        let target = Target::parse_with(input, kind == Kind::Capture).map_err(|e| {
+            combine(&mut err, e);
+            err.take().unwrap()
+        })?;
+        let bounds = if input.peek(Token![;]) {
+            match input.parse::<Bounds>() {
+                Ok(bounds) => Some(bounds),
+                Err(e) => {
+                    combine(&mut err, e);
+                    // Don't also complain about the rest of the clause
+                    let _ = input.parse::<TokenStream>();
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        // rustfmt and code generators may leave a comma after the last argument
+        if input.peek(Token![,]) && {
+            let fork = input.fork();
+            fork.parse::<Token![,]>().is_ok() && fork.is_empty()
+        } {
+            input.parse::<Token![,]>()?;
+        }
+        if !input.is_empty() {
+            // Consumed, so that the target can still be expanded
+            let rest = input.parse::<TokenStream>()?;
+            let mut message = format!("expected the macro input to end, found `{}`", rest);
+            let mut tokens = rest.clone().into_iter();
+            match (tokens.next(), tokens.next()) {
+                (Some(TokenTree::Punct(p)), Some(_)) if p.as_char() == ',' => message
+                    .push_str("; to apply the directives to several closures, put them in a tuple"),
+                _ => (),
+            }
+            combine(&mut err, Error::new_spanned(rest, message));
+        }
+        // The other checks need the input as a whole, so those errors can't be recovered from
+        let mut parsed = Input::finish(directives, target, kind, None).map_err(|e| {
+            combine(&mut err, e);
+            err.take().unwrap()
+        })?;
+        match kind {
+            #[cfg(feature = "pyo3")]
+            Kind::PyClosure => parsed.py = lead,
+            _ => parsed.ctx = lead,
+        }
+        parsed.bounds = bounds;
+        Ok((parsed, err))
+    }
+
+    /// Checks that the directives are compatible with each other, the macro, and the target.
+    fn finish(
+        directives: Directives,
+        mut target: Target,
+        kind: Kind,
+        mut err: Option<Error>,
+    ) -> syn::Result<Self> {
+        let Directives {
+            mut assigned,
+            all,
+            flags,
+            defaults,
+            mut needs_move,
+            last_invalid: _,
+        } = directives;
+        // Defaults only apply to variables that are used and don't already have a directive. The
+        // innermost `capture_defaults!` inserts its defaults first, so it takes precedence.
+        // The body is only walked once, rather than once for each variable
+        let names = match target {
+            Target::Macro(_) if defaults.is_empty() => HashSet::new(),
+            _ => target.names(),
+        };
+        if !defaults.is_empty() {
+            let mut taken: HashSet<_> = assigned.iter().map(|a| unraw_name(&a.upvar)).collect();
+            taken.extend(all.iter().map(|a| unraw_name(&a.upvar)));
+            for d in defaults {
+                let name = unraw_name(&d.upvar);
+                if names.contains(&name) && taken.insert(name) {
+                    needs_move |= !matches!(&d.ty, DirectiveType::Ref(..));
+                    assigned.push(d);
+                }
+            }
+        }
+        flags.check(kind, &mut err);
+        // The span is captured, and entered in the body
+        #[cfg(feature = "tracing")]
+        if let Some(span) = flags.instrument {
+            needs_move = true;
+            if matches!(
+                target.kind(),
+                TargetKind::Gen | TargetKind::Block | TargetKind::Macro
+            ) {
+                combine(
+                    &mut err,
+                    Error::new(span, "`instrument` needs a closure or an async block"),
+                );
+            }
+        }
+        if kind == Kind::CaptureCtx {
+            for d in &all {
+                combine(
+                    &mut err,
+                    Error::new(
+                        d.upvar.span(),
+                        "`all` directives cannot be used with `capture_ctx!`",
+                    ),
+                );
+            }
+        }
+        #[cfg(feature = "pyo3")]
+        if kind != Kind::PyClosure {
+            for d in &assigned {
+                if let DirectiveType::CloneRef(sp) = d.ty {
+                    combine(
+                        &mut err,
+                        Error::new(
+                            sp,
+                            "`clone_ref` directives can only be used with `py_closure!`",
+                        ),
+                    );
+                }
+            }
+        }
+        #[cfg(feature = "glib")]
+        if !assigned
+            .iter()
+            .any(|d| matches!(d.ty, DirectiveType::Weak(_)))
+        {
+            let spans = [
+                flags
+                    .default_return
+                    .as_ref()
+                    .map(|d| (d.span, "default_return")),
+                flags.glib.as_ref().map(|g| (g.span, "glib")),
+            ];
+            for (span, flag) in spans.into_iter().flatten() {
+                combine(
+                    &mut err,
+                    Error::new(
+                        span,
+                        format!("`{}` has no effect without a `weak` directive", flag),
+                    ),
+                );
+            }
+        }
+        match kind.requires() {
+            Some(TargetKind::Async)
+                if !matches!(target.kind(), TargetKind::Async | TargetKind::Macro) =>
+            {
+                combine(
+                    &mut err,
+                    Error::new_spanned(
+                        target.header(),
+                        format!("`{}` expects {}", kind.name(), kind.expects()),
+                    ),
+                )
+            }
+            Some(TargetKind::Closure)
+                if !matches!(target.kind(), TargetKind::Closure | TargetKind::Macro) =>
+            {
+                combine(
+                    &mut err,
+                    Error::new_spanned(
+                        target.header(),
+                        format!("`{}` expects {}", kind.name(), kind.expects()),
+                    ),
+                )
+            }
+            Some(TargetKind::Block) if target.kind() != TargetKind::Block => combine(
+                &mut err,
+                Error::new_spanned(
+                    target.header(),
+                    format!("`{}` expects {}", kind.name(), kind.expects()),
+                ),
+            ),
+            None if target.kind() == TargetKind::Block => combine(
+                &mut err,
+                Error::new_spanned(
+                    target.header(),
+                    format!("`{}` expects {}", kind.name(), kind.expects()),
+                ),
+            ),
+            _ => (),
+        }
+        // We can't see into the macro's expansion, so there is no way to add to its body
+        if target.kind() == TargetKind::Macro {
+            if matches!(kind, Kind::CaptureOnly | Kind::CaptureLint) {
+                combine(
+                    &mut err,
+                    Error::new_spanned(
+                        target.header(),
+                        format!("`{}` cannot be applied to a macro invocation", kind.name()),
+                    ),
+                );
+            }
+            if kind.needs_arguments() {
+                combine(
+                    &mut err,
+                    Error::new_spanned(
+                        target.header(),
+                        format!(
+                            "`{}` needs to see the closure's arguments, so it cannot be applied to a macro invocation",
+                            kind.name()
+                        ),
+                    ),
+                );
+            }
+            for d in &all {
+                combine(
+                    &mut err,
+                    Error::new(
+                        d.upvar.span(),
+                        "`all` directives cannot be used with a macro invocation",
+                    ),
+                );
+            }
+            #[cfg(feature = "glib")]
+            for d in &assigned {
+                if let DirectiveType::Weak(sp) = d.ty {
+                    combine(
+                        &mut err,
+                        Error::new(
+                            sp,
+                            "`weak` directives cannot be used with a macro invocation",
+                        ),
+                    );
+                }
+            }
+        }
+        // A failed upgrade returns from the closure, but a block has no closure to return from, so
+        // it would return from the surrounding function instead
+        #[cfg(feature = "glib")]
+        if target.kind() == TargetKind::Block {
+            let mut weak = false;
+            for d in &assigned {
+                if let DirectiveType::Weak(sp) = d.ty {
+                    weak = true;
+                    combine(
+                        &mut err,
+                        Error::new(sp, "`weak` directives cannot be used with a block"),
+                    );
+                }
+            }
+            // Without a `weak` directive, `default_return` is already reported as having no effect
+            if let (true, Some(d)) = (weak, &flags.default_return) {
+                combine(
+                    &mut err,
+                    Error::new(d.span, "`default_return` cannot be used with a block"),
+                );
+            }
+        }
+        // The trampoline is a function, so it needs the types of the arguments
+        #[cfg(feature = "alloc")]
+        if let (Kind::CaptureExtern, Target::Closure(closure)) = (kind, &target) {
+            for arg in closure
+                .inputs
+                .iter()
+                .filter(|a| !matches!(a, syn::Pat::Type(_)))
+            {
+                combine(
+                    &mut err,
+                    Error::new_spanned(arg, "the arguments in `capture_extern!` must have types"),
+                );
+            }
+            if let Some(asyncness) = &closure.asyncness {
+                combine(
+                    &mut err,
+                    Error::new_spanned(asyncness, "`capture_extern!` needs a plain closure"),
+                );
+            }
+        }
+        // The `move` flag is written by hand, so it is also enough for `explicit_move`
+        if let Some(span) = flags.make_move {
+            let target_kind = target.kind();
+            match target.capture_mut() {
+                Some(capture) => {
+                    capture.get_or_insert(Token![move](span));
+                }
+                // A block that the macro doesn't accept is already reported
+                None if target_kind == TargetKind::Macro
+                    || kind.requires() == Some(TargetKind::Block) =>
+                {
+                    combine(
+                        &mut err,
+                        Error::new(span, "`move` needs a closure or an async block"),
+                    )
+                }
+                None => (),
+            }
+        }
+        // Blocks don't capture anything, so the variables stay in scope for `ref` directives. The
+        // closure produced by a macro must be `move` itself, which we can't check.
+        let header = target.header();
+        let capture = target.capture_mut();
+        let mut made_move = None;
+        let is_move = match capture {
+            Some(capture) if needs_move && capture.is_none() => {
+                let mover = assigned
+                    .iter()
+                    .find(|d| !matches!(&d.ty, DirectiveType::Ref(..)))
+                    .map(|d| (d.upvar.clone(), d.kind()));
+                let needed_by = match &mover {
+                    Some((var, kind)) => format!("the `{} {}` directive needs", kind, var),
+                    // Only `instrument` needs it otherwise
+                    None => "the `instrument` flag needs".to_string(),
+                };
+                match flags.explicit_move {
+                    Some(_) => combine(
+                        &mut err,
+                        Error::new_spanned(
+                            &header,
+                            format!(
+                                "add `move`, which {} when `explicit_move` is set",
+                                needed_by
+                            ),
+                        ),
+                    ),
+                    None => {
+                        *capture = Some(Default::default());
+                        made_move = mover;
+                    }
+                }
+                // Either way, don't complain about the `ref` directives too
+                true
+            }
+            Some(capture) => capture.is_some(),
+            None => true,
+        };
+        if !is_move {
+            for dir in assigned.iter() {
+                match &dir.ty {
+                    DirectiveType::Ref(sp, _) => combine(
+                        &mut err,
+                        Error::new(
+                            *sp,
+                            "`ref` directives only allowed on `move` closures and async blocks",
+                        ),
+                    ),
+                    _ => panic!("Bug: Somehow not `needs_move`"),
+                }
+            }
+        }
+
+        // `#[coroutine]` is needed to make a closure into a coroutine, so that one is kept
+        let is_closure = target.kind() == TargetKind::Closure;
+        let (kept, attrs): (Vec<_>, Vec<_>) = std::mem::take(target.attrs_mut())
+            .into_iter()
+            .partition(|a| is_closure && a.path.is_ident("coroutine"));
+        *target.attrs_mut() = kept;
+        if !attrs.is_empty() {
+            let add_err = Error::new_spanned(
+                &attrs[0],
+                "attributes are not allowed on the closure inside a `captures!`",
+            );
+            combine(&mut err, add_err);
+        }
+        // What the closure produced by a macro uses can't be seen, and names starting with `_`
+        // are unused on purpose, as for `with _guard = ...`. A variable may also be used only by
+        // the `with` directives after it, which see its new value.
+        let used_later = |i: usize, id: &Ident| {
+            assigned[i + 1..].iter().any(|d| match &d.ty {
+                DirectiveType::With(expr) | DirectiveType::Custom(_, expr) => {
+                    mentions(expr.to_token_stream(), id)
+                }
+                _ => false,
+            })
+        };
+        let unused = match target {
+            Target::Macro(_) => Vec::new(),
+            _ => assigned
+                .iter()
+                .enumerate()
+                .filter(|(i, d)| !used_later(*i, &d.upvar))
+                .map(|(_, d)| &d.upvar)
+                .chain(all.iter().map(|d| &d.upvar))
+                .filter(|id| {
+                    let name = unraw_name(id);
+                    !name.starts_with('_') && !names.contains(&name)
+                })
+                .cloned()
+                .collect(),
+        };
+        if let Some(err) = err {
+            Err(err)
+        } else {
+            Ok(Input {
+                ctx: None,
+                #[cfg(feature = "pyo3")]
+                py: None,
+                all,
+                assigned,
+                flags,
+                target,
+                bounds: None,
+                unused,
+                made_move,
+            })
+        }
+    }
+}
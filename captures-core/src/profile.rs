@@ -0,0 +1,55 @@
+//! Timing of the expansions for the `profile` feature.
+//!
+//! Each invocation logs one line with the location of the invocation, the time spent expanding
+//! it, and how much of that went into parsing the input. The lines go to the file named by the
+//! `CAPTURES_PROFILE` environment variable, or to stderr if it isn't set.
+
+use std::cell::Cell;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use proc_macro2::Span;
+
+thread_local! {
+    /// The time spent parsing in the expansion that is currently timed
+    static PARSING: Cell<Duration> = Cell::new(Duration::ZERO);
+}
+
+/// Runs the expansion of an invocation of `name`, like `capture_only!`, and logs its time
+pub fn expansion<T>(name: &str, expand: impl FnOnce() -> T) -> T {
+    let outer = PARSING.with(|p| p.replace(Duration::ZERO));
+    let start = Instant::now();
+    let out = expand();
+    let total = start.elapsed();
+    let parsing = PARSING.with(|p| p.replace(outer));
+
+    // Only known on Rust 1.88 and later, and otherwise left out
+    let span = Span::call_site();
+    let location = match span.start().line {
+        0 => String::new(),
+        line => format!(" at {}:{}", span.file(), line),
+    };
+    let line = format!(
+        "{}{} took {:?}, {:?} of it parsing\n",
+        name, location, total, parsing
+    );
+    match std::env::var_os("CAPTURES_PROFILE") {
+        Some(path) => {
+            // The log is best-effort, and mustn't fail the build
+            if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+                let _ = file.write_all(line.as_bytes());
+            }
+        }
+        None => eprint!("{}", line),
+    }
+    out
+}
+
+/// Runs the parsing of the input, adding its time to that of the expansion
+pub fn parsing<T>(parse: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let out = parse();
+    PARSING.with(|p| p.set(p.get() + start.elapsed()));
+    out
+}
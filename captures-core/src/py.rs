@@ -0,0 +1,26 @@
+//! Expansion for `py_closure!`.
+
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::quote;
+use syn::Expr;
+
+/// The variable holding the GIL token, which `clone_ref` directives use
+pub fn gil() -> Ident {
+    Ident::new("py", Span::mixed_site())
+}
+
+/// `PyCFunction::new_closure`, with the GIL token evaluated before the captures
+pub fn wrap(py: &Expr, expanded: TokenStream) -> TokenStream {
+    let gil = gil();
+    quote! {
+        {
+            let #gil = #py;
+            ::pyo3::types::PyCFunction::new_closure(
+                #gil,
+                ::core::option::Option::None,
+                ::core::option::Option::None,
+                #expanded,
+            )
+        }
+    }
+}
@@ -0,0 +1,65 @@
+//! Expansions for the macros that hand the captured closure or future off to be run elsewhere.
+//!
+//! Each of these takes the already expanded closure or async block and wraps it in the call to the
+//! appropriate spawning function.
+
+use proc_macro2::TokenStream;
+#[cfg(any(feature = "tokio", feature = "async-std", feature = "smol"))]
+use {proc_macro2::Span, quote::quote_spanned};
+
+/// `std::thread::spawn`, or `std::thread::Builder` if the thread is to be named
+#[cfg(feature = "thread")]
+pub fn thread(name: Option<Box<syn::Expr>>, expanded: TokenStream) -> TokenStream {
+    match name {
+        Some(name) => quote::quote! {
+            ::std::thread::Builder::new()
+                .name(::core::convert::Into::into(#name))
+                .spawn(#expanded)
+                .expect("failed to spawn thread")
+        },
+        None => quote::quote!(::std::thread::spawn(#expanded)),
+    }
+}
+
+/// Passes the future to `spawn`, which is the path to a function like `tokio::spawn`
+///
+/// The `Send + 'static` bounds are additionally checked by a helper function whose signature is
+/// spanned to the async block, so that errors point there.
+#[cfg(any(feature = "tokio", feature = "async-std", feature = "smol"))]
+pub fn task(span: Span, spawn: TokenStream, expanded: TokenStream) -> TokenStream {
+    let span = span.resolved_at(Span::mixed_site());
+    quote_spanned! {span=>
+        {
+            fn assert_task<F>(
+                f: F,
+            ) -> impl ::core::future::Future<Output = F::Output> + ::core::marker::Send + 'static
+            where
+                F: ::core::future::Future + ::core::marker::Send + 'static,
+                F::Output: ::core::marker::Send + 'static,
+            {
+                f
+            }
+            #spawn(assert_task(#expanded))
+        }
+    }
+}
+
+/// `tokio::task::spawn_blocking`
+///
+/// Like for [`task`], the bounds are checked by a helper spanned to the closure.
+#[cfg(feature = "tokio")]
+pub fn tokio_blocking(span: Span, expanded: TokenStream) -> TokenStream {
+    let span = span.resolved_at(Span::mixed_site());
+    quote_spanned! {span=>
+        {
+            fn assert_blocking<F, R>(f: F) -> impl ::core::ops::FnOnce() -> R + ::core::marker::Send + 'static
+            where
+                F: ::core::ops::FnOnce() -> R + ::core::marker::Send + 'static,
+                R: ::core::marker::Send + 'static,
+            {
+                f
+            }
+            ::tokio::task::spawn_blocking(assert_blocking(#expanded))
+        }
+    }
+}
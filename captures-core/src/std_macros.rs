@@ -0,0 +1,257 @@
+//! The arguments of the well-known std macros, which are ordinary expressions.
+//!
+//! The tokens passed to other macros can only be treated as a whole, but the arguments of these
+//! can be cleaned and checked like the rest of the closure, respecting the scopes of patterns and
+//! closures within them.
+
+use proc_macro2::{Ident, TokenStream};
+use quote::{quote, ToTokens};
+use syn::parse::{ParseStream, Parser};
+use syn::punctuated::Punctuated;
+use syn::{Expr, Lit, Macro, Pat, PatOr, Token};
+use unicode_ident::{is_xid_continue, is_xid_start};
+
+/// The macros, with the position of their format string, if they take one
+const STD_MACROS: &[(&str, Option<usize>)] = &[
+    ("assert", Some(1)),
+    ("assert_eq", Some(2)),
+    ("assert_ne", Some(2)),
+    ("debug_assert", Some(1)),
+    ("debug_assert_eq", Some(2)),
+    ("debug_assert_ne", Some(2)),
+    ("dbg", None),
+    ("eprint", Some(0)),
+    ("eprintln", Some(0)),
+    ("format", Some(0)),
+    ("matches", None),
+    ("print", Some(0)),
+    ("println", Some(0)),
+    ("vec", None),
+    ("write", Some(1)),
+    ("writeln", Some(1)),
+];
+
+pub enum StdMacroArgs {
+    /// A list of expressions, possibly starting with a format string and its arguments
+    Exprs(Punctuated<Expr, Token![,]>),
+    /// `vec![x; n]`
+    Repeat(Box<Expr>, Token![;], Box<Expr>),
+    /// `matches!(x, pattern if guard)`
+    Matches {
+        expr: Box<Expr>,
+        comma: Token![,],
+        pat: Pat,
+        guard: Option<(Token![if], Box<Expr>)>,
+        trailing: Option<Token![,]>,
+    },
+}
+
+impl StdMacroArgs {
+    /// Parses the arguments if the macro is one of the std macros, called as `name!`,
+    /// `std::name!` or `core::name!`. Format strings are given explicit arguments for the
+    /// variables that they use inline, since those resolve at the format string.
+    pub fn parse(mac: &Macro) -> Option<Self> {
+        let segments: Vec<_> = mac.path.segments.iter().map(|s| &s.ident).collect();
+        let name = match &segments[..] {
+            [name] => name,
+            [krate, name] if *krate == "std" || *krate == "core" || *krate == "alloc" => name,
+            _ => return None,
+        };
+        let (name, fmt) = STD_MACROS.iter().find(|(m, _)| name == m)?;
+        let mut args = match *name {
+            "matches" => parse_matches.parse2(mac.tokens.clone()).ok()?,
+            "vec" => parse_repeat
+                .parse2(mac.tokens.clone())
+                .or_else(|_| parse_exprs.parse2(mac.tokens.clone()))
+                .ok()?,
+            _ => parse_exprs.parse2(mac.tokens.clone()).ok()?,
+        };
+        if let (StdMacroArgs::Exprs(exprs), Some(fmt)) = (&mut args, fmt) {
+            add_inline_args(exprs, *fmt);
+        }
+        Some(args)
+    }
+
+    /// The expressions that are evaluated, skipping the names of named format arguments, with
+    /// the pattern and guard of `matches!`
+    pub fn parts_mut(&mut self) -> (Vec<&mut Expr>, Option<(&mut Pat, Option<&mut Expr>)>) {
+        match self {
+            StdMacroArgs::Exprs(exprs) => (exprs.iter_mut().map(value_mut).collect(), None),
+            StdMacroArgs::Repeat(value, _, len) => (vec![&mut **value, &mut **len], None),
+            StdMacroArgs::Matches {
+                expr, pat, guard, ..
+            } => (
+                vec![&mut **expr],
+                Some((pat, guard.as_mut().map(|(_, g)| &mut **g))),
+            ),
+        }
+    }
+
+    /// Like [`StdMacroArgs::parts_mut`]
+    pub fn parts(&self) -> (Vec<&Expr>, Option<(&Pat, Option<&Expr>)>) {
+        match self {
+            StdMacroArgs::Exprs(exprs) => (exprs.iter().map(value).collect(), None),
+            StdMacroArgs::Repeat(value, _, len) => (vec![&**value, &**len], None),
+            StdMacroArgs::Matches {
+                expr, pat, guard, ..
+            } => (
+                vec![&**expr],
+                Some((pat, guard.as_ref().map(|(_, g)| &**g))),
+            ),
+        }
+    }
+}
+
+impl ToTokens for StdMacroArgs {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        match self {
+            StdMacroArgs::Exprs(exprs) => exprs.to_tokens(tokens),
+            StdMacroArgs::Repeat(value, semi, len) => {
+                value.to_tokens(tokens);
+                semi.to_tokens(tokens);
+                len.to_tokens(tokens);
+            }
+            StdMacroArgs::Matches {
+                expr,
+                comma,
+                pat,
+                guard,
+                trailing,
+            } => {
+                expr.to_tokens(tokens);
+                comma.to_tokens(tokens);
+                pat.to_tokens(tokens);
+                if let Some((if_token, guard)) = guard {
+                    if_token.to_tokens(tokens);
+                    guard.to_tokens(tokens);
+                }
+                trailing.to_tokens(tokens);
+            }
+        }
+    }
+}
+
+/// The value of a named format argument like `width = w`, or the expression itself
+fn value(expr: &Expr) -> &Expr {
+    match expr {
+        Expr::Assign(assign) => &assign.right,
+        expr => expr,
+    }
+}
+
+fn value_mut(expr: &mut Expr) -> &mut Expr {
+    match expr {
+        Expr::Assign(assign) => &mut assign.right,
+        expr => expr,
+    }
+}
+
+fn parse_exprs(input: ParseStream) -> syn::Result<StdMacroArgs> {
+    Punctuated::parse_terminated(input).map(StdMacroArgs::Exprs)
+}
+
+fn parse_repeat(input: ParseStream) -> syn::Result<StdMacroArgs> {
+    Ok(StdMacroArgs::Repeat(
+        input.parse()?,
+        input.parse()?,
+        input.parse()?,
+    ))
+}
+
+fn parse_matches(input: ParseStream) -> syn::Result<StdMacroArgs> {
+    let expr = input.parse()?;
+    let comma = input.parse()?;
+    let pat = parse_pat(input)?;
+    let guard = match input.parse::<Option<Token![if]>>()? {
+        Some(if_token) => Some((if_token, input.parse()?)),
+        None => None,
+    };
+    Ok(StdMacroArgs::Matches {
+        expr,
+        comma,
+        pat,
+        guard,
+        trailing: input.parse()?,
+    })
+}
+
+/// Parses a pattern that may have alternatives, like `A | B`, which `Pat`'s own parser doesn't take
+pub fn parse_pat(input: ParseStream) -> syn::Result<Pat> {
+    let leading_vert: Option<Token![|]> = input.parse()?;
+    let mut cases = Punctuated::new();
+    cases.push_value(input.parse()?);
+    while input.peek(Token![|]) && !input.peek(Token![||]) {
+        cases.push_punct(input.parse()?);
+        cases.push_value(input.parse()?);
+    }
+    Ok(if leading_vert.is_none() && cases.len() == 1 {
+        cases.into_iter().next().unwrap()
+    } else {
+        Pat::Or(PatOr {
+            attrs: Vec::new(),
+            leading_vert,
+            cases,
+        })
+    })
+}
+
+/// Adds a named argument for each variable that the format string at `fmt` uses inline, like the
+/// `a` in `"{a}"`, unless it is named explicitly
+fn add_inline_args(exprs: &mut Punctuated<Expr, Token![,]>, fmt: usize) {
+    let (value, span) = match exprs.iter().nth(fmt) {
+        Some(Expr::Lit(syn::ExprLit {
+            lit: Lit::Str(s), ..
+        })) => (s.value(), s.span()),
+        _ => return,
+    };
+    let named: Vec<_> = exprs
+        .iter()
+        .skip(fmt + 1)
+        .filter_map(|expr| match expr {
+            Expr::Assign(assign) => Some(assign.left.to_token_stream().to_string()),
+            _ => None,
+        })
+        .collect();
+    for name in inline_args(&value) {
+        if named.contains(&name) {
+            continue;
+        }
+        // Keywords can't be used inline, which the format macro reports itself
+        let ident = Ident::new(&name, span);
+        if let Ok(arg) = syn::parse2(quote!(#ident = #ident)) {
+            exprs.push(arg);
+        }
+    }
+}
+
+/// The names used inline in a format string, as arguments or as `name$` widths and precisions
+pub fn inline_args(fmt: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut rest = fmt;
+    while let Some(start) = rest.find('{') {
+        rest = &rest[start + 1..];
+        if let Some(escaped) = rest.strip_prefix('{') {
+            rest = escaped;
+            continue;
+        }
+        let end = rest.find('}').unwrap_or(rest.len());
+        let (arg, spec) = match rest[..end].split_once(':') {
+            Some((arg, spec)) => (arg, spec),
+            None => (&rest[..end], ""),
+        };
+        let widths = spec
+            .split(|c: char| !is_xid_continue(c) && c != '$')
+            .filter_map(|part| part.strip_suffix('$'));
+        for candidate in std::iter::once(arg).chain(widths) {
+            // The same characters as in identifiers, which can be in any script
+            let is_name = candidate.starts_with(|c: char| is_xid_start(c) || c == '_')
+                && candidate != "_"
+                && candidate.chars().all(is_xid_continue);
+            if is_name && !names.iter().any(|n| n == candidate) {
+                names.push(candidate.to_string());
+            }
+        }
+        rest = &rest[end..];
+    }
+    names
+}
@@ -0,0 +1,54 @@
+//! Logging for the `trace` feature.
+
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::quote;
+
+use crate::{Input, Target};
+
+/// The statements logging the creation of the closure, and the one moving the value that logs its
+/// drop into the closure.
+///
+/// Closures that aren't `move` would only borrow that value, so their drop isn't logged.
+pub fn bindings(input: &Input) -> (TokenStream, TokenStream) {
+    let (what, is_move) = match &input.target {
+        Target::Closure(c) => ("closure", c.capture.is_some()),
+        Target::Async(a) => ("async block", a.capture.is_some()),
+        Target::Gen(g) => ("gen block", g.capture.is_some()),
+        Target::Block(_) | Target::Macro(_) => return Default::default(),
+    };
+    let captures: Vec<_> = input
+        .directive_kinds()
+        .into_iter()
+        .map(|(name, kind)| format!("{} {}", kind, name))
+        .collect();
+    let created = format!(
+        "{} at {{}}:{{}} created, capturing {}",
+        what,
+        captures.join(", ")
+    );
+    let dropped = format!("{} at {{}}:{{}} dropped", what);
+
+    let location = quote!(::core::file!(), ::core::line!());
+    if !is_move {
+        return (
+            quote!(::log::trace!(#created, #location);),
+            TokenStream::new(),
+        );
+    }
+    let (ty, var) = (
+        Ident::new("Trace", Span::mixed_site()),
+        Ident::new("trace", Span::mixed_site()),
+    );
+    let ext = quote! {
+        #[derive(::core::clone::Clone)]
+        struct #ty(&'static str, u32);
+        impl ::core::ops::Drop for #ty {
+            fn drop(&mut self) {
+                ::log::trace!(#dropped, self.0, self.1);
+            }
+        }
+        let #var = #ty(#location);
+        ::log::trace!(#created, #location);
+    };
+    (ext, quote!(let _ = &#var;))
+}
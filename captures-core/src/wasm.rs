@@ -0,0 +1,25 @@
+//! Expansions for the wasm-bindgen helpers.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// `Closure::wrap`, casting the boxed closure to a `dyn FnMut` of the right arity
+///
+/// The argument and return types are left to inference.
+pub fn wrap(arity: usize, expanded: TokenStream) -> TokenStream {
+    let args = std::iter::repeat(quote!(_)).take(arity);
+    let (krate, path) = crate::alloc_box();
+    quote! {
+        {
+            #krate
+            ::wasm_bindgen::closure::Closure::wrap(
+                #path::new(#expanded) as #path<dyn ::core::ops::FnMut(#(#args),*) -> _>
+            )
+        }
+    }
+}
+
+/// `Closure::once`
+pub fn once(expanded: TokenStream) -> TokenStream {
+    quote!(::wasm_bindgen::closure::Closure::once(#expanded))
+}
@@ -0,0 +1,75 @@
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+
+/// A closure along with a record of the directives it was created with.
+///
+/// This is produced by the `introspect` flag of the `captures` macros. Its [`Debug`] impl lists
+/// the captures, which helps with finding out what a callback keeps alive. Stable Rust doesn't
+/// allow implementing the `Fn` traits, so the closure is reached through [`Deref`] instead, and
+/// is called as `(*f)(args)`.
+pub struct Captured<F> {
+    inner: F,
+    captures: &'static [Capture],
+}
+
+/// One directive of a [`Captured`] closure.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Capture {
+    /// The name of the variable
+    pub name: &'static str,
+    /// The directive, like `clone` or `ref mut`
+    pub kind: &'static str,
+}
+
+impl<F> Captured<F> {
+    /// Wraps the closure. This is called by the macros, and doesn't usually need to be called
+    /// directly.
+    pub const fn new(inner: F, captures: &'static [Capture]) -> Self {
+        Captured { inner, captures }
+    }
+
+    /// The directives that the closure was created with, in the order they were written in.
+    pub fn captures(&self) -> &'static [Capture] {
+        self.captures
+    }
+
+    /// Returns the closure itself.
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+}
+
+impl<F> Deref for Captured<F> {
+    type Target = F;
+
+    fn deref(&self) -> &F {
+        &self.inner
+    }
+}
+
+impl<F> DerefMut for Captured<F> {
+    fn deref_mut(&mut self) -> &mut F {
+        &mut self.inner
+    }
+}
+
+impl<F: Clone> Clone for Captured<F> {
+    fn clone(&self) -> Self {
+        Captured {
+            inner: self.inner.clone(),
+            captures: self.captures,
+        }
+    }
+}
+
+impl<F> fmt::Debug for Captured<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Captured").field(&self.captures).finish()
+    }
+}
+
+impl fmt::Debug for Capture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.kind, self.name)
+    }
+}
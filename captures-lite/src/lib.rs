@@ -0,0 +1,148 @@
+//! A dependency-free version of the `capture!` macro from [`captures`].
+//!
+//! `captures` is a procedural macro crate, and so depends on `syn`, which can make up a noticeable
+//! part of the build time of a small crate. This crate instead implements the most common part of
+//! `capture!` with `macro_rules!`, namely the `clone`, `with`, and `ref` directives:
+//! ```
+//! use captures_lite::capture;
+//! use std::rc::Rc;
+//!
+//! let a = Rc::new(1);
+//! let c = 3;
+//! let f = capture!(clone a, with b = 2, ref c, || *a + b + *c);
+//! assert_eq!(f(), 6);
+//! assert_eq!(*a, 1);
+//! ```
+//! All three may be written with `mut`: `clone mut a` and `with mut b = ...` make the captured
+//! variable mutable, and `ref mut c` captures `c` by mutable reference. As with `captures`, a
+//! closure or async block with `clone` or `with` directives or the `move` flag is made `move` if it
+//! isn't already, while one with only `ref` directives has to be written as `move`, and the target
+//! may be followed by a comma.
+//!
+//! Everything else, including the other directives and flags, tuples of closures, `capture_only!`,
+//! and the spawning macros, needs the full crate. In particular, the crate can't clean a closure
+//! body, so mistakes like a `clone` of a variable that the closure never uses aren't pointed out.
+//! An invocation that only uses what this crate supports expands the same way with `captures`, so
+//! switching between the two is a matter of changing the import.
+//!
+//! This crate also holds the [`Captured`] wrapper that the `introspect` flag of `captures`
+//! produces, since a procedural macro crate can't export types.
+//!
+//! [`captures`]: https://docs.rs/captures
+#![no_std]
+
+mod captured;
+
+pub use captured::{Capture, Captured};
+
+/// Captures clones of or references to variables, or values of expressions, into a closure or
+/// async block.
+///
+/// See the [crate level documentation][`crate`] for more info.
+#[macro_export]
+macro_rules! capture {
+    ($($input:tt)+) => {
+        $crate::__capture!(_ [] $($input)+)
+    };
+}
+
+// The first token is `move` once a directive or flag makes the target `move`, and `_` before that
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __capture {
+    // The target, from which a trailing comma is removed
+    (@target $mv:tt [$($lets:tt)*] [$($target:tt)*] ,) => {
+        $crate::__capture!(@made $mv [$($lets)*] $($target)*)
+    };
+    (@target $mv:tt [$($lets:tt)*] [$($target:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::__capture!(@target $mv [$($lets)*] [$($target)* $next] $($rest)*)
+    };
+    (@target $mv:tt [$($lets:tt)*] [$($target:tt)*]) => {
+        $crate::__capture!(@made $mv [$($lets)*] $($target)*)
+    };
+    // Without directives or the `move` flag, the target is left as it is, like in `captures`
+    (@made _ [] $($target:tt)+) => {
+        { $($target)+ }
+    };
+    // `ref` directives alone don't make the target `move`, so it has to be already
+    (@made _ [$($lets:tt)+] $($target:tt)+) => {
+        $crate::__capture!(@moved [$($lets)+] $($target)+)
+    };
+    (@made move [$($lets:tt)*] $($target:tt)+) => {
+        $crate::__capture!(@move [$($lets)*] $($target)+)
+    };
+    (@moved [$($lets:tt)*] #[coroutine] $($target:tt)+) => {
+        $crate::__capture!(@moved [$($lets)* #[coroutine]] $($target)+)
+    };
+    (@moved [$($lets:tt)*] move $($target:tt)+) => {
+        { $($lets)* move $($target)+ }
+    };
+    (@moved [$($lets:tt)*] async move $($target:tt)+) => {
+        { $($lets)* async move $($target)+ }
+    };
+    (@moved [$($lets:tt)*] static move $($target:tt)+) => {
+        { $($lets)* static move $($target)+ }
+    };
+    (@moved [$($lets:tt)*] $($target:tt)+) => {
+        ::core::compile_error!("`ref` directives only allowed on `move` closures and async blocks")
+    };
+    // Otherwise it is made `move` if it isn't already, after the `#[coroutine]` of a coroutine
+    (@move [$($lets:tt)*] #[coroutine] $($target:tt)+) => {
+        $crate::__capture!(@move [$($lets)* #[coroutine]] $($target)+)
+    };
+    (@move [$($lets:tt)*] move $($target:tt)+) => {
+        { $($lets)* move $($target)+ }
+    };
+    (@move [$($lets:tt)*] async move $($target:tt)+) => {
+        { $($lets)* async move $($target)+ }
+    };
+    (@move [$($lets:tt)*] async $($target:tt)+) => {
+        { $($lets)* async move $($target)+ }
+    };
+    (@move [$($lets:tt)*] static move $($target:tt)+) => {
+        { $($lets)* static move $($target)+ }
+    };
+    (@move [$($lets:tt)*] static $($target:tt)+) => {
+        { $($lets)* static move $($target)+ }
+    };
+    (@move [$($lets:tt)*] $($target:tt)+) => {
+        { $($lets)* move $($target)+ }
+    };
+    // The `move` flag
+    ($mv:tt [$($lets:tt)*] move, $($rest:tt)+) => {
+        $crate::__capture!(move [$($lets)*] $($rest)+)
+    };
+    // The directives, which are collected into the `let`s in the brackets
+    ($mv:tt [$($lets:tt)*] clone $upvar:ident, $($rest:tt)+) => {
+        $crate::__capture!(
+            move [$($lets)* let $upvar = ::core::clone::Clone::clone(&$upvar);] $($rest)+
+        )
+    };
+    ($mv:tt [$($lets:tt)*] clone mut $upvar:ident, $($rest:tt)+) => {
+        $crate::__capture!(
+            move [$($lets)* let mut $upvar = ::core::clone::Clone::clone(&$upvar);] $($rest)+
+        )
+    };
+    ($mv:tt [$($lets:tt)*] with $upvar:ident = $val:expr, $($rest:tt)+) => {
+        $crate::__capture!(move [$($lets)* let $upvar = $val;] $($rest)+)
+    };
+    ($mv:tt [$($lets:tt)*] with mut $upvar:ident = $val:expr, $($rest:tt)+) => {
+        $crate::__capture!(move [$($lets)* let mut $upvar = $val;] $($rest)+)
+    };
+    // A `with` directive may also end with `;`
+    ($mv:tt [$($lets:tt)*] with $upvar:ident = $val:expr; $($rest:tt)+) => {
+        $crate::__capture!(move [$($lets)* let $upvar = $val;] $($rest)+)
+    };
+    ($mv:tt [$($lets:tt)*] with mut $upvar:ident = $val:expr; $($rest:tt)+) => {
+        $crate::__capture!(move [$($lets)* let mut $upvar = $val;] $($rest)+)
+    };
+    ($mv:tt [$($lets:tt)*] ref $upvar:ident, $($rest:tt)+) => {
+        $crate::__capture!($mv [$($lets)* let $upvar = &$upvar;] $($rest)+)
+    };
+    ($mv:tt [$($lets:tt)*] ref mut $upvar:ident, $($rest:tt)+) => {
+        $crate::__capture!($mv [$($lets)* let $upvar = &mut $upvar;] $($rest)+)
+    };
+    ($mv:tt [$($lets:tt)*] $($target:tt)+) => {
+        $crate::__capture!(@target $mv [$($lets)*] [] $($target)+)
+    };
+}
@@ -0,0 +1,81 @@
+use captures_lite::capture;
+
+use std::future::Future;
+use std::rc::Rc;
+
+fn takes_static<T: 'static>(t: T) -> T {
+    t
+}
+
+// Checks that `clone` and `with` directives are applied and make the closure `move`
+fn directives() {
+    let a = Rc::new(1);
+    let f = takes_static(capture!(clone a, with b = 2, || *a + b));
+    assert_eq!(f(), 3);
+    assert_eq!(*a, 1);
+}
+
+// Checks that `mut` is allowed and that explicit `move` is kept
+fn mutable() {
+    let a = vec![1];
+    let mut f = capture!(clone mut a, with mut n = 0, move |x: i32| {
+        a.push(x);
+        n += 1;
+        (a.len(), n)
+    });
+    assert_eq!(f(2), (2, 1));
+    assert_eq!(a, [1]);
+}
+
+// Checks that `ref` directives borrow the variables instead of moving them, so that the closure
+// can be `move`
+fn references() {
+    let (a, mut b) = (vec![1], vec![2]);
+    let mut f = capture!(ref a, ref mut b, move || {
+        b.push(a.len());
+        b.len()
+    });
+    assert_eq!(f(), 2);
+    assert_eq!((a, b), (vec![1], vec![2, 1]));
+}
+
+// Checks that async blocks are made `move`
+fn async_block() {
+    let a = Rc::new(1);
+    let _fut: &dyn Future<Output = i32> = &takes_static(capture!(clone a, async { *a }));
+}
+
+// Checks that variables may have the names of the directives
+fn keyword_names() {
+    let clone = 1;
+    let mut r#ref = 2;
+    let mut f = capture!(clone clone, with with = clone + 1, ref mut r#ref, || {
+        *r#ref += with;
+        clone
+    });
+    assert_eq!(f(), 1);
+    assert_eq!(r#ref, 4);
+}
+
+// Checks that a `with` directive may end with `;`
+fn semicolons() {
+    let f = capture!(with a = { let v = [1, 2]; v.len() }; with mut b = 1; || a + b);
+    assert_eq!(f(), 3);
+}
+
+// Checks that the `move` flag is accepted
+fn move_flag() {
+    let a = Rc::new(1);
+    let f = takes_static(capture!(move, clone a, || *a));
+    assert_eq!(f(), 1);
+}
+
+fn main() {
+    directives();
+    mutable();
+    references();
+    async_block();
+    keyword_names();
+    semicolons();
+    move_flag();
+}
@@ -0,0 +1,41 @@
+//! Checks that any input to any of the macros gives an expansion or errors, and never a panic.
+//!
+//! The first byte picks the macro, and the rest is the input, if it is valid tokens.
+
+#![no_main]
+
+use captures_core::Kind;
+use libfuzzer_sys::fuzz_target;
+use proc_macro2::TokenStream;
+
+const MACROS: &[&str] = &[
+    "capture",
+    "capture_only",
+    "capture_async",
+    "capture_ctx",
+    "capture_lint",
+    "bind",
+    "capture_extern",
+    "spawn",
+    "spawn_task",
+    "spawn_blocking",
+    "spawn_async_std",
+    "spawn_smol",
+    "wasm_closure",
+    "wasm_closure_once",
+    "py_closure",
+];
+
+fuzz_target!(|data: &[u8]| {
+    let (first, rest) = match data.split_first() {
+        Some(split) => split,
+        None => return,
+    };
+    let kind = Kind::from_name(MACROS[*first as usize % MACROS.len()]).unwrap();
+    let tokens: TokenStream = match std::str::from_utf8(rest).ok().and_then(|s| s.parse().ok()) {
+        Some(tokens) => tokens,
+        None => return,
+    };
+    captures_core::expand(tokens.clone(), kind);
+    let _ = captures_core::try_expand(tokens, kind);
+});
@@ -0,0 +1,47 @@
+//! The assertion macros, like `assert_send_closure!`.
+//!
+//! Each one expands to a call of a local function with the bound, named after what is being
+//! checked, since the name of that function is what shows up in the error message:
+//! ```text
+//! {
+//!     fn captures_must_be_send<T: Send>(_: &T) {}
+//!     captures_must_be_send(&f);
+//! }
+//! ```
+
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::{quote, quote_spanned};
+use syn::parse::Parser;
+use syn::spanned::Spanned;
+use syn::{Expr, Token};
+
+/// The property checked by one of the macros
+pub enum Property {
+    Static,
+    Send,
+    Sync,
+}
+
+pub fn assert(inp: TokenStream, property: Property) -> TokenStream {
+    let parser = |input: syn::parse::ParseStream| {
+        let expr: Expr = input.parse()?;
+        input.parse::<Option<Token![,]>>()?;
+        Ok(expr)
+    };
+    let expr = match parser.parse2(inp) {
+        Ok(expr) => expr,
+        Err(e) => return e.into_compile_error(),
+    };
+    let (name, bound) = match property {
+        Property::Static => ("captures_must_be_static", quote!('static)),
+        Property::Send => ("captures_must_be_send", quote!(::core::marker::Send)),
+        Property::Sync => ("captures_must_be_sync", quote!(::core::marker::Sync)),
+    };
+    let f = Ident::new(name, Span::mixed_site());
+    quote_spanned! {expr.span()=>
+        {
+            fn #f<T: ?::core::marker::Sized + #bound>(_: &T) {}
+            #f(&#expr);
+        }
+    }
+}
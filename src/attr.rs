@@ -0,0 +1,236 @@
+//! The `#[captures]` and `#[deny_implicit_captures]` attributes.
+//!
+//! `#[captures]` walks the annotated item looking for closures and async blocks that carry a
+//! `#[capture]` or `#[capture_only]` attribute, and replaces each of them with the expansion of the
+//! corresponding macro. An attribute on a `let` statement is moved to the closure it is initialized
+//! with. Given directives, `#[captures(...)]` may also be put on such a statement itself, which is
+//! then treated as if it had a `#[capture(...)]` attribute.
+//!
+//! `#[deny_implicit_captures]` walks the annotated item and reports an error for every closure that
+//! is neither annotated in this way nor inside of one of our macros (which it can't see into).
+
+use captures_core::{hiding_literals, Input, Kind, Target};
+use proc_macro2::{TokenStream, TokenTree};
+use quote::ToTokens;
+use syn::parse::Parser;
+use syn::visit_mut::{self, VisitMut};
+use syn::{Attribute, Block, Error, Expr, Item, Local, Stmt};
+
+pub fn captures(attr: TokenStream, item: TokenStream) -> TokenStream {
+    hiding_literals(attr, |attr| {
+        hiding_literals(item, |item| {
+            let expand = || rewrite_item(attr, item);
+            #[cfg(feature = "profile")]
+            let expand = || captures_core::profile::expansion("#[captures]", expand);
+            expand()
+        })
+    })
+}
+
+fn rewrite_item(attr: TokenStream, item: TokenStream) -> TokenStream {
+    if !attr.is_empty() {
+        if let Ok(mut stmts) = Block::parse_within.parse2(item.clone()) {
+            if let (1, Some(Stmt::Local(local))) = (stmts.len(), stmts.pop()) {
+                return statement(attr, local);
+            }
+        }
+    }
+    let mut item = match syn::parse2::<Item>(item) {
+        Ok(item) => item,
+        Err(e) => return e.into_compile_error(),
+    };
+
+    // If `#[deny_implicit_captures]` comes after us, it would see the closures in our expansion and
+    // reject them, so take care of it now instead.
+    let mut checker = Checker { err: None };
+    if let Some(attrs) = item_attrs_mut(&mut item) {
+        let len = attrs.len();
+        attrs.retain(|a| !is_attr(a, "deny_implicit_captures"));
+        if attrs.len() != len {
+            checker.visit_item_mut(&mut item);
+        }
+    }
+
+    let mut rewriter = Rewriter { err: None };
+    rewriter.visit_item_mut(&mut item);
+    let mut out = item.into_token_stream();
+    out.extend(rewriter.err.map(Error::into_compile_error));
+    out.extend(checker.err.map(Error::into_compile_error));
+    if let Some(tt) = attr.into_iter().next() {
+        out.extend(
+            Error::new(
+                tt.span(),
+                "`#[captures]` only takes directives when put on a `let` statement",
+            )
+            .into_compile_error(),
+        );
+    }
+    out
+}
+
+/// `#[captures(directives)] let f = ...;`, which is the same as `#[capture(directives)]` inside of
+/// an item with the attribute
+fn statement(attr: TokenStream, mut local: Local) -> TokenStream {
+    let capture = match Attribute::parse_outer.parse2(quote::quote!(#[capture(#attr)])) {
+        Ok(mut attrs) => attrs.remove(0),
+        Err(e) => return e.into_compile_error(),
+    };
+    local.attrs.insert(0, capture);
+    let mut rewriter = Rewriter { err: None };
+    rewriter.visit_local_mut(&mut local);
+    let mut out = local.into_token_stream();
+    out.extend(rewriter.err.map(Error::into_compile_error));
+    out
+}
+
+pub fn deny_implicit_captures(attr: TokenStream, item: TokenStream) -> TokenStream {
+    hiding_literals(item, |item| check_item(attr, item))
+}
+
+fn check_item(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut item = match syn::parse2::<Item>(item) {
+        Ok(item) => item,
+        Err(e) => return e.into_compile_error(),
+    };
+
+    let mut checker = Checker { err: None };
+    checker.visit_item_mut(&mut item);
+    let mut out = item.into_token_stream();
+    out.extend(checker.err.map(Error::into_compile_error));
+    if let Some(tt) = attr.into_iter().next() {
+        out.extend(
+            Error::new(
+                tt.span(),
+                "`#[deny_implicit_captures]` does not take any arguments",
+            )
+            .into_compile_error(),
+        );
+    }
+    out
+}
+
+struct Rewriter {
+    err: Option<Error>,
+}
+
+struct Checker {
+    err: Option<Error>,
+}
+
+/// Whether the attribute is the one with the given name, possibly qualified by a path
+fn is_attr(attr: &Attribute, name: &str) -> bool {
+    attr.path.segments.last().map_or(false, |s| s.ident == name)
+}
+
+/// Which macro the attribute corresponds to, if any
+fn kind_of(attr: &Attribute) -> Option<Kind> {
+    if attr.path.is_ident("capture") {
+        Some(Kind::Capture)
+    } else if attr.path.is_ident("capture_only") {
+        Some(Kind::CaptureOnly)
+    } else {
+        None
+    }
+}
+
+fn item_attrs_mut(item: &mut Item) -> Option<&mut Vec<Attribute>> {
+    match item {
+        Item::Const(i) => Some(&mut i.attrs),
+        Item::Fn(i) => Some(&mut i.attrs),
+        Item::Impl(i) => Some(&mut i.attrs),
+        Item::Mod(i) => Some(&mut i.attrs),
+        Item::Static(i) => Some(&mut i.attrs),
+        Item::Trait(i) => Some(&mut i.attrs),
+        _ => None,
+    }
+}
+
+/// The directives inside the attribute's parentheses
+fn arguments(attr: &Attribute) -> syn::Result<TokenStream> {
+    let mut tokens = attr.tokens.clone().into_iter();
+    match (tokens.next(), tokens.next()) {
+        (None, _) => Ok(TokenStream::new()),
+        (Some(TokenTree::Group(g)), None)
+            if g.delimiter() == proc_macro2::Delimiter::Parenthesis =>
+        {
+            Ok(g.stream())
+        }
+        _ => Err(Error::new_spanned(
+            &attr.tokens,
+            "expected the directives to be in parentheses",
+        )),
+    }
+}
+
+impl VisitMut for Rewriter {
+    fn visit_local_mut(&mut self, node: &mut Local) {
+        // `#[capture(...)] let f = || ...;` is the same as putting the attribute on the closure
+        if let Some(pos) = node.attrs.iter().position(|a| kind_of(a).is_some()) {
+            let attr = node.attrs.remove(pos);
+            let init = node.init.as_mut().map(|(_, init)| &mut **init);
+            match init {
+                Some(Expr::Closure(c)) => c.attrs.insert(0, attr),
+                Some(Expr::Async(a)) => a.attrs.insert(0, attr),
+                _ => {
+                    let e = Error::new_spanned(
+                        &attr,
+                        "the value must be a closure or async block to apply the directives to",
+                    );
+                    match &mut self.err {
+                        Some(err) => err.combine(e),
+                        None => self.err = Some(e),
+                    }
+                }
+            }
+        }
+        visit_mut::visit_local_mut(self, node);
+    }
+
+    fn visit_expr_mut(&mut self, node: &mut Expr) {
+        // Handle the inside first, so that nested annotated closures are expanded too
+        visit_mut::visit_expr_mut(self, node);
+
+        let attrs = match node {
+            Expr::Closure(c) => &mut c.attrs,
+            Expr::Async(a) => &mut a.attrs,
+            _ => return,
+        };
+        let (pos, kind) = match attrs
+            .iter()
+            .enumerate()
+            .find_map(|(i, a)| Some((i, kind_of(a)?)))
+        {
+            Some(x) => x,
+            None => return,
+        };
+        let attr = attrs.remove(pos);
+
+        let target = match std::mem::replace(node, Expr::Verbatim(TokenStream::new())) {
+            Expr::Closure(c) => Target::Closure(c),
+            Expr::Async(a) => Target::Async(a),
+            _ => unreachable!(),
+        };
+        let expanded = arguments(&attr)
+            .and_then(|args| Input::from_attribute(args, target, kind))
+            .map(|input| captures_core::generate(input, kind));
+        *node = Expr::Verbatim(expanded.unwrap_or_else(Error::into_compile_error));
+    }
+}
+
+impl VisitMut for Checker {
+    fn visit_expr_closure_mut(&mut self, node: &mut syn::ExprClosure) {
+        visit_mut::visit_expr_closure_mut(self, node);
+        if node.attrs.iter().any(|a| kind_of(a).is_some()) {
+            return;
+        }
+        let (or1, inputs, or2) = (&node.or1_token, &node.inputs, &node.or2_token);
+        let e = Error::new_spanned(
+            quote::quote!(#or1 #inputs #or2),
+            "closures must be written with `capture!` or `capture_only!` here",
+        );
+        match &mut self.err {
+            Some(err) => err.combine(e),
+            None => self.err = Some(e),
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//! The `capture_defaults!` macro.
+//!
+//! This works purely on tokens: every invocation of one of our macros inside of the scope gets a
+//! `default { ... }` directive inserted in front of its other directives, so that
+//! ```text
+//! capture_defaults! {
+//!     clone log;
+//!     let f = capture!(with a = 1, || log.info(a));
+//! }
+//! ```
+//! becomes
+//! ```text
+//! let f = capture!(default { clone log }, with a = 1, || log.info(a));
+//! ```
+//! Which of the defaults are actually used is decided once the closure has been parsed.
+
+use captures_core::Directives;
+use proc_macro2::{Group, Spacing, TokenStream, TokenTree};
+use quote::quote;
+use syn::parse::{ParseStream, Parser};
+use syn::{Error, Token};
+
+/// The macros that take directives, and so get the defaults
+const MACROS: &[&str] = &[
+    "capture",
+    "capture_only",
+    "capture_async",
+    "capture_ctx",
+    "capture_lint",
+    "bind",
+    "capture_extern",
+    "spawn",
+    "spawn_task",
+    "spawn_blocking",
+    "spawn_async_std",
+    "spawn_smol",
+    "wasm_closure",
+    "wasm_closure_once",
+    "py_closure",
+];
+
+pub fn capture_defaults(inp: TokenStream) -> TokenStream {
+    let parser = |input: ParseStream| {
+        let mut defaults = TokenStream::new();
+        while !input.is_empty() && !input.peek(Token![;]) {
+            defaults.extend(Some(input.parse::<TokenTree>()?));
+        }
+        input.parse::<Token![;]>()?;
+        // Check the defaults now, so that mistakes are reported once and not at every use
+        let check = |input: ParseStream| {
+            let mut err = None;
+            Directives::parse(input, &mut err);
+            err.map_or(Ok(()), Err)
+        };
+        check.parse2(quote!(default { #defaults }))?;
+        let rest = input.parse::<TokenStream>()?;
+        Ok(insert(rest, &defaults))
+    };
+    parser.parse2(inp).unwrap_or_else(Error::into_compile_error)
+}
+
+/// Inserts the defaults into all invocations of our macros in `tokens`
+fn insert(tokens: TokenStream, defaults: &TokenStream) -> TokenStream {
+    let mut tokens: Vec<_> = tokens.into_iter().collect();
+    for i in 0..tokens.len() {
+        let is_ours = match &tokens[..=i] {
+            [.., TokenTree::Ident(name), TokenTree::Punct(bang), _] => {
+                bang.as_char() == '!' && MACROS.iter().any(|m| name == m)
+            }
+            _ => false,
+        };
+        if let TokenTree::Group(g) = &tokens[i] {
+            let mut stream = insert(g.stream(), defaults);
+            if is_ours {
+                stream = with_defaults(stream, defaults);
+            }
+            let mut new = Group::new(g.delimiter(), stream);
+            new.set_span(g.span());
+            tokens[i] = TokenTree::Group(new);
+        }
+    }
+    tokens.into_iter().collect()
+}
+
+/// Adds the `default` directive to the input of one of our macros
+fn with_defaults(inp: TokenStream, defaults: &TokenStream) -> TokenStream {
+    let tokens: Vec<_> = inp.into_iter().collect();
+    // `capture_ctx!` starts with the context, which must be kept in front
+    let start = tokens
+        .windows(2)
+        .position(|w| match w {
+            [TokenTree::Punct(a), TokenTree::Punct(b)] => {
+                a.as_char() == '=' && a.spacing() == Spacing::Joint && b.as_char() == '>'
+            }
+            _ => false,
+        })
+        .map_or(0, |pos| pos + 2);
+    let (before, after) = tokens.split_at(start);
+    quote!(#(#before)* default { #defaults }, #(#after)*)
+}
@@ -1,4 +1,4 @@
-//! Provides two macros for more powerful closure captures.
+//! Provides macros for more powerful closure captures.
 //!
 //! # Background
 //!
@@ -49,10 +49,11 @@
 //!
 //! # Usage
 //!
-//! The `capture!` and `capture_only!` macros are invoked with a comma-seperated
-//! list of "capture directives" and finally a closure expression. One example of a capture
-//! directive is the `clone x` directive, which indicates that a clone of `x` should be captured in
-//! place of `x`. As such, the example above can be re-written to:
+//! The `capture!` and `capture_only!` macros are invoked with a comma-seperated list of "capture
+//! directives" and finally a closure expression or async block, which may be followed by a comma,
+//! as rustfmt sometimes leaves one. One example of a capture directive is the `clone x` directive,
+//! which indicates that a clone of `x` should be captured in place of `x`. As such, the example
+//! above can be re-written to:
 //! ```
 //! # use std::rc::Rc;
 //! use captures::capture;
@@ -73,38 +74,87 @@
 //! // `local` has not been captured!
 //! assert_eq!(*local.as_ref(), 1);
 //! ```
+//! `capture!` only reads the parameters and return type of the closure, and passes its body on
+//! as it is written, so the body may use any syntax that the compiler supports.
 //!
 //! ## Capture Directives
 //!
 //! These capture directives are currently supported:
 //!
 //!  - `clone x` captures a clone of `x`.
-//!  - `with x = expr` captures a value `x` that is computed from `expr`.
+//!  - `with x = expr` captures a value `x` that is computed from `expr`. The directives are
+//!    applied from left to right, so `expr` sees the values captured by the directives before it,
+//!    like the clone in `clone x, with n = x.len()`, and the outer variables otherwise, like in
+//!    `with n = x.len(), clone x`.
 //!  - `all x` captures all of `x`. Beginning in Rust 2021, writing `x.y` in your closure would lead
 //!    to only the `y` field of `x` being captured. Specifying `all x` causes all of `x` to be
-//!    captured instead. This does not influence whether `x` is captured by value or by reference -
-//!    if the closure is a `move` closure, it will still be captured by value, and if it is a
-//!    non-`move` closure, the compiler's standard inference algorithm is allowed to make the
-//!    decision.
+//!    captured instead, also when `x.y` is only used in an async block within the closure, which
+//!    then takes it from the closure's `x`. This does not influence whether `x` is captured by
+//!    value or by reference - if the closure is a `move` closure, it will still be captured by
+//!    value, and if it is a non-`move` closure, the compiler's standard inference algorithm is
+//!    allowed to make the decision. Since it captures `x` itself, it can't be combined with a
+//!    directive like `clone x` that captures a new value in its place. In methods, `all self` is
+//!    how the closure is given `self`, which is the only directive that `self` may be used with,
+//!    since it can't be rebound.
 //  - `rename x y` captures `y` outside the closure, but renames it to `x` and allows it to be
 //    accessed as `x` inside the body of the closure. This does not force all of `y` to be
 //    captured, and it does not influence whether `y` or any of its fields are captured by value or
 //    by reference. (not yet supported)
 //!
 //! To avoid surprises and compilation errors, if you specify a `clone` or `with` directive, then
-//! this macro will turn your closure into a move closure if it was not one already. Because of
-//! this, if your closure is a `move` closure - either because you explicitly marked it as such or
-//! because you used a `with` or `clone` directive - then you may additionally specify these
-//! directives:
-// FIXME: Decide if its not better to require that the user specify the `move` instead of
-// "inferring" it.
+//! this macro will turn your closure into a move closure if it was not one already (unless the
+//! `explicit_move` flag, described below, is given). The variables used without a directive are
+//! then moved into the closure too, and the `explain_move` flag points out the directive
+//! responsible when that leads to an error. Because of this, if your closure is a `move`
+//! closure - either because you explicitly marked it as such or because you used a `with` or
+//! `clone` directive - then you may additionally specify these directives:
 //!
 //!  - `ref x` captures `x` by immutable reference.
 //!  - `ref mut x` captures `x` by mutable reference.
 //!
-//! The `x` in all of these directives must simply be the name of a local variable. Some more
-//! complicated things may be supported in the future. There is at the moment also no support for
-//! combining directives. I will add this once I figure out a pretty and consistent way to do it.
+//! A future that borrows a variable through a `ref` directive can't be `'static`. When the future
+//! has to be, because of an `assert 'static` or because it is spawned as a task by a macro like
+//! `spawn_task!`, each `ref` directive gets a warning explaining this next to the borrow checker's
+//! error.
+//!
+//! The `x` in all of these directives must simply be the name of a local variable, which may be
+//! written as a raw identifier like `r#type`; `r#x` and `x` name the same variable. The variable
+//! may also have the name of a directive or flag, as in `clone clone` or `with all = all + 1`,
+//! since the keyword is always the first word of a directive. Some more complicated things may be
+//! supported in the future. There is at the moment also no support for combining directives. I
+//! will add this once I figure out a pretty and consistent way to do it.
+//!
+//! If the type of a variable with a `clone` directive doesn't implement `Clone`, the error points
+//! at the variable, and names the directive as the source of the requirement, even when the impl
+//! is only missing for a type parameter. Values that can't be cloned can still be captured by
+//! making them some other way, with a `with x = ...` directive.
+//!
+//! The directives only borrow the variables they name while the closure is created, except for
+//! `ref` directives, whose borrows last as long as the closure, and the temporaries in the
+//! expression of a `with` directive are dropped right after it. The closure owns the values of
+//! all of its directives, even those it only uses a field of, so they are dropped along with it,
+//! except for one that a later `with` directive uses, which may move it.
+//!
+//! A directive for a variable that the closure never mentions still does its work, for example
+//! keeping a clone of an `Arc` alive for as long as the closure is. Since that is usually left over
+//! from an edit, it causes a warning, unless the name starts with an underscore. A parameter of the
+//! closure with the same name as a directive's variable, as in `capture!(clone x, move |x| ...)`,
+//! hides the variable in the whole body, so it causes a warning at both of them too.
+//!
+//! A directive with a syntax error is reported and left out, and the closure is still expanded with
+//! the rest of the directives, so that completion and other IDE features keep working in its body
+//! while the directives are being written. The variables in the directives and the body, and the
+//! context of [`capture_ctx!`](#capture_ctx) if it is a path, keep their locations in the
+//! expansion, so an IDE can go from a use of `x` to the directive `clone x`, and from there to
+//! the variable that is cloned.
+//!
+//! In a long invocation, the directives may be put in brackets to set them apart from the closure,
+//! as in `capture!([clone a, ref b, with c = f()], move || ...)`. The brackets make no difference
+//! to what the directives do, and directives may also be written outside of them. The directives
+//! may also end with `=>` instead of a comma, which is the syntax of glib's `clone!`, as in
+//! `capture!(clone a, weak self => move || ...)`. A `with` directive may end with `;` instead,
+//! which sets a long expression apart from what follows, as in
+//! `capture!(with config = Config { .. }; clone a, move || ...)`.
 //!
 //! ## Mutability
 //!
@@ -153,12 +203,186 @@
 //! ```
 //! This will still emit a warning because the mutability of the variable `v` outside the closure is
 //! unused. Writing instead `let v = vec![1, 2];` would continue to compile and the warning would not
-//! be emitted.
+//! be emitted. The macro can't avoid the warning itself: only a mutable use of `v`, like `&mut v`,
+//! counts as using its mutability, and that would fail to compile where `v` is declared without
+//! `mut`. The warning is on the declaration, so an `#[allow(unused_mut)]` in the expansion
+//! wouldn't reach it either.
 //!
 //! `all` directives are not affected by this. Variables captured under such a directive, if
 //! captured by value, correctly inherit their mutability. As such, the `mut` prefix is not
 //! supported on these directives.
 //!
+//! ## Flags
+//!
+//! Besides directives, the list may also contain flags, which affect the macro's expansion as a
+//! whole instead of how any one variable is captured. Flags may appear anywhere in the list, but
+//! each may be specified at most once.
+//!
+//!  - `boxed` puts the closure into a `Box`. It may optionally be followed by a `dyn Trait` type,
+//!    in which case the box is additionally coerced to `Box<dyn Trait>`. This is useful for
+//!    storing callbacks:
+//!    ```
+//!    # use captures::capture;
+//!    # use std::rc::Rc;
+//!    struct Button {
+//!        on_click: Box<dyn FnMut(u32)>,
+//!    }
+//!
+//!    let clicks = Rc::new(std::cell::Cell::new(0u32));
+//!    let mut button = Button {
+//!        on_click: capture!(boxed dyn FnMut(u32), clone clicks, |n| {
+//!            clicks.set(clicks.get() + n);
+//!        }),
+//!    };
+//!    (button.on_click)(2);
+//!    assert_eq!(clicks.get(), 2);
+//!    ```
+//!  - `debug` shows the code that the macro expands to as a warning on the flag. The expansion is
+//!    not affected otherwise, so the flag can be added to any invocation while working on it and
+//!    removed again afterwards.
+//!  - `introspect` wraps the closure in a [`Captured`], which records the directives it was
+//!    created with and lists them in its `Debug` impl. This helps with finding out what a callback
+//!    keeps alive. The wrapper type lives in the [`captures-lite`] crate, which must be a
+//!    dependency for the flag to be used. The closure is reached through `Deref`:
+//!    ```
+//!    # use captures::capture;
+//!    # use std::rc::Rc;
+//!    let (db, n) = (Rc::new(1), 2);
+//!    let f = capture!(introspect, clone db, ref n, move || *db + *n);
+//!    assert_eq!(format!("{:?}", f), "Captured([clone db, ref n])");
+//!    assert_eq!((*f)(), 3);
+//!    ```
+//!  - `instrument` (feature `tracing`) captures the current `tracing` span when the closure is
+//!    created, and enters it at the start of every call, so that the closure's logs stay attached
+//!    to the span even when it is called from elsewhere. Async blocks are instead wrapped with
+//!    `tracing::Instrument::instrument`. The flag makes the closure `move`.
+//!  - `explicit_move` turns off the conversion into a `move` closure described above. Instead,
+//!    `clone` and `with` directives on a closure or async block that isn't `move` are an error
+//!    asking for the `move` to be added, so that it is always visible at the closure:
+//!    ```compile_fail
+//!    # use captures::capture;
+//!    let a = 1;
+//!    let f = capture!(explicit_move, clone a, || a); // error: add `move`
+//!    ```
+//!  - `move` makes the closure or async block `move`, so that this is decided next to the
+//!    directives rather than in the closure's header, as in `capture!(move, all a, || ...)`. Since
+//!    the `move` is written by hand, `explicit_move` accepts it.
+//!  - `deny_unused` turns the warnings about unused directives into errors. With several
+//!    closures, the directives are applied to each of them, so these aren't checked at all.
+//!  - `explain_move` warns when the closure is made `move`, naming the directive it was made
+//!    `move` for. Variables without a directive that are used after the closure, as `names` below,
+//!    then fail to compile with an error that only mentions the closure, and the warning explains
+//!    where the `move` came from:
+//!    ```compile_fail
+//!    # use captures::capture;
+//!    let names = vec!["a".to_string()];
+//!    let f = capture!(explain_move, with n = 1, || names.len() + n); // warning: for `with n`
+//!    println!("{} {:?}", f(), names); // error: borrow of moved value
+//!    ```
+//!  - `lint_copy` warns about `clone` directives on values that are `Copy`, including references,
+//!    which a `move` closure already copies without a directive. Values of a type parameter are
+//!    only reported if the parameter is bounded by `Copy`:
+//!    ```
+//!    # #![allow(deprecated)]
+//!    # use captures::capture;
+//!    let a = 1;
+//!    let f = capture!(lint_copy, clone a, move || a); // warning: `a` is `Copy`
+//!    # assert_eq!(f(), 1);
+//!    ```
+//!  - `report` lists the directives of the invocation in a warning at the flag, such as
+//!    "`capture_only!` with clone a, all b; no other variables can be used", to check what a
+//!    long-lived callback holds on to without reading the whole directive list, or any groups
+//!    and defaults it pulls in.
+//!  - `strict_macros`, only for [`capture_only!`](#capture_only), rejects the macros in the body
+//!    whose arguments it can't check, for when the guarantee that nothing else is captured
+//!    has to hold without exceptions. Allowed are the std macros that it checks, macros like
+//!    `stringify!` and `concat!` that are passed no variables, and this crate's own macros.
+//!  - `trust path!` and `deny path!`, only for [`capture_only!`](#capture_only), change how it
+//!    treats a macro in the body. The tokens passed to a trusted macro keep their hygiene, apart
+//!    from the locals of the closure, so they can use variables without a directive, and the
+//!    macro is allowed with `strict_macros`. A denied macro, like `deny serde_json::json!`, is an
+//!    error wherever the body uses it. The paths match if one ends with the other, so
+//!    `trust log_ctx!` also covers `my_logging::log_ctx!`:
+//!    ```
+//!    # use captures::capture_only;
+//!    macro_rules! log_ctx {
+//!        ($ctx:expr, $msg:expr) => { format!("[{}] {}", $ctx, $msg) };
+//!    }
+//!    let ctx = "request";
+//!    let id = 7;
+//!    let f = capture_only!(trust log_ctx!, clone id, move || log_ctx!(ctx, id));
+//!    assert_eq!(f(), "[request] 7");
+//!    ```
+//!
+//! [`Captured`]: https://docs.rs/captures-lite/latest/captures_lite/struct.Captured.html
+//!
+//! ## Asserting bounds
+//!
+//! A closure that needs to be `Send` usually only finds out when it is passed to a function
+//! requiring that, and the resulting error points at the closure as a whole. The macro input may
+//! end with `; assert` followed by bounds, which are then checked right away:
+//! ```
+//! # use captures::capture;
+//! # use std::sync::Arc;
+//! let state = Arc::new(1);
+//! let f = capture!(clone state, move || *state + 1; assert Send + Sync + 'static);
+//! # assert_eq!(f(), 2);
+//! ```
+//! The bounds are checked for each variable captured via a directive, with errors pointing at that
+//! variable, and then for the closure as a whole. That makes them a good fit for bounds like `Send`
+//! and `'static` that closures get from their captures, but not for bounds like `Fn()`. The bounds
+//! may also not refer to generic parameters of the surrounding function.
+//!
+//! To check these properties for a closure that has already been created, for example in a test
+//! making sure that a callback can still be moved to another thread, there are also the
+//! `assert_static!`, `assert_send_closure!`, and `assert_sync_closure!` macros. They take the
+//! closure by reference, and fail to compile if it doesn't have the property:
+//! ```
+//! # use captures::{assert_send_closure, assert_static, capture};
+//! # use std::sync::Arc;
+//! let state = Arc::new(1);
+//! let f = capture!(clone state, move || *state + 1);
+//! assert_send_closure!(f);
+//! assert_static!(f);
+//! # assert_eq!(f(), 2);
+//! ```
+//!
+//! ## Several closures
+//!
+//! Instead of a single closure, the macros also accept a parenthesized list of closures and async
+//! blocks, and then produce a tuple. The directives are applied to each of them separately, so
+//! every closure gets its own clones, and `with` expressions are evaluated once per closure:
+//! ```
+//! # use captures::capture;
+//! # use std::sync::mpsc;
+//! let (tx, rx) = mpsc::channel();
+//! let (on_success, on_error) = capture!(clone tx, (
+//!     move |v: i32| tx.send(Ok(v)).unwrap(),
+//!     move |e: &str| tx.send(Err(e.to_string())).unwrap(),
+//! ));
+//! on_success(1);
+//! on_error("oops");
+//! assert_eq!(rx.recv().unwrap(), Ok(1));
+//! ```
+//!
+//! ## Closures from other macros
+//!
+//! The closure can also be produced by a macro invocation. The directives then only introduce
+//! their variables around the invocation, and everything else is left to the macro:
+//! ```
+//! # use captures::capture;
+//! # use std::rc::Rc;
+//! macro_rules! adder {
+//!     ($v:ident) => { move |x: i32| x + *$v };
+//! }
+//! let v = Rc::new(1);
+//! let f = capture!(clone v, adder!(v));
+//! assert_eq!(f(2), 3);
+//! # drop(v);
+//! ```
+//! Since the macro's expansion can't be seen, the closure it produces must be `move` itself for
+//! the directives to have any effect, and `all` directives and `capture_only!` aren't supported.
+//!
 //! # `capture_only`
 //!
 //! The `capture_only` macro behaves exactly like the `capture` macro, with the exception that it
@@ -176,6 +400,12 @@
 //! assert_eq!(b, 11);
 //! ```
 //! does not compile, with an error message indicating that there is no local variable `b`.
+//! Alongside it, a warning names the macro and suggests a directive to add, like `all b`. If
+//! several variables are missing, the one warning lists them all, along with the directives to
+//! paste into the macro, like `all b, all c`. Like the warnings of
+//! [`capture_lint`](#migrating-with-capture_lint), it is a deprecation warning found by a
+//! heuristic, so names that are only passed as function arguments, like `parse` in `map(parse)`,
+//! are left out, since they are usually functions rather than variables.
 //! Switching `capture_only` to `capture` would allow the above code to compile. If you would like
 //! to indicate that `b` may also be captured, but do not want to add any restrictions on how, you
 //! can add an `all` directive:
@@ -190,31 +420,544 @@
 //! assert_eq!(f(), 2);
 //! assert_eq!(b, 11);
 //! ```
+//! The restriction covers the whole body, so closures and async blocks nested in it can't use a
+//! variable without a directive either, even when only they mention it.
 //!
-use proc_macro2::TokenStream;
-use quote::quote;
-
-/// Takes a place with type having `.set_span(_)` and `.span()` methods
-macro_rules! make_mixed {
-    ($i:expr) => {
-        let e = &mut $i;
-        e.set_span(e.span().resolved_at(::proc_macro2::Span::mixed_site()));
-    };
-}
+//! Macros invoked in the body, including nested uses of this crate's macros, see the same
+//! variables as the code around them, so an inner `capture!` can clone anything the outer closure
+//! has a directive for. The arguments of well-known std macros, like `assert_eq!`, `matches!`,
+//! `vec!`, `dbg!` and `write!` and the other formatting macros, are checked like the rest of the
+//! body instead, including the variables used inline in a format string, like `b` in `"{b}"`.
+//! Other macros get the variables with directives, but could still refer to others in the code
+//! that they expand to; the `strict_macros` flag makes such macros an error, and the `trust` and
+//! `deny` flags allow or forbid a single macro. Items defined in the body, like functions,
+//! structs, impls, `use` items and `macro_rules!` macros, are left as written, since they can't use
+//! the variables of the closure anyway. Only plain names like `b` can refer to variables, so paths
+//! like `Self::new()`, `T::default()` or `Shape::Line`, and `Self` itself, always work, as do
+//! attributes like `#[allow(...)]` and `#[cfg(...)]` on statements. A `let` with a `cfg`
+//! attribute shadows a variable with a directive only when it is compiled, and the code that a
+//! `cfg` may remove isn't checked for variables without a directive. Panics, `line!()` and
+//! `#[track_caller]` functions in the body report the location they are written at, like they
+//! would without the macro.
+//!
+//! Variables bound in the body shadow those with directives only where the compiler has them in
+//! scope. In a let chain, the variables of a pattern are used by the conditions after it, while
+//! the `else` branch still sees the captured variable:
+//! ```edition2024
+//! # use captures::capture_only;
+//! let a = Some(1);
+//! let f = capture_only!(all a, |p: Option<(i32, Option<i32>)>| {
+//!     if let Some((a, b)) = p && a > 0 && let Some(b) = b {
+//!         a + b
+//!     } else {
+//!         a.unwrap_or(0)
+//!     }
+//! });
+//! assert_eq!(f(Some((2, Some(3)))), 5);
+//! assert_eq!(f(Some((-2, Some(3)))), 1);
+//! ```
+//!
+//! ## Migrating with `capture_lint`
+//!
+//! Switching a large codebase to `capture_only` at once is disruptive, since every missing
+//! directive is an error. `capture_lint` instead expands like `capture`, and reports the variables
+//! that are captured without a directive as warnings:
+//! ```
+//! # #![allow(deprecated)]
+//! # use captures::capture_lint;
+//! # use std::rc::Rc;
+//! let a = Rc::new(1);
+//! let b = 2;
+//! let f = capture_lint!(clone a, move || *a + b); // warning: `b` is captured without a directive
+//! assert_eq!(f(), 3);
+//! ```
+//! The warnings are deprecation warnings, since that is the only kind of warning a macro can emit
+//! on stable, so `#[deny(deprecated)]` turns them into errors. Which names are local variables is
+//! only known after the macro has run, so the check is an approximation: it reports the names
+//! that don't start with an uppercase letter, in any script, that are used in the closure without
+//! being bound in it, other than those that are only called, like functions. A function that is
+//! used as a value is reported too, and can be allowed with an `all` directive.
+//!
+//! # `capture_ctx`
+//!
+//! It is common to have a "context" struct holding many handles, only some of which are needed by
+//! a given closure. The `capture_ctx` macro takes such a context, followed by `=>` and then the
+//! usual directives and closure. The variables in the `clone` and `ref` directives (and `weak` and
+//! `strong`, if enabled) then refer to fields of the context, and are available in the closure
+//! under the same name:
+//! ```
+//! # use captures::capture_ctx;
+//! # use std::sync::Arc;
+//! struct Ctx {
+//!     db: Arc<Vec<i32>>,
+//!     metrics: Vec<i32>,
+//! }
+//!
+//! let mut ctx = Ctx { db: Arc::new(vec![1]), metrics: vec![] };
+//! let mut handler = capture_ctx!(ctx => clone db, ref mut metrics, move |x: i32| {
+//!     metrics.push(db[0] + x);
+//! });
+//! handler(1);
+//! assert_eq!(ctx.metrics, [2]);
+//! ```
+//! The context expression is repeated for each directive, so it should be a place expression like
+//! `ctx` or `self.ctx` rather than a function call. `with` directives work as they always do, and
+//! `all` directives are not supported.
+//!
+//! # Directive groups
+//!
+//! When many closures need the same directives, they can be given a name once with
+//! `define_captures!`, and then pulled into any invocation with `use`:
+//! ```
+//! # use captures::{capture, define_captures};
+//! # use std::rc::Rc;
+//! define_captures!(handler_env = clone db, clone cfg);
+//!
+//! let db = Rc::new(1);
+//! let cfg = Rc::new(2);
+//! let on_click = capture!(use handler_env, || *db + *cfg);
+//! let on_hover = capture!(use handler_env, with n = 3, || *db + *cfg + n);
+//! assert_eq!(on_click() + on_hover(), 9);
+//! ```
+//! The directives in the group refer to the variables that are in scope where the group is used,
+//! not where it is defined. A group is a `macro_rules!` macro under the hood, so it follows the
+//! same scoping rules and must be defined before it is used; attributes such as `#[macro_export]`
+//! may be put in front of its name. For the same reason, a group can't share its name with a macro
+//! from the standard prelude, like `env` or `line`. Groups may themselves `use` other groups, but
+//! can't be used in the `#[capture(...)]` attribute.
+//!
+//! # Default directives
+//!
+//! In modules with lots of callbacks, the same few handles tend to be cloned into nearly all of
+//! them. `capture_defaults!` takes a list of directives followed by a `;`, and then any items or
+//! statements. Every macro from this crate invoked in there uses the default directives for the
+//! variables that it refers to but doesn't have a directive for:
+//! ```
+//! # use captures::{capture, capture_defaults};
+//! # use std::rc::Rc;
+//! let log = Rc::new(Vec::<i32>::new());
+//! let cfg = Rc::new(5);
+//! capture_defaults! {
+//!     clone log, clone cfg;
+//!
+//!     // Uses `clone log` and `clone cfg`
+//!     let a = capture!(|| log.len() + *cfg);
+//!     // Uses `clone cfg`, but the reference to `log` is given explicitly
+//!     let b = capture!(ref log, move || log.len() + *cfg);
+//! }
+//! assert_eq!(a() + b(), 10);
+//! ```
+//! Whether a variable is referred to is decided by looking for its name anywhere in the closure,
+//! so a field or method of the same name also counts. When `capture_defaults!` is nested, the
+//! innermost default for a variable wins. Only `ref`, `clone`, and `with` directives (and `weak`
+//! and `strong`, if enabled) can be defaults, and the `#[capture(...)]` attribute doesn't pick them
+//! up.
+//!
+//! # `bind`
+//!
+//! The `bind` macro applies the directives to a plain block instead of a closure. The variables are
+//! bound right before the block, so that it can use them:
+//! ```
+//! # use captures::bind;
+//! # use std::rc::Rc;
+//! let config = Rc::new(String::from("verbose"));
+//! let len = bind!(clone config, with n = config.len() * 2, {
+//!     // `config` is a new `Rc` here
+//!     assert_eq!(Rc::strong_count(&config), 2);
+//!     n
+//! });
+//! assert_eq!(len, 14);
+//! ```
+//! Since nothing is moved into a block, `ref` directives may be used freely.
+//!
+//! # `capture_fn`
+//!
+//! The type of a closure can't be named, which is a problem for libraries whose public APIs need to
+//! mention their callbacks. The `capture_fn` macro instead generates a struct holding the captures,
+//! with a constructor and a method that runs the closure body. Since the struct is an item, the
+//! directives need the types of the captured values:
+//! ```
+//! # use captures::capture_fn;
+//! # use std::rc::Rc;
+//! capture_fn!(pub struct Counter, clone step: Rc<u32>, with mut count: u32 = 0, |n: u32| -> u32 {
+//!     *count += **step * n;
+//!     *count
+//! });
+//!
+//! let step = Rc::new(2);
+//! let mut counter = Counter::new(&step);
+//! assert_eq!(counter.call_mut(1), 2);
+//! assert_eq!(counter.call_mut(3), 8);
+//! ```
+//! `new` takes a reference for each `clone` directive and a value for each `with` directive that
+//! isn't given an initial value. The method is `call(&self, ...)`, or `call_mut(&mut self, ...)` if
+//! any of the directives are `mut`; in its body, the captures are references to the fields. Only
+//! `clone` and `with` directives are supported, and the arguments of the closure need types.
+//!
+//! # The `captures` attribute
+//!
+//! Function-like macros can be awkward to use in the middle of long builder chains. As an
+//! alternative, the `#[captures]` attribute can be put on a function or other item, after which the
+//! closures and async blocks inside of it may be annotated with `#[capture(...)]` or
+//! `#[capture_only(...)]`. The arguments of these annotations are the directives, and the annotated
+//! closure is expanded exactly as if it had been passed to the corresponding macro:
+//! ```
+//! # use captures::captures;
+//! # use std::rc::Rc;
+//! #[captures]
+//! fn total(values: &[i32]) -> i32 {
+//!     let offset = Rc::new(1);
+//!     values
+//!         .iter()
+//!         .map(#[capture(clone offset)] |v| v + *offset)
+//!         .sum()
+//! }
+//! assert_eq!(total(&[1, 2]), 5);
+//! ```
+//! The annotations may also be put on a `let` statement, in which case they apply to the closure or
+//! async block that the variable is initialized with:
+//! ```
+//! # use captures::captures;
+//! # use std::rc::Rc;
+//! #[captures]
+//! fn adder(offset: &Rc<i32>) -> impl Fn(i32) -> i32 {
+//!     #[capture(with offset = Rc::clone(offset))]
+//!     let f = move |v| v + *offset;
+//!     f
+//! }
+//! assert_eq!(adder(&Rc::new(1))(2), 3);
+//! ```
+//! On nightly, with `#![feature(proc_macro_hygiene)]`, a `let` statement can also be annotated
+//! directly, without an attribute on the surrounding item. The attribute is then `#[captures]` with
+//! the directives as arguments, since `capture` already names the function-like macro:
+//! ```ignore
+//! #[captures(clone a, weak self)]
+//! let f = |x| self.handle(x, &a);
+//! ```
+//!
+//! # `deny_implicit_captures`
+//!
+//! The `#[deny_implicit_captures]` attribute can be put on a function, impl block, or module, and
+//! turns every closure inside of it that is not written via `capture!`, `capture_only!`, or one of
+//! the other macros in this crate into an error. Closures annotated for use with `#[captures]` are
+//! also accepted. This makes it possible to enforce, as a local policy, that every closure states
+//! what it captures:
+//! ```compile_fail
+//! # use captures::deny_implicit_captures;
+//! #[deny_implicit_captures]
+//! fn sum(values: &[i32]) -> i32 {
+//!     values.iter().map(|v| v + 1).sum() // error
+//! }
+//! ```
+//! ```
+//! # use captures::{capture, deny_implicit_captures};
+//! #[deny_implicit_captures]
+//! fn sum(values: &[i32]) -> i32 {
+//!     values.iter().map(capture!(|v| v + 1)).sum() // fine
+//! }
+//! ```
+//! The attribute is applied to the item as written, so it cannot see closures produced by other
+//! macros.
+//!
+//! # Spawning
+//!
+//! The directives are frequently used for closures which are immediately handed off to another
+//! thread or task. To avoid the extra layer of nesting, the crate provides feature-gated
+//! macros which take the same input as `capture!` and spawn the result:
+//!
+//!  - `spawn!` (feature `thread`) runs the closure on a new `std::thread`.
+//!  - `spawn_task!` (feature `tokio`) spawns the async block as a tokio task.
+//!  - `spawn_blocking!` (feature `tokio`) runs the closure on tokio's blocking thread pool.
+//!  - `spawn_async_std!` (feature `async-std`) spawns the async block as an async-std task.
+//!  - `spawn_smol!` (feature `smol`) spawns the async block as a smol task.
+//!
+//! # wasm-bindgen
+//!
+//! With the `wasm-bindgen` feature enabled, the `wasm_closure!` and `wasm_closure_once!` macros
+//! apply the directives to a closure and turn it into a `wasm_bindgen::closure::Closure`, taking
+//! care of the boxing and casting that `Closure::wrap` requires.
+//!
+//! # FFI callbacks
+//!
+//! C libraries usually take callbacks as a function pointer along with a `void *` that is passed
+//! back to the function. `capture_extern!` applies the directives to a closure, boxes it, and
+//! returns a tuple of the pointer to the box, an `unsafe extern "C"` function calling the closure,
+//! and one dropping it. The functions take the pointer as their last argument, after those of the
+//! closure, whose types must be written out. The return type is inferred:
+//! ```
+//! # use captures::capture_extern;
+//! # use std::{ffi::c_void, rc::Rc};
+//! # unsafe fn register(
+//! #     cb: unsafe extern "C" fn(i32, *mut c_void) -> i32,
+//! #     drop: unsafe extern "C" fn(*mut c_void),
+//! #     data: *mut c_void,
+//! # ) -> i32 {
+//! #     let r = cb(1, data);
+//! #     drop(data);
+//! #     r
+//! # }
+//! let offset = Rc::new(10);
+//! let (data, call, destroy) = capture_extern!(clone offset, |x: i32| x + *offset);
+//! assert_eq!(unsafe { register(call, destroy, data) }, 11);
+//! ```
+//! The closure must be `'static`, and so can't borrow anything, but may be `FnMut`. Calling the
+//! functions is up to the C code, which must not use the pointer after it was passed to the
+//! function dropping it, and must not call the closure from several threads at once unless it is
+//! `Sync`. A panic in the closure aborts the process, since it can't unwind into the C code.
+//!
+//! # glib
+//!
+//! With the `glib` feature enabled, two further directives are available, making it possible to
+//! use this crate in place of `glib::clone!` in gtk-rs applications:
+//!
+//!  - `strong x` captures a clone of `x`, exactly like `clone x`.
+//!  - `weak x` captures a weak reference to `x`, created via `glib::clone::Downgrade`. Each time
+//!    the closure is called, the reference is upgraded via `glib::clone::Upgrade` and the result is
+//!    available as `x` in the body. If the upgrade fails, the closure returns immediately. Since a
+//!    block has no closure to return from, `weak` can't be used with `bind!`.
+//!
+//! Like `clone`, both of these turn the closure into a `move` closure, and both accept a `mut`
+//! prefix. When an upgrade fails the closure returns `()` by default; if the closure returns
+//! something else, specify the value to return with the `default_return = expr` flag:
+//! ```ignore
+//! # use captures::capture;
+//! let label = gtk::Label::new(None);
+//! let counter = std::rc::Rc::new(std::cell::Cell::new(0));
+//! button.connect_clicked(capture!(weak label, strong counter, move |_| {
+//!     counter.set(counter.get() + 1);
+//!     label.set_text(&counter.get().to_string());
+//! }));
+//! window.connect_close_request(capture!(weak label, default_return = glib::Propagation::Proceed,
+//!     move |_| {
+//!         label.set_text("closing");
+//!         glib::Propagation::Stop
+//!     }
+//! ));
+//! ```
+//! The traits are reached through `::glib`, which needs a direct dependency on the `glib` crate.
+//! Applications that only depend on `gtk4`, and so use `gtk::glib`, pass that path with the
+//! `glib = path` flag, which can also be put in a [directive group](#directive-groups):
+//! ```ignore
+//! # use captures::{capture, define_captures};
+//! define_captures!(gtk_glib = glib = gtk::glib);
+//!
+//! button.connect_clicked(capture!(use gtk_glib, weak label, move |_| label.set_text("clicked")));
+//! ```
+//!
+//! # PyO3
+//!
+//! With the `pyo3` feature enabled, the `py_closure!` macro applies the directives to a closure
+//! and passes it to `pyo3::types::PyCFunction::new_closure`, producing a function that can be
+//! handed to Python. The GIL token comes first, followed by `=>`, like the context of
+//! `capture_ctx!`. The closure is called with the positional and keyword arguments:
+//! ```ignore
+//! # use captures::py_closure;
+//! # use pyo3::prelude::*;
+//! # fn f(py: Python<'_>, callbacks: &Bound<'_, PyAny>, handler: Py<PyAny>) -> PyResult<()> {
+//! let prefix = String::from("got");
+//! let f = py_closure!(py => clone prefix, clone_ref handler, |args, _kwargs| {
+//!     let py = args.py();
+//!     handler.call1(py, (format!("{} {}", prefix, args.len()),))
+//! })?;
+//! callbacks.call_method1("append", (f,))?;
+//! # Ok(())
+//! # }
+//! ```
+//! Python objects such as `Py<PyAny>` should be captured with the `clone_ref` directive, which
+//! clones them via `Py::clone_ref` using the GIL token, since their `Clone` impl may be
+//! unavailable. The token itself isn't captured, so the closure remains `Send + 'static` as PyO3
+//! requires; a token for use in the body can be had from the arguments, as above.
+//!
+//! # Tracing captures
+//!
+//! Callbacks that are never dropped keep everything they captured alive. To find them, the `trace`
+//! feature makes every closure and async block log its creation at the `trace` level via the `log`
+//! crate, with its location and directives, e.g. `closure at src/ui.rs:12 created, capturing
+//! clone db, with mut count`. `move` closures and async blocks additionally capture a value that
+//! logs their drop. Closures that aren't `move` only borrow, and so only log their creation.
+//!
+//! The crate using the macros must depend on `log`. Since the value logging the drop is `Clone`
+//! but not `Copy`, closures and async blocks that would otherwise be `Copy` aren't with this
+//! feature, which is why it is meant for debugging builds only.
+//!
+//! # `capture_async`
+//!
+//! Async blocks suffer from the same capture problems as closures. All of the macros in this crate
+//! accept an async block in place of the closure, and treat it exactly as they would a closure: the
+//! directives are applied, and `clone` and `with` directives turn the block into an `async move`
+//! block. The `capture_async` macro additionally boxes and pins the resulting future, which is what
+//! is usually needed when implementing trait methods that return a boxed future:
+//! ```
+//! # use std::{future::Future, pin::Pin, rc::Rc};
+//! use captures::capture_async;
+//!
+//! struct Counter {
+//!     step: Rc<i32>,
+//! }
+//!
+//! impl Counter {
+//!     fn next(&self, current: i32) -> Pin<Box<dyn Future<Output = i32>>> {
+//!         capture_async!(with step = self.step.clone(), async {
+//!             current + *step
+//!         })
+//!     }
+//! }
+//! ```
+//!
+//! # Unstable syntax
+//!
+//! Closure bodies may use unstable syntax like `try` blocks. On nightly, the macros also accept
+//! coroutine closures, including `static` ones. The `#[coroutine]` attribute that these need is
+//! kept on the closure, and the resume arguments may be used in the body as usual, including with
+//! `capture_only!`:
+//! ```ignore
+//! let mut co = capture_only!(clone a, #[coroutine] |mut x: i32| loop {
+//!     x = yield x + *a;
+//! });
+//! ```
+//! `gen` blocks, including `async gen` blocks, may be used in place of an async block with
+//! `capture!` and the other macros that take either a closure or an async block, and are made
+//! `move` by the same directives:
+//! ```ignore
+//! let evens = capture!(clone limit, gen {
+//!     for i in (0..*limit).step_by(2) {
+//!         yield i;
+//!     }
+//! });
+//! ```
+//!
+//! # Warnings
+//!
+//! The code that the macros expand to causes no warnings of its own, in any edition, so they can
+//! be used in crates with `#![deny(warnings)]`. The only warnings are the ones described here, like
+//! those about unused directives, and those the compiler reports about the code as written, like an
+//! unused `mut` on a directive.
+//!
+//! ## Pedantic warnings
+//!
+//! With the `pedantic` feature, a `capture!` or `capture_ctx!` without any directives, flags or
+//! bounds, like `capture!(|| a + 1)`, causes a warning, since it returns the closure unchanged.
+//! Such invocations are often left over from a refactoring; the warning suggests removing the
+//! macro or adding directives. `capture_only!` and the other macros still do something without
+//! directives, so they aren't warned about.
+//!
+//! ## Compiler warnings on nightly
+//!
+//! With the `nightly-diagnostics` feature, which needs a nightly compiler, the warnings about
+//! unused directives, those of `capture_lint!`, those explaining errors in `capture_only!` and
+//! those of the `explain_move` flag are emitted as compiler warnings through
+//! `proc_macro::Diagnostic`, instead of as deprecation warnings. They then read like the other
+//! warnings, and the suggestions, like adding an `all b` directive, are shown as a separate help
+//! message. These can't be applied by `cargo fix`, since `proc_macro::Diagnostic` has no way of
+//! attaching suggested code. They also can't be silenced with `#[allow(deprecated)]`. The warning
+//! of `lint_copy` depends on types, so it stays a deprecation warning.
+//!
+//! # Inside `macro_rules!`
+//!
+//! The macros can be used from `macro_rules!` macros, with the usual hygiene. Variables and
+//! closures passed in as `$ident` or `$expr` fragments keep referring to the caller's variables,
+//! and variables introduced inside the macro can be captured too:
+//! ```
+//! # use captures::capture;
+//! # use std::rc::Rc;
+//! macro_rules! counter {
+//!     ($start:expr, $step:ident) => {{
+//!         let count = Rc::new(std::cell::Cell::new($start));
+//!         capture!(clone count, ref $step, move || count.replace(count.get() + *$step))
+//!     }};
+//! }
+//! let step = 2;
+//! let next = counter!(1, step);
+//! assert_eq!((next(), next()), (1, 3));
+//! ```
+//! Directives written inside the macro, including a `use` of a directive group, refer to the
+//! variables visible inside the macro, just like any other code in it. Directive groups and the
+//! defaults of `capture_defaults!` have some caveats, since they work by naming macros:
+//! - The macro defined by `define_captures!` invokes `::captures::capture!` and the like, so the
+//!   crate using a group must depend on `captures` under that name, even if its macros are
+//!   re-exported from another crate.
+//! - `capture_defaults!` only sees invocations written out within its scope, by the name of one of
+//!   this crate's macros. Invocations that are produced by a macro defined elsewhere, or that go
+//!   through a renamed import, don't get the defaults.
+//!
+//! # `no_std`
+//!
+//! The macros can be used in `#![no_std]` crates. The code they generate only refers to `core`,
+//! and to `alloc` for `capture_async!`, `capture_extern!` and the `boxed` flag, which declare the
+//! `alloc` crate themselves, so that it doesn't have to be declared by the crate using them. These
+//! three are behind the default `alloc` feature; crates for targets without an allocator can
+//! disable it to make sure that nothing allocates. The `spawn!` macro and the `wasm-bindgen` and
+//! `pyo3` features need `std`, or the allocator, as the libraries they wrap do.
+//!
+//! Every path in the generated code starts with `::`, like `::core::clone::Clone`, so the macros
+//! also work in modules with `#[no_implicit_prelude]`.
+//!
+//! # Snapshot testing
+//!
+//! `expand_str!` takes an invocation of one of the other macros and produces its expansion as a
+//! string literal, which can be compared against a snapshot to make sure that an upgrade of this
+//! crate doesn't change what a closure captures:
+//! ```
+//! # use captures::expand_str;
+//! const EXPANSION: &str = expand_str!(capture!(ref a, with b = 1, move || *a + b));
+//! # #[cfg(not(feature = "trace"))]
+//! assert_eq!(
+//!     EXPANSION,
+//!     "{ # [ allow ( clippy :: shadow_same , clippy :: shadow_reuse , clippy :: shadow_unrelated ) ] \
+//!      let a = & a ; # [ allow ( clippy :: shadow_same , clippy :: shadow_reuse , \
+//!      clippy :: shadow_unrelated ) ] let b = 1 ; move | | { * a + b } }",
+//! );
+//! ```
+//! The tokens are separated by single spaces, so that the strings don't depend on the compiler
+//! version. The `allow` attributes keep clippy's shadowing lints, which some crates enable, from
+//! firing on the variables that the expansion rebinds. The invocation is only expanded, and the
+//! variables it names need not exist. Snapshots should be taken without the `trace` feature, which
+//! adds its logging to every expansion.
+//!
+//! # Using the directives in other macros
+//!
+//! The parsing and expansion of the directives lives in the [`captures-core`] crate, so that other
+//! procedural macros can accept the same syntax and hand it back to this crate's implementation.
+//! Such a macro can also add directives of its own, like a `db_handle x` that captures a handle
+//! to a database, through the `CustomDirective` trait of that crate.
+//!
+//! [`captures-core`]: https://docs.rs/captures-core
+//!
+//! # Compile times
+//!
+//! Being a procedural macro, this crate depends on `syn`. Crates that only need `clone`, `with`,
+//! and `ref` directives in `capture!` can use [`captures-lite`] instead, which implements those
+//! with `macro_rules!` and has no dependencies. The syntax is the same, so switching back only
+//! requires changing the import. A feature of this crate can't do the same, since it would still
+//! have to build `syn` for the other macros whenever any crate in the build enables them.
+//!
+//! To find out how much the macros add to the build time, the `profile` feature makes each
+//! invocation log the time spent expanding it, and how much of that was spent parsing the input:
+//! ```text
+//! capture_only! at src/handlers.rs:42 took 412.3µs, 180.9µs of it parsing
+//! ```
+//! The lines go to stderr, or are appended to the file named by the `CAPTURES_PROFILE` environment
+//! variable, which is easier to sum up over a whole build. Since cargo doesn't know about the
+//! variable, the crates to measure have to be rebuilt, for example after a `cargo clean -p`. The
+//! locations need Rust 1.88 or later, and are left out on older compilers. The time to compile the
+//! expanded code isn't included.
+//!
+//! [`captures-lite`]: https://docs.rs/captures-lite
 
-mod changes;
-mod clean;
-mod parse;
+use captures_core::Kind;
 
-use changes::*;
-use parse::*;
+mod asserts;
+mod attr;
+mod defaults;
+mod named;
+mod snapshot;
 
 /// Captures variables into a closure with special semantics.
 ///
 /// See the [crate level documentation][`crate`] for more info.
 #[proc_macro]
 pub fn capture(inp: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    main(inp.into(), false).into()
+    captures_core::expand(inp.into(), Kind::Capture).into()
 }
 
 /// Captures only the listed variables into the closure.
@@ -222,51 +965,299 @@ pub fn capture(inp: proc_macro::TokenStream) -> proc_macro::TokenStream {
 /// See the [crate level documentation][`crate`] for more info.
 #[proc_macro]
 pub fn capture_only(inp: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    main(inp.into(), true).into()
+    captures_core::expand(inp.into(), Kind::CaptureOnly).into()
 }
 
-fn main(inp: TokenStream, only: bool) -> TokenStream {
-    let parsed: Input = match syn::parse2::<Input>(inp) {
-        Ok(x) => x,
-        Err(e) => return e.into_compile_error(),
-    };
+/// Captures variables into an async block and boxes the resulting future.
+///
+/// See the [crate level documentation][`crate`] for more info.
+///
+/// Only available with the `alloc` feature, which is enabled by default.
+#[cfg(feature = "alloc")]
+#[proc_macro]
+pub fn capture_async(inp: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    captures_core::expand(inp.into(), Kind::CaptureAsync).into()
+}
 
-    let Changes {
-        exterior,
-        interior,
-        exempt,
-    } = Changes::from_input(&parsed, only);
-    let syn::ExprClosure {
-        attrs,
-        asyncness,
-        movability,
-        capture,
-        or1_token,
-        inputs,
-        or2_token,
-        output,
-        mut body,
-    } = parsed.closure;
+/// Captures variables into a closure and runs it on a new thread.
+///
+/// The directives are applied to the closure as with [`capture!`], and the closure is then passed
+/// to `std::thread::spawn`. The macro evaluates to the thread's `JoinHandle`. A `name = expr` flag
+/// may additionally be given, in which case the thread is created with that name via
+/// `std::thread::Builder`:
+/// ```
+/// # use captures::spawn;
+/// # use std::sync::mpsc;
+/// let (tx, rx) = mpsc::channel();
+/// let handle = spawn!(name = "worker", clone tx, || {
+///     tx.send(std::thread::current().name().map(String::from)).unwrap();
+/// });
+/// handle.join().unwrap();
+/// assert_eq!(rx.recv().unwrap().as_deref(), Some("worker"));
+/// ```
+///
+/// Only available with the `thread` feature.
+#[cfg(feature = "thread")]
+#[proc_macro]
+pub fn spawn(inp: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    captures_core::expand(inp.into(), Kind::Spawn).into()
+}
+
+/// Captures variables into an async block and spawns it onto the tokio runtime.
+///
+/// The directives are applied to the async block as with [`capture!`], and the future is then
+/// passed to `tokio::spawn`. The macro evaluates to the task's `JoinHandle`:
+/// ```
+/// # use captures::spawn_task;
+/// # use std::sync::Arc;
+/// # let rt = tokio::runtime::Builder::new_current_thread().build().unwrap();
+/// # rt.block_on(async {
+/// let state = Arc::new(1);
+/// let handle = spawn_task!(clone state, async { *state + 1 });
+/// assert_eq!(handle.await.unwrap(), 2);
+/// # });
+/// ```
+///
+/// The `Send` and `'static` requirements of `tokio::spawn` are checked at the async block itself,
+/// so that errors point into the macro input instead of at the macro as a whole.
+///
+/// Only available with the `tokio` feature.
+#[cfg(feature = "tokio")]
+#[proc_macro]
+pub fn spawn_task(inp: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    captures_core::expand(inp.into(), Kind::SpawnTask).into()
+}
+
+/// Captures variables into a closure and runs it on tokio's blocking thread pool.
+///
+/// The directives are applied to the closure as with [`capture!`], and the closure is then passed
+/// to `tokio::task::spawn_blocking`. The macro evaluates to the task's `JoinHandle`:
+/// ```
+/// # use captures::spawn_blocking;
+/// # use std::sync::{Arc, Mutex};
+/// # let rt = tokio::runtime::Builder::new_current_thread().build().unwrap();
+/// # rt.block_on(async {
+/// let pool = Arc::new(Mutex::new(vec![1, 2]));
+/// let config = Arc::new(String::from("config"));
+/// let handle = spawn_blocking!(clone pool, clone config, || {
+///     pool.lock().unwrap().push(config.len());
+/// });
+/// handle.await.unwrap();
+/// assert_eq!(*pool.lock().unwrap(), [1, 2, 6]);
+/// # });
+/// ```
+///
+/// As with [`spawn_task!`], the `Send` and `'static` requirements are checked at the closure.
+///
+/// Only available with the `tokio` feature.
+#[cfg(feature = "tokio")]
+#[proc_macro]
+pub fn spawn_blocking(inp: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    captures_core::expand(inp.into(), Kind::SpawnBlocking).into()
+}
+
+/// Captures variables into an async block and spawns it as an async-std task.
+///
+/// This is the equivalent of [`spawn_task!`] for `async_std::task::spawn`:
+/// ```
+/// # use captures::spawn_async_std;
+/// # use std::sync::Arc;
+/// # async_std::task::block_on(async {
+/// let state = Arc::new(1);
+/// let handle = spawn_async_std!(clone state, async { *state + 1 });
+/// assert_eq!(handle.await, 2);
+/// # });
+/// ```
+///
+/// Only available with the `async-std` feature.
+#[cfg(feature = "async-std")]
+#[proc_macro]
+pub fn spawn_async_std(inp: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    captures_core::expand(inp.into(), Kind::SpawnAsyncStd).into()
+}
 
-    assert!(attrs.is_empty());
-    if only {
-        clean::clean(&mut body, &exempt);
-    }
+/// Captures variables into an async block and spawns it as a smol task.
+///
+/// This is the equivalent of [`spawn_task!`] for `smol::spawn`. Note that smol cancels tasks when
+/// their `Task` handle is dropped, so you will usually want to `.await` or `.detach()` it:
+/// ```
+/// # use captures::spawn_smol;
+/// # use std::sync::Arc;
+/// # smol::block_on(async {
+/// let state = Arc::new(1);
+/// let task = spawn_smol!(clone state, async { *state + 1 });
+/// assert_eq!(task.await, 2);
+/// # });
+/// ```
+///
+/// Only available with the `smol` feature.
+#[cfg(feature = "smol")]
+#[proc_macro]
+pub fn spawn_smol(inp: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    captures_core::expand(inp.into(), Kind::SpawnSmol).into()
+}
 
-    quote! {
-        {
-            #exterior
-            #asyncness
-            #movability
-            #capture
-            #or1_token
-            #inputs
-            #or2_token
-            #output
-            {
-                #interior
-                #body
-            }
-        }
-    }
+/// Captures variables into a closure and wraps it in a `wasm_bindgen::closure::Closure`.
+///
+/// The directives are applied to the closure as with [`capture!`], and the closure is then boxed,
+/// cast to the appropriate `Box<dyn FnMut(..)>` type, and passed to `Closure::wrap`. This is the
+/// usual way of creating DOM callbacks:
+/// ```
+/// # use captures::wasm_closure;
+/// # use std::{cell::Cell, rc::Rc};
+/// # use wasm_bindgen::closure::Closure;
+/// # fn f() {
+/// let clicks = Rc::new(Cell::new(0));
+/// let on_click: Closure<dyn FnMut(u32)> = wasm_closure!(clone clicks, |n: u32| {
+///     clicks.set(clicks.get() + n);
+/// });
+/// # }
+/// ```
+///
+/// The argument types of the closure will usually need to be annotated.
+///
+/// Only available with the `wasm-bindgen` feature.
+#[cfg(feature = "wasm-bindgen")]
+#[proc_macro]
+pub fn wasm_closure(inp: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    captures_core::expand(inp.into(), Kind::WasmClosure).into()
+}
+
+/// Captures variables into a closure and wraps it in a `wasm_bindgen::closure::Closure` that may
+/// only be called once.
+///
+/// This is like [`wasm_closure!`], except that the closure is passed to `Closure::once` and so may
+/// be an `FnOnce`.
+///
+/// Only available with the `wasm-bindgen` feature.
+#[cfg(feature = "wasm-bindgen")]
+#[proc_macro]
+pub fn wasm_closure_once(inp: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    captures_core::expand(inp.into(), Kind::WasmClosureOnce).into()
+}
+
+/// Captures variables into a closure and turns it into a Python function.
+///
+/// See the [crate level documentation][`crate`] for more info.
+///
+/// Only available with the `pyo3` feature.
+#[cfg(feature = "pyo3")]
+#[proc_macro]
+pub fn py_closure(inp: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    captures_core::expand(inp.into(), Kind::PyClosure).into()
+}
+
+/// Captures fields of a context struct into a closure.
+///
+/// See the [crate level documentation][`crate`] for more info.
+#[proc_macro]
+pub fn capture_ctx(inp: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    captures_core::expand(inp.into(), Kind::CaptureCtx).into()
+}
+
+/// Defines a reusable group of directives.
+///
+/// See the [crate level documentation][`crate`] for more info.
+#[proc_macro]
+pub fn define_captures(inp: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    captures_core::define_group(inp.into()).into()
+}
+
+/// Applies the directives to a block instead of a closure.
+///
+/// See the [crate level documentation][`crate`] for more info.
+#[proc_macro]
+pub fn bind(inp: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    captures_core::expand(inp.into(), Kind::Bind).into()
+}
+
+/// Generates a named struct holding the captures, with a method running the closure body.
+///
+/// See the [crate level documentation][`crate`] for more info.
+#[proc_macro]
+pub fn capture_fn(inp: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    named::capture_fn(inp.into()).into()
+}
+
+/// Captures variables into a closure, warning about the variables that have no directive.
+///
+/// See the [crate level documentation][`crate`] for more info.
+#[proc_macro]
+pub fn capture_lint(inp: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    captures_core::expand(inp.into(), Kind::CaptureLint).into()
+}
+
+/// Captures variables into a closure and turns it into a callback for C code.
+///
+/// See the [crate level documentation][`crate`] for more info.
+///
+/// Only available with the `alloc` feature, which is enabled by default.
+#[cfg(feature = "alloc")]
+#[proc_macro]
+pub fn capture_extern(inp: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    captures_core::expand(inp.into(), Kind::CaptureExtern).into()
+}
+
+/// Checks that the closure (or any other value) is `'static`, so that it doesn't borrow anything.
+///
+/// See the [crate level documentation][`crate`] for more info.
+#[proc_macro]
+pub fn assert_static(inp: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    asserts::assert(inp.into(), asserts::Property::Static).into()
+}
+
+/// Checks that the closure (or any other value) is `Send`.
+///
+/// See the [crate level documentation][`crate`] for more info.
+#[proc_macro]
+pub fn assert_send_closure(inp: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    asserts::assert(inp.into(), asserts::Property::Send).into()
+}
+
+/// Checks that the closure (or any other value) is `Sync`.
+///
+/// See the [crate level documentation][`crate`] for more info.
+#[proc_macro]
+pub fn assert_sync_closure(inp: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    asserts::assert(inp.into(), asserts::Property::Sync).into()
+}
+
+/// Produces the expansion of one of the other macros as a string literal.
+///
+/// See the [crate level documentation][`crate`] for more info.
+#[proc_macro]
+pub fn expand_str(inp: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    snapshot::expand_str(inp.into()).into()
+}
+
+/// Sets default directives for the macros invoked in the given tokens.
+///
+/// See the [crate level documentation][`crate`] for more info.
+#[proc_macro]
+pub fn capture_defaults(inp: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    defaults::capture_defaults(inp.into()).into()
+}
+
+/// Expands `#[capture(...)]` and `#[capture_only(...)]` annotations on closures in the item.
+///
+/// With directives as arguments, applies them to the closure in the annotated `let` statement.
+///
+/// See the [crate level documentation][`crate`] for more info.
+#[proc_macro_attribute]
+pub fn captures(
+    attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    attr::captures(attr.into(), item.into()).into()
+}
+
+/// Rejects closures in the item that are not written via one of this crate's macros.
+///
+/// See the [crate level documentation][`crate`] for more info.
+#[proc_macro_attribute]
+pub fn deny_implicit_captures(
+    attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    attr::deny_implicit_captures(attr.into(), item.into()).into()
 }
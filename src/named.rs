@@ -0,0 +1,186 @@
+//! The `capture_fn!` macro.
+//!
+//! Instead of a closure, this generates a struct with a field for each capture. An invocation like
+//! ```text
+//! capture_fn!(pub struct Handler, clone db: Rc<Db>, with mut count: u32 = 0, |n: u32| -> u32 {
+//!     ...
+//! });
+//! ```
+//! becomes
+//! ```text
+//! pub struct Handler {
+//!     db: Rc<Db>,
+//!     count: u32,
+//! }
+//!
+//! impl Handler {
+//!     pub fn new(db: &Rc<Db>) -> Self {
+//!         Handler { db: Clone::clone(db), count: 0 }
+//!     }
+//!
+//!     // `call` instead if there are no `mut` directives
+//!     pub fn call_mut(&mut self, n: u32) -> u32 {
+//!         let Handler { db, count } = self;
+//!         ...
+//!     }
+//! }
+//! ```
+
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::{quote, quote_spanned};
+use syn::ext::IdentExt;
+use syn::parse::{Parse, ParseStream, Parser};
+use syn::{Attribute, Error, Expr, ExprClosure, Pat, Token, Type, Visibility};
+
+struct CaptureFn {
+    attrs: Vec<Attribute>,
+    vis: Visibility,
+    name: Ident,
+    fields: Vec<Field>,
+    closure: ExprClosure,
+}
+
+/// A directive with the type of the captured value
+struct Field {
+    kind: FieldKind,
+    mu: Option<Token![mut]>,
+    name: Ident,
+    ty: Type,
+}
+
+enum FieldKind {
+    /// `clone x: T`, which takes a `&T` in the constructor
+    Clone(Span),
+    /// `with x: T`, which takes a `T` in the constructor
+    With,
+    /// `with x: T = expr`, which is initialized to `expr` by the constructor
+    WithValue(Box<Expr>),
+}
+
+impl Parse for Field {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let kw = input.call(Ident::parse_any)?;
+        let mu = input.parse()?;
+        let name = input.parse()?;
+        input.parse::<Token![:]>()?;
+        let ty = input.parse()?;
+        let kind = match &*kw.to_string() {
+            "clone" => FieldKind::Clone(kw.span()),
+            "with" if input.peek(Token![=]) => {
+                input.parse::<Token![=]>()?;
+                FieldKind::WithValue(input.parse()?)
+            }
+            "with" => FieldKind::With,
+            _ => return Err(Error::new(
+                kw.span(),
+                "expected `clone` or `with`; other directives are not supported in `capture_fn!`",
+            )),
+        };
+        Ok(Field { kind, mu, name, ty })
+    }
+}
+
+impl Parse for CaptureFn {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
+        let vis = input.parse()?;
+        input.parse::<Token![struct]>()?;
+        let name = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let mut fields = Vec::new();
+        while !input.peek(Token![|]) && !input.peek(Token![move]) {
+            fields.push(input.parse()?);
+            input.parse::<Token![,]>()?;
+        }
+        let closure: ExprClosure = input.parse()?;
+        input.parse::<Option<Token![;]>>()?;
+        if closure.asyncness.is_some() || closure.movability.is_some() {
+            return Err(Error::new_spanned(
+                &closure,
+                "`capture_fn!` needs a plain closure",
+            ));
+        }
+        for arg in &closure.inputs {
+            if !matches!(arg, Pat::Type(_)) {
+                return Err(Error::new_spanned(
+                    arg,
+                    "the arguments in `capture_fn!` must have types",
+                ));
+            }
+        }
+        Ok(CaptureFn {
+            attrs,
+            vis,
+            name,
+            fields,
+            closure,
+        })
+    }
+}
+
+pub fn capture_fn(inp: TokenStream) -> TokenStream {
+    let CaptureFn {
+        attrs,
+        vis,
+        name,
+        fields,
+        closure,
+    } = match CaptureFn::parse.parse2(inp) {
+        Ok(c) => c,
+        Err(e) => return e.into_compile_error(),
+    };
+
+    let names: Vec<_> = fields.iter().map(|f| &f.name).collect();
+    let types = fields.iter().map(|f| &f.ty);
+    let mut params = Vec::new();
+    let mut inits = Vec::new();
+    for Field { kind, name, ty, .. } in &fields {
+        match kind {
+            FieldKind::Clone(sp) => {
+                params.push(quote!(#name: &#ty));
+                inits.push(quote_spanned!(*sp=> #name: ::core::clone::Clone::clone(#name)));
+            }
+            FieldKind::With => {
+                params.push(quote!(#name: #ty));
+                inits.push(quote!(#name));
+            }
+            FieldKind::WithValue(expr) => inits.push(quote!(#name: #expr)),
+        }
+    }
+
+    let (method, receiver) = if fields.iter().any(|f| f.mu.is_some()) {
+        (quote!(call_mut), quote!(&mut self))
+    } else {
+        (quote!(call), quote!(&self))
+    };
+    let (inputs, output) = (closure.inputs, closure.output);
+    // Put the statements of a block body directly into the method, instead of nesting the block
+    let body = match *closure.body {
+        Expr::Block(b) if b.attrs.is_empty() && b.label.is_none() => {
+            let stmts = b.block.stmts;
+            quote!(#(#stmts)*)
+        }
+        body => quote!(#body),
+    };
+
+    quote! {
+        #(#attrs)*
+        #vis struct #name {
+            #(#names: #types,)*
+        }
+
+        impl #name {
+            /// Creates the value, capturing the given variables.
+            #[allow(clippy::new_without_default)]
+            #vis fn new(#(#params),*) -> Self {
+                #name { #(#inits,)* }
+            }
+
+            /// Runs the body of the closure.
+            #vis fn #method(#receiver, #inputs) #output {
+                let #name { #(#names,)* } = self;
+                #body
+            }
+        }
+    }
+}
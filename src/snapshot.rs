@@ -0,0 +1,64 @@
+//! The `expand_str!` macro.
+
+use captures_core::Kind;
+use proc_macro2::{Delimiter, Literal, Spacing, TokenStream, TokenTree};
+use quote::ToTokens;
+use syn::{Error, ExprMacro};
+
+pub fn expand_str(inp: TokenStream) -> TokenStream {
+    let mac = match syn::parse2::<ExprMacro>(inp) {
+        Ok(mac) => mac.mac,
+        Err(e) => return e.into_compile_error(),
+    };
+    let name = mac.path.segments.last().map(|s| s.ident.to_string());
+    let kind = match name.as_deref().and_then(Kind::from_name) {
+        Some(kind) => kind,
+        None => {
+            return Error::new_spanned(
+                &mac.path,
+                "expected one of the macros that take directives, like `capture!`",
+            )
+            .into_compile_error()
+        }
+    };
+    match captures_core::try_expand(mac.tokens, kind) {
+        Ok(out) => {
+            let mut s = String::new();
+            write_tokens(&mut s, out, &mut false);
+            Literal::string(&s).into_token_stream()
+        }
+        Err(e) => e.into_compile_error(),
+    }
+}
+
+/// Writes the tokens separated by single spaces, except after joint punctuation like the first
+/// `:` of `::`.
+///
+/// The compiler's formatting of token streams has changed between versions, so this is done by
+/// hand to keep the strings the same.
+fn write_tokens(s: &mut String, tokens: TokenStream, joint: &mut bool) {
+    for tt in tokens {
+        if !s.is_empty() && !*joint {
+            s.push(' ');
+        }
+        *joint = false;
+        match tt {
+            TokenTree::Group(g) => {
+                let (open, close) = match g.delimiter() {
+                    Delimiter::Parenthesis => ("(", " )"),
+                    Delimiter::Brace => ("{", " }"),
+                    Delimiter::Bracket => ("[", " ]"),
+                    Delimiter::None => ("", ""),
+                };
+                s.push_str(open);
+                write_tokens(s, g.stream(), joint);
+                s.push_str(close);
+            }
+            TokenTree::Punct(p) => {
+                s.push(p.as_char());
+                *joint = p.spacing() == Spacing::Joint;
+            }
+            tt => s.push_str(&tt.to_string()),
+        }
+    }
+}
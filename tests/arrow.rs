@@ -0,0 +1,22 @@
+use captures::*;
+
+use std::rc::Rc;
+
+struct Ctx {
+    db: Rc<i32>,
+}
+
+// `=>` may end the directives, as in glib's `clone!`
+fn main() {
+    let a = Rc::new(1);
+    let b = 2;
+    let f = capture!(clone a, ref b => move || *a + *b);
+    assert_eq!(f(), 3);
+    let g = capture_only!(clone a, with c = b >= 2 => move || *a + c as i32);
+    assert_eq!(g(), 2);
+    let (h, i) = capture!([clone a] => (move || *a, move || *a + 1));
+    assert_eq!(h() + i(), 3);
+    let ctx = Ctx { db: Rc::new(1) };
+    let j = capture_ctx!(ctx => clone db => move || *db);
+    assert_eq!(j(), 1);
+}
@@ -0,0 +1,36 @@
+use captures::*;
+
+use std::sync::Arc;
+
+fn takes_send<T: Send>(t: T) -> T {
+    t
+}
+
+// Checks that closures satisfying the bounds pass through unchanged
+fn closure() {
+    let a = Arc::new(1);
+    let b = 2;
+    let f = capture!(clone a, move || *a + b; assert Send + Sync + 'static);
+    assert_eq!(takes_send(f)(), 3);
+}
+
+// Checks that `ref` directives are checked as references
+fn refs() {
+    let a = vec![1];
+    let f = capture!(ref a, move || a.len(); assert Send + Sync);
+    assert_eq!(f(), 1);
+}
+
+// Checks that async blocks and other macros support the clause
+fn other_macros() {
+    let a = Arc::new(1);
+    let _fut = capture_only!(clone a, async { *a }; assert Send + 'static);
+    let g = capture_ctx!(a => with b = 1, || b; assert Clone);
+    assert_eq!(g(), 1);
+}
+
+fn main() {
+    closure();
+    refs();
+    other_macros();
+}
@@ -0,0 +1,27 @@
+use captures::*;
+
+use std::rc::Rc;
+use std::sync::Arc;
+
+// Checks that the assertions accept closures with the properties, and don't move them
+fn closures() {
+    let a = Arc::new(1);
+    let f = capture!(clone a, move || *a);
+    assert_static!(f);
+    assert_send_closure!(f);
+    assert_sync_closure!(f);
+    assert_eq!(f(), 1);
+}
+
+// Checks that the assertions accept any expression
+fn expressions() {
+    let b = Rc::new(2);
+    assert_static!(capture!(clone b, move || *b),);
+    assert_send_closure!(|x: i32| x + 1);
+    assert_sync_closure!(&1);
+}
+
+fn main() {
+    closures();
+    expressions();
+}
@@ -0,0 +1,64 @@
+use captures::*;
+
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+
+fn takes_static<T: 'static + Future<Output = i32>>(f: T) -> T {
+    f
+}
+
+// Checks that `clone` directives make an async block `move`
+fn clone_dir() {
+    let a = Rc::new(1);
+    let _fut = takes_static(capture!(clone a, async { *a + 1 }));
+    assert_eq!(*a, 1);
+}
+
+// Checks that the result of `capture_async!` coerces to a boxed future
+fn boxed() {
+    let a = Rc::new(1);
+    let _fut: Pin<Box<dyn Future<Output = i32>>> = capture_async!(clone a, with b = 2, async {
+        *a + b
+    });
+    assert_eq!(*a, 1);
+}
+
+// Checks that `capture_only!` is also applied to async blocks
+fn only() {
+    let a = 1;
+    let _fut = takes_static(capture_only!(with b = a, async move { b + 1 }));
+}
+
+struct Pair {
+    a: Rc<i32>,
+    _b: Rc<i32>,
+}
+
+// Checks that `all` captures the whole variable when only a field is used in a nested async
+// block, both in a closure and in an async block, so that the unused field is dropped with it
+fn nested_all() {
+    let b = Rc::new(2);
+    let p = Pair {
+        a: Rc::new(1),
+        _b: b.clone(),
+    };
+    let f = capture_only!(all p, move || async move { *p.a });
+    drop(f);
+    assert_eq!(Rc::strong_count(&b), 1);
+
+    let q = Pair {
+        a: Rc::new(1),
+        _b: b.clone(),
+    };
+    let fut = capture!(all q, async move { async move { *q.a }.await });
+    drop(fut);
+    assert_eq!(Rc::strong_count(&b), 1);
+}
+
+fn main() {
+    clone_dir();
+    boxed();
+    only();
+    nested_all();
+}
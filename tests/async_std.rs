@@ -0,0 +1,19 @@
+#![cfg(feature = "async-std")]
+
+use captures::*;
+
+use std::sync::Arc;
+
+// Directives are applied and the task's output is available through the handle
+fn spawn() {
+    async_std::task::block_on(async {
+        let state = Arc::new(5);
+        let handle = spawn_async_std!(clone state, with offset = 2, async { *state + offset });
+        assert_eq!(handle.await, 7);
+        assert_eq!(*state, 5);
+    });
+}
+
+fn main() {
+    spawn();
+}
@@ -0,0 +1,68 @@
+use captures::*;
+
+use std::rc::Rc;
+
+fn takes_static<T: 'static + FnOnce() -> i32>(f: T) -> i32 {
+    f()
+}
+
+// Annotated closures are expanded like the corresponding macro
+#[captures]
+fn basic() {
+    let a = Rc::new(1);
+    let b = 2;
+    let out = takes_static(
+        #[capture(clone a, with c = b + 1)]
+        || *a + c,
+    );
+    assert_eq!(out, 4);
+    assert_eq!(*a, 1);
+}
+
+// The attributes work in long method chains and on nested closures
+#[captures]
+fn chains() {
+    let offset = Rc::new(1);
+    let total: i32 = (0..3)
+        .map(
+            #[capture(clone offset)]
+            |x| {
+                let f = #[capture_only(clone offset, all x)]
+                || *offset + x;
+                f()
+            },
+        )
+        .sum();
+    assert_eq!(total, 6);
+}
+
+// On a `let` statement, the attribute applies to the value
+#[captures]
+fn statements() {
+    let a = Rc::new(1);
+    #[capture(clone a)]
+    let f = || *a + 1;
+    #[capture_only(with b = 2)]
+    let g = move || b;
+    assert_eq!(takes_static(f) + takes_static(g), 4);
+}
+
+struct Counter {
+    step: Rc<i32>,
+}
+
+// The attribute may also be put on impl blocks
+#[captures]
+impl Counter {
+    fn adder(&self) -> impl Fn(i32) -> i32 {
+        #[capture(with step = Rc::clone(&self.step))]
+        move |x| x + *step
+    }
+}
+
+fn main() {
+    basic();
+    chains();
+    statements();
+    assert_eq!(Counter { step: Rc::new(2) }.adder()(1), 3);
+}
@@ -0,0 +1,32 @@
+use captures::*;
+
+use std::rc::Rc;
+
+// Checks that the directives are bound for the block
+fn directives() {
+    let a = Rc::new(1);
+    let mut b = vec![1];
+    let n = bind!(clone a, ref mut b, with c = 2, {
+        b.push(*a + c);
+        Rc::strong_count(&a)
+    });
+    assert_eq!(n, 2);
+    assert_eq!(b, [1, 3]);
+}
+
+// Checks that the block may be labeled and break out
+fn labeled() {
+    let a = 5;
+    let n = bind!(with b = a * 2, 'outer: {
+        if b > 5 {
+            break 'outer b;
+        }
+        0
+    });
+    assert_eq!(n, 10);
+}
+
+fn main() {
+    directives();
+    labeled();
+}
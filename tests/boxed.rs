@@ -0,0 +1,33 @@
+use captures::*;
+
+struct Callbacks {
+    plain: Box<dyn Fn() -> i32>,
+    args: Box<dyn FnMut(i32, i32) -> i32 + Send>,
+}
+
+// The type after `dyn` is used for the coercion
+fn coerced() {
+    let a = 1;
+    let mut total = 0;
+    let mut cbs = Callbacks {
+        plain: capture!(boxed dyn Fn() -> i32, clone a, || a + 1),
+        args: capture!(clone a, boxed dyn FnMut(i32, i32) -> i32 + Send, move |x, y| {
+            total += a + x + y;
+            total
+        }),
+    };
+    assert_eq!((cbs.plain)(), 2);
+    assert_eq!((cbs.args)(1, 2), 4);
+}
+
+// Without a type we just get a `Box<impl Fn>`
+fn plain() {
+    let a = 1;
+    let f: Box<_> = capture!(boxed, all a, || a + 1);
+    assert_eq!(f(), 2);
+}
+
+fn main() {
+    coerced();
+    plain();
+}
@@ -0,0 +1,53 @@
+use captures::*;
+
+use std::rc::Rc;
+
+define_captures!(shared = clone a);
+
+struct Ctx {
+    db: Rc<i32>,
+}
+
+fn f() -> i32 {
+    3
+}
+
+// Directives in brackets work like those written without them
+fn basic() {
+    let a = Rc::new(1);
+    let mut b = 2;
+    let mut g = capture!([clone a, ref mut b, with c = f()], || {
+        *b += 1;
+        *a + *b + c
+    });
+    assert_eq!(g(), 7);
+    drop(g);
+    assert_eq!(b, 3);
+    assert_eq!(Rc::strong_count(&a), 1);
+    let h = capture_only!([all a, with c = f(),], move || *a + c);
+    assert_eq!(h(), 4);
+}
+
+// Brackets can be combined with other directives and groups, and nested
+fn combined() {
+    let a = Rc::new(1);
+    let b = 2;
+    let g = capture!([use shared, ref b], with c = 3, move || *a + *b + c);
+    assert_eq!(g(), 6);
+    let h = capture!(with c = 3, [[clone a], ref b], move || *a + *b + c);
+    assert_eq!(h(), 6);
+    let (i, j) = capture!([clone a, ref b], (move || *a, move || *b));
+    assert_eq!(i() + j(), 3);
+}
+
+fn ctx() {
+    let ctx = Ctx { db: Rc::new(1) };
+    let g = capture_ctx!(ctx => [clone db], move || *db);
+    assert_eq!(g(), 1);
+}
+
+fn main() {
+    basic();
+    combined();
+    ctx();
+}
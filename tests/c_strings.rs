@@ -0,0 +1,27 @@
+use captures::*;
+
+use std::ffi::CStr;
+
+// C string literals, which syn can't parse, can be used in the directives and in the body
+fn macros() {
+    let a = 1;
+    let f = capture_only!(all a, with b = c"b", move || {
+        let s: &CStr = c"hi";
+        (a, b.to_bytes().len() + s.to_bytes().len() + cr"raw".to_bytes().len())
+    });
+    assert_eq!(f(), (1, 6));
+    let g = capture!(with b = c"b", move || b.to_bytes().len());
+    assert_eq!(g(), 1);
+}
+
+#[captures]
+fn annotated() -> usize {
+    let f = #[capture_only(with b = c"b")]
+    move || b.to_bytes().len() + c"cd".to_bytes().len();
+    f()
+}
+
+fn main() {
+    macros();
+    assert_eq!(annotated(), 3);
+}
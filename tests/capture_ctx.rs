@@ -0,0 +1,56 @@
+use captures::*;
+
+use std::rc::Rc;
+
+struct Ctx {
+    db: Rc<Vec<i32>>,
+    metrics: Vec<i32>,
+}
+
+struct Outer {
+    ctx: Ctx,
+}
+
+// Checks that fields of the context are captured under their own name
+fn fields() {
+    let mut ctx = Ctx {
+        db: Rc::new(vec![1]),
+        metrics: Vec::new(),
+    };
+    let mut f = capture_ctx!(ctx => clone db, ref mut metrics, with n = 2, move || {
+        metrics.push(db[0] + n);
+    });
+    f();
+    assert_eq!(Rc::strong_count(&ctx.db), 2);
+    drop(f);
+    assert_eq!(ctx.metrics, [3]);
+}
+
+// Checks that the context may be a field access or another expression
+fn nested() {
+    let outer = Outer {
+        ctx: Ctx {
+            db: Rc::new(vec![1]),
+            metrics: vec![5],
+        },
+    };
+    let f = capture_ctx!(outer.ctx => clone db, || db.len());
+    assert_eq!(f(), 1);
+    let g = capture_ctx!(&outer.ctx => ref metrics, move || metrics[0]);
+    assert_eq!(g(), 5);
+}
+
+// Checks that async blocks work as well
+fn async_block() {
+    let ctx = Ctx {
+        db: Rc::new(vec![1]),
+        metrics: Vec::new(),
+    };
+    let _fut = capture_ctx!(ctx => clone db, async { db.len() });
+}
+
+fn main() {
+    fields();
+    nested();
+    async_block();
+}
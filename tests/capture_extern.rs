@@ -0,0 +1,48 @@
+use captures::*;
+
+use std::ffi::c_void;
+use std::rc::Rc;
+
+// Stands in for a C library that calls the callback a few times and then frees it
+unsafe extern "C" fn run(
+    cb: unsafe extern "C" fn(i32, *mut c_void) -> i32,
+    destroy: unsafe extern "C" fn(*mut c_void),
+    data: *mut c_void,
+) -> i32 {
+    let total = cb(1, data) + cb(2, data);
+    destroy(data);
+    total
+}
+
+// Checks that the closure is called with its state, and that it is dropped by the destructor
+fn basic() {
+    let a = Rc::new(10);
+    let (data, call, destroy) = capture_extern!(clone a, with mut calls = 0, |x: i32| {
+        calls += 1;
+        x * *a + calls
+    });
+    assert_eq!(Rc::strong_count(&a), 2);
+    assert_eq!(unsafe { run(call, destroy, data) }, 33);
+    assert_eq!(Rc::strong_count(&a), 1);
+}
+
+// Checks closures without arguments or return values, and with pointer arguments
+fn signatures() {
+    let (data, call, destroy) =
+        capture_extern!(with v = String::from("a"), move || assert_eq!(v, "a"));
+    unsafe {
+        call(data);
+        destroy(data);
+    }
+    let (data, call, destroy) = capture_extern!(|s: *const u8, n: usize| unsafe { *s.add(n) });
+    let bytes = b"abc";
+    unsafe {
+        assert_eq!(call(bytes.as_ptr(), 2, data), b'c');
+        destroy(data);
+    }
+}
+
+fn main() {
+    basic();
+    signatures();
+}
@@ -0,0 +1,39 @@
+use captures::*;
+
+use std::rc::Rc;
+
+capture_fn!(
+    /// A callback with a nameable type
+    pub struct Greeter,
+    clone name: Rc<String>,
+    with greeting: &'static str,
+    |punct: char| -> String { format!("{}, {}{}", greeting, name, punct) }
+);
+
+capture_fn!(struct Counter, with mut count: u32 = 0, |by: u32| -> u32 {
+    *count += by;
+    *count
+});
+
+capture_fn!(struct Nothing, || {});
+
+// Checks that `call` runs the body with references to the captures
+fn greeter() {
+    let name = Rc::new(String::from("world"));
+    let g = Greeter::new(&name, "hello");
+    assert_eq!(g.call('!'), "hello, world!");
+    assert_eq!(Rc::strong_count(&name), 2);
+}
+
+// Checks that `mut` directives produce `call_mut`
+fn counter() {
+    let mut c = Counter::new();
+    c.call_mut(1);
+    assert_eq!(c.call_mut(2), 3);
+}
+
+fn main() {
+    greeter();
+    counter();
+    Nothing::new().call();
+}
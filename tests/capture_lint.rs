@@ -0,0 +1,37 @@
+use captures::*;
+
+use std::rc::Rc;
+
+// Closures that declare all of their captures compile without warnings
+fn declared() {
+    let a = Rc::new(1);
+    let b = 2;
+    let f = capture_lint!(clone a, all b, move |x: i32| {
+        let total = *a + b;
+        let g = |y: i32| y + total;
+        match g(x) {
+            n if n > 0 => n,
+            _ => 0,
+        }
+    });
+    assert_eq!(f(1), 4);
+}
+
+// Undeclared captures only cause warnings, and the closure behaves as with `capture!`, so `b` is
+// copied into the `move` closure
+#[allow(deprecated)]
+fn undeclared() {
+    let a = Rc::new(1);
+    let mut b = 2;
+    let mut f = capture_lint!(clone a, || {
+        b += 1;
+        *a + b
+    });
+    assert_eq!((f(), f()), (4, 5));
+    assert_eq!(b, 2);
+}
+
+fn main() {
+    declared();
+    undeclared();
+}
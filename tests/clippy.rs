@@ -0,0 +1,81 @@
+#![deny(clippy::all, clippy::pedantic, clippy::nursery)]
+#![deny(clippy::shadow_same, clippy::shadow_reuse, clippy::shadow_unrelated)]
+#![allow(clippy::many_single_char_names, clippy::missing_const_for_fn)]
+
+use captures::*;
+use std::ffi::c_void;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+
+capture_fn!(struct Counter, with mut count: u32 = 0, clone name: Rc<String>, |by: u32| -> u32 {
+    *count += by + u32::try_from(name.len()).unwrap_or(0);
+    *count
+});
+
+// The expansions don't trigger clippy lints in code the user didn't write
+fn clones() -> usize {
+    let (a, b, c) = (Rc::new(1), String::from("b"), vec![1, 2]);
+    let mut f = capture!(clone a, clone mut b, ref c, with d = c.len(), move || {
+        b.push('c');
+        *a + b.len() + c.len() + d
+    });
+    f()
+}
+
+fn last_use() -> usize {
+    // `b` isn't used after the closure, so the clone would be redundant if written by hand
+    let b = String::from("b");
+    let f = capture!(clone b, move || b.len());
+    f()
+}
+
+struct Ctx {
+    name: String,
+    count: usize,
+}
+
+fn ctx(mut ctx: Ctx) -> usize {
+    let mut f = capture_ctx!(ctx => clone name, ref mut count, move || {
+        *count += 1;
+        name.len() + *count
+    });
+    f()
+}
+
+fn only() -> usize {
+    let (a, b) = (1, vec![2]);
+    let f = capture_only!(all a, clone b, move || a + b.len());
+    f()
+}
+
+fn asserted() -> usize {
+    let a = String::new();
+    let f = capture!(clone a, move || a.len(); assert Send + 'static);
+    f()
+}
+
+fn other_macros() -> usize {
+    let a = Rc::new(1);
+    let (f, g) = capture!(clone a, (move || *a, move |x: usize| *a + x));
+    let h: Box<dyn Fn() -> usize> = capture!(boxed dyn Fn() -> usize, clone a, move || *a);
+    let _fut: Pin<Box<dyn Future<Output = usize>>> =
+        capture_async!(clone a, with b = 2, async { *a + b });
+    let lint = capture_lint!(clone a, move || *a);
+    let mut counter = Counter::new(&Rc::new(String::new()));
+    let (data, _call, destroy) = capture_extern!(clone a, move |x: usize| x + *a);
+    unsafe { destroy(data.cast::<c_void>()) };
+    f() + g(1) + h() + lint() + counter.call_mut(1) as usize
+}
+
+fn main() {
+    clones();
+    last_use();
+    ctx(Ctx {
+        name: String::new(),
+        count: 0,
+    });
+    only();
+    asserted();
+    other_macros();
+}
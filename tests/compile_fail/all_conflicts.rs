@@ -0,0 +1,8 @@
+use captures::*;
+
+// `all` keeps the variable itself, so it can't be combined with a directive replacing it
+fn main() {
+    let (a, b) = (1, 2);
+    let _ = capture!(all a, clone a, move || a);
+    let _ = capture!(ref mut b, all b, move || *b);
+}
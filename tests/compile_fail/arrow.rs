@@ -0,0 +1,8 @@
+use captures::*;
+
+// A mistake in the directive before a `=>` is pointed out, and the target is still expanded
+fn main() {
+    let a = 1;
+    let _ = capture!(clone 1 => move || a);
+    let _ = capture!(clone a =>);
+}
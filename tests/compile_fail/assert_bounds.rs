@@ -0,0 +1,22 @@
+use captures::*;
+
+use std::rc::Rc;
+
+// Errors for captures with a directive should point at the directive
+fn directive() {
+    let a = Rc::new(1);
+    let b = 2;
+    let _ = capture!(clone a, with b = b, move || *a + b; assert Send);
+}
+
+// Other captures are still checked as part of the closure
+fn implicit() {
+    let c = Rc::new(1);
+    let _ = capture!(move || *c; assert Send);
+}
+
+fn syntax() {
+    let _ = capture!(move || 1; check Send);
+}
+
+fn main() {}
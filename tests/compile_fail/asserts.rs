@@ -0,0 +1,9 @@
+use captures::*;
+
+use std::rc::Rc;
+
+fn main() {
+    let a = Rc::new(1);
+    let f = capture!(clone a, move || *a);
+    assert_send_closure!(f);
+}
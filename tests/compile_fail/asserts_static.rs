@@ -0,0 +1,7 @@
+use captures::*;
+
+fn main() {
+    let b = 2;
+    let g = capture!(ref b, move || *b);
+    assert_static!(g);
+}
@@ -0,0 +1,19 @@
+use captures::*;
+
+// Errors in the directives are reported at the attribute
+#[captures]
+fn directives() {
+    let a = 1;
+    let _ = #[capture(clone a, garbage a)] || a;
+    let _ = #[capture_only = "clone a"] || a;
+    #[capture(clone a)]
+    let _ = a;
+}
+
+#[captures(clone a)]
+fn arguments() {}
+
+fn main() {
+    directives();
+    arguments();
+}
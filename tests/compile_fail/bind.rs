@@ -0,0 +1,7 @@
+use captures::*;
+
+fn main() {
+    let a = 1;
+    let _ = bind!(clone a, || a);
+    let _ = capture!(clone a, { a });
+}
@@ -0,0 +1,10 @@
+use captures::*;
+
+// The variables used without a directive are listed in one warning
+fn main() {
+    let (a, b, c, d) = (1, 2, 3, vec![4]);
+    let f = capture_only!(clone a, move || {
+        let total = a + b + c;
+        d.iter().map(|x| x + total + b).sum::<i32>()
+    });
+}
@@ -0,0 +1,8 @@
+use captures::*;
+
+// A mistake in brackets is pointed out, and the directives after them still apply
+fn main() {
+    let a = 1;
+    let b = 2;
+    let _ = capture!([clone 1, ref a], clone b, move || a + b);
+}
@@ -0,0 +1,7 @@
+use captures::*;
+
+// `capture_async!` needs an async block, not a closure
+fn main() {
+    let a = 1;
+    let _ = capture_async!(clone a, || a + 1);
+}
@@ -0,0 +1,11 @@
+use captures::*;
+
+struct Ctx {
+    a: i32,
+}
+
+// `all` directives don't make sense for a context
+fn main() {
+    let ctx = Ctx { a: 1 };
+    let _ = capture_ctx!(ctx => all a, || a);
+}
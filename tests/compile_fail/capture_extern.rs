@@ -0,0 +1,7 @@
+use captures::*;
+
+fn main() {
+    let a = 1;
+    let _ = capture_extern!(clone a, |x| x + a);
+    let _ = capture_extern!(async { a });
+}
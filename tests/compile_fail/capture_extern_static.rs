@@ -0,0 +1,7 @@
+use captures::*;
+
+// The closure can't borrow anything, since C code may keep it around
+fn main() {
+    let a = 1;
+    let _ = capture_extern!(ref a, move |x: i32| x + *a);
+}
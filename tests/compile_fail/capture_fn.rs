@@ -0,0 +1,9 @@
+use captures::*;
+
+capture_fn!(struct A, ref a: u32, || ());
+
+capture_fn!(struct B, clone b: u32, |x| x);
+
+capture_fn!(struct C, clone c, || ());
+
+fn main() {}
@@ -0,0 +1,22 @@
+#![deny(deprecated)]
+
+use captures::*;
+
+fn helper(x: i32) -> i32 {
+    x
+}
+
+// Only the variables without a directive are reported, once each
+fn main() {
+    let a = 1;
+    let b = 2;
+    let c = vec![3];
+    let f = capture_lint!(clone a, move |x: i32| {
+        let d = helper(a + b + x);
+        if let Some(e) = c.first() {
+            println!("{} {}", e, b);
+        }
+        d
+    });
+    f(1);
+}
@@ -0,0 +1,17 @@
+use captures::capture_ctx;
+
+struct Ctx {
+    log: Vec<i32>,
+}
+
+struct Outer {
+    ctx: Ctx,
+}
+
+// A context given as a path keeps its location, so errors in it point at the path itself
+fn main() {
+    let ctx = Ctx { log: Vec::new() };
+    let _ = capture_ctx!(ctz => clone log, move || log.len());
+    let outer = Outer { ctx };
+    let _ = capture_ctx!(outer.cxt => clone log, move || log.len());
+}
@@ -0,0 +1,9 @@
+#![deny(deprecated)]
+
+use captures::*;
+
+// The `debug` flag shows the expansion as a warning, which is turned into an error here
+fn main() {
+    let a = 1;
+    let _ = capture!(debug, clone a, with b = 2, || a + b);
+}
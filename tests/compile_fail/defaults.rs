@@ -0,0 +1,11 @@
+use captures::*;
+
+capture_defaults! {
+    clone a, all b;
+}
+
+capture_defaults! {
+    clone a, oops b;
+}
+
+fn main() {}
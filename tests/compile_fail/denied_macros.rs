@@ -0,0 +1,25 @@
+use captures::*;
+
+macro_rules! double {
+    ($e:expr) => {
+        $e * 2
+    };
+}
+
+fn main() {
+    let a = 1;
+    // Denied macros are errors wherever they are used, including std macros and other paths
+    let _ = capture_only!(deny double!, deny std::vec!, all a, move || {
+        let b = double!(a);
+        let v = vec![b];
+        let w = std::vec![a];
+        v.len() + w.len()
+    });
+    // Without `trust`, the variable without a directive is not found
+    let ctx = 2;
+    let _ = capture_only!(all a, move || double!(a + ctx));
+    let _ = capture_only!(trust double!, all a, move || double!(a + ctx));
+
+    let _ = capture_only!(trust double!, deny crate::double!, all a, || a);
+    let _ = capture!(trust double!, all a, || a);
+}
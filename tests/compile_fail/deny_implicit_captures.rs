@@ -0,0 +1,43 @@
+use captures::*;
+
+// Bare closures are rejected, the others are fine
+#[deny_implicit_captures]
+fn closures() {
+    let a = 1;
+    let _ = || a;
+    let _ = capture!(clone a, || a);
+    let _ = (0..3).map(|x| x + a);
+}
+
+// Annotated closures are fine too, regardless of the order of the attributes
+#[deny_implicit_captures]
+#[captures]
+fn before() {
+    let a = 1;
+    let _ = #[capture(clone a)] || a;
+    let _ = move || a;
+}
+
+#[captures]
+#[deny_implicit_captures]
+fn after() {
+    let a = 1;
+    let _ = #[capture(clone a)] || a;
+    let _ = move || a;
+}
+
+// Works on impl blocks
+struct S;
+
+#[deny_implicit_captures]
+impl S {
+    fn f(&self) -> impl Fn() {
+        || ()
+    }
+}
+
+fn main() {
+    closures();
+    before();
+    after();
+}
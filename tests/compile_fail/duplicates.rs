@@ -0,0 +1,9 @@
+use captures::*;
+
+// Both directives for the same variable are pointed out, also when only one is written as raw
+fn main() {
+    let (a, b) = (1, 2);
+    let _ = capture!(clone a, all b, with c = 3, ref a, move || *a + b + c);
+    let _ = capture!(all b, with b = 1, all b, move || b);
+    let _ = capture!(clone r#a, ref a, move || *a);
+}
@@ -0,0 +1,6 @@
+use captures::*;
+
+fn main() {
+    let _ = expand_str!(println!("{}", 1));
+    let _ = expand_str!(capture!(clone, || 1));
+}
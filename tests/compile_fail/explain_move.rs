@@ -0,0 +1,12 @@
+use captures::*;
+
+// The directive that the `move` was inserted for is named, next to the error it leads to
+fn main() {
+    let a = 1;
+    let v = vec![1, 2];
+    let f = capture!(explain_move, ref a, clone v, with b = 2, || *a + b + v.len());
+    let _ = capture!(explain_move, clone a, move || a);
+    let names = vec!["a".to_string()];
+    let g = capture!(explain_move, with n = 1, || names.len() + n);
+    println!("{} {:?}", f() + g(), names);
+}
@@ -0,0 +1,9 @@
+use captures::*;
+
+// With the flag, the `move` that the directives need must be written out
+fn main() {
+    let a: i32 = 0;
+    let _ = capture!(explicit_move, clone a, || a);
+    let _ = capture!(with b = 1, explicit_move, |x: i32| x + b);
+    let _ = capture_only!(explicit_move, ref a, async { *a });
+}
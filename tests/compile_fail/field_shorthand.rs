@@ -0,0 +1,12 @@
+use captures::*;
+
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+// Field init shorthand can't be used to get around `capture_only!`
+fn main() {
+    let (x, y) = (1, 2);
+    let _ = capture_only!(all x, move || Point { x, y });
+}
@@ -0,0 +1,11 @@
+use captures::*;
+
+// Flags are rejected when misused
+fn main() {
+    let a = 1;
+    capture!(boxed, clone a, boxed dyn Fn() -> i32, || a);
+    capture!(boxed mut, || a);
+    capture_async!(boxed, async { a });
+    capture!(name = "thread", || a);
+    bind!(introspect, { a });
+}
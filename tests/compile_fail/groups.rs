@@ -0,0 +1,13 @@
+use captures::*;
+
+define_captures!(env = clone a, oops b);
+
+define_captures!(other = clone a);
+
+#[captures]
+fn attribute() {
+    let a = 1;
+    let _ = #[capture(use other)] move || a;
+}
+
+fn main() {}
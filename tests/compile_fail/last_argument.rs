@@ -0,0 +1,16 @@
+use captures::*;
+
+fn f() -> i32 {
+    1
+}
+
+// A last argument that is neither a directive nor a closure is pointed out as the target
+fn main() {
+    let a = 1;
+    let _ = capture!(clone a, f);
+    let _ = capture!(clone a, *a + 1);
+    let _ = capture!(clone a, move *a + 1);
+    let _ = capture!(clone a);
+    let _ = capture!(clone a, garbage a, f());
+    let _ = bind!(clone a, a + 1);
+}
@@ -0,0 +1,11 @@
+use captures::*;
+
+// Variables used in `let ... else` need directives like anywhere else in the closure
+fn main() {
+    let a = Some(1);
+    let b = 2;
+    let _ = capture_only!(clone a, move || {
+        let Some(x) = a else { return b };
+        x
+    });
+}
@@ -0,0 +1,11 @@
+#![deny(deprecated)]
+
+use captures::*;
+use std::rc::Rc;
+
+// Cloning a `Copy` value, including a reference, is reported
+fn main() {
+    let (a, b) = (1, Rc::new(2));
+    let r = &b;
+    let _ = capture!(lint_copy, clone a, clone b, clone r, move || a + *b + **r);
+}
@@ -0,0 +1,6 @@
+// Both crates reject `ref` directives on a closure that isn't `move`
+fn main() {
+    let a = 1;
+    let _ = captures::capture!(ref a, || *a);
+    let _ = captures_lite::capture!(ref a, || *a);
+}
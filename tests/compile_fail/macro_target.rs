@@ -0,0 +1,14 @@
+use captures::*;
+
+macro_rules! closure {
+    ($e:expr) => {
+        move || $e
+    };
+}
+
+fn main() {
+    let a = 1;
+    let _ = capture!(all a, closure!(a));
+    let _ = capture_only!(clone a, closure!(a));
+    let _ = bind!(clone a, closure!(a));
+}
@@ -0,0 +1,12 @@
+use captures::*;
+
+// A missing comma between directives is reported once, and the directives still apply
+fn main() {
+    let (a, b) = (1, 2);
+    let f = capture!(clone a clone b, move || a + b);
+    let _: i32 = f();
+    let g = capture!(clone a all b with c = 3 move || a + b + c);
+    let _: i32 = g();
+    let h = capture!(with c = a + 1 ref b, move || c + *b);
+    let _: i32 = h();
+}
@@ -0,0 +1,16 @@
+use captures::*;
+
+#[allow(unused_macros)]
+macro_rules! adder {
+    ($v:ident) => {
+        move |x: i32| x + $v
+    };
+}
+
+// The `move` flag needs something that captures, and may only be given once
+fn main() {
+    let a = 1;
+    let _ = capture!(move, all a, move, || a);
+    let _ = bind!(move, with b = a, { b });
+    let _ = capture!(move, with b = a, adder!(b));
+}
@@ -0,0 +1,11 @@
+use captures::*;
+
+// Nested invocations can't bring in variables that the outer closure doesn't capture
+fn main() {
+    let a = 1;
+    let b = 2;
+    let _f = capture_only!(all a, move || {
+        let g = capture!(clone b, move || a + b);
+        g()
+    });
+}
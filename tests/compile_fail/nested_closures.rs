@@ -0,0 +1,12 @@
+use captures::*;
+
+// Closures and async blocks nested in the body are restricted like the body itself
+fn main() {
+    let v = vec![1, 2];
+    let offset = 1;
+    let f = capture_only!(clone v, move || {
+        let inner = |y: &i32| y + offset;
+        let fut = async { offset };
+        v.iter().map(inner).sum::<i32>()
+    });
+}
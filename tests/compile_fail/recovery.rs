@@ -0,0 +1,10 @@
+use captures::*;
+
+// The closure is still expanded after a broken directive, so the errors in its body are found too
+fn main() {
+    let a = 1;
+    let f = capture!(clone a b, with c = 2, move || a + c + missing);
+    let _: i32 = f();
+    let (g, h) = capture!(garbage a, (move || a, move || unknown));
+    let _: i32 = g() + h();
+}
@@ -0,0 +1,22 @@
+use captures::{capture, capture_ctx};
+
+struct Ctx {
+    log: Vec<i32>,
+}
+
+fn main() {
+    let mut v = vec![1];
+    let mut push = capture!(ref mut v, move || v.push(2));
+    v.push(3);
+    push();
+
+    let mut s = String::new();
+    let read = capture!(ref s, move || s.len());
+    s.push('a');
+    read();
+
+    let mut ctx = Ctx { log: Vec::new() };
+    let mut log = capture_ctx!(ctx => ref mut log, move |x| log.push(x));
+    ctx.log.clear();
+    log(1);
+}
@@ -0,0 +1,15 @@
+use captures::*;
+
+use std::future::Future;
+
+fn takes_static<T: Future + 'static>(f: T) -> T {
+    f
+}
+
+// A future that borrows through a `ref` directive can't be `'static`
+fn main() {
+    let a = vec![1];
+    let _fut = capture!(ref a, async move { a.len() }; assert 'static);
+    let b = vec![2];
+    let _fut = takes_static(capture!(ref b, async move { b.len() }));
+}
@@ -0,0 +1,15 @@
+#![deny(deprecated)]
+
+use captures::*;
+use std::rc::Rc;
+
+// The report lists the directives of the invocation
+fn main() {
+    let (a, b, c) = (Rc::new(1), 2, 3);
+    let _ = capture!(report, clone a, ref b, move || *a + *b + c);
+    let _ = capture_only!(clone mut a, all b, with d = 4, report, move || {
+        a = Rc::new(b);
+        *a + d
+    });
+    let _ = bind!(report, { c });
+}
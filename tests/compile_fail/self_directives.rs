@@ -0,0 +1,19 @@
+use captures::*;
+
+struct Counter {
+    count: i32,
+}
+
+impl Counter {
+    // `self` isn't captured without a directive
+    fn blocked(&self) -> impl Fn() -> i32 + '_ {
+        capture_only!(|| self.count)
+    }
+
+    // `self` can't be rebound, so `all` is the only directive it can be given
+    fn cloned(&self) -> impl Fn() -> i32 + '_ {
+        capture_only!(clone self, || self.count)
+    }
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+#![deny(deprecated)]
+
+use captures::*;
+use std::rc::Rc;
+
+// A parameter with the name of a directive's variable makes the directive useless
+fn main() {
+    let (a, b) = (Rc::new(1), 2);
+    let _ = capture!(clone a, move |a: i32| a + 1);
+    let _ = capture!(all b, |(b, c): (i32, i32)| b + c);
+    let _ = capture!(clone a, all b, move |x: i32| *a + b + x);
+}
@@ -0,0 +1,11 @@
+use captures::*;
+
+// Variables used in the arguments of std macros are blocked, including in format strings
+fn main() {
+    let (a, b, c) = (1, 2, 3);
+    let f = capture_only!(all a, move || {
+        assert_eq!(a, b);
+        println!("{c}");
+        matches!(a, x if x == b)
+    });
+}
@@ -0,0 +1,27 @@
+use captures::*;
+
+macro_rules! double {
+    ($e:expr) => {
+        $e * 2
+    };
+}
+
+// Only macros whose arguments can be checked are allowed with `strict_macros`
+fn main() {
+    let a = 1;
+    let _ = capture_only!(strict_macros, all a, move || {
+        assert_eq!(a, 1, "{a}");
+        let b = double!(a);
+        // Defining a macro is fine, since its body isn't run here
+        #[allow(unused_macros)]
+        macro_rules! triple {
+            ($e:expr) => {
+                $e * 3
+            };
+        }
+        let v = vec![stringify!(a), concat!("a", "b")];
+        let _ = capture!(clone v, move || v.len());
+        format!("{}", std::dbg!(b) + v.len() as i32)
+    });
+    let _ = capture!(strict_macros, all a, || double!(a));
+}
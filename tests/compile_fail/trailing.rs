@@ -0,0 +1,8 @@
+use captures::*;
+
+// Tokens after the closure are pointed out, along with what they look like
+fn main() {
+    let a = 1;
+    let _ = capture!(clone a, move || a, move || a);
+    let _ = capture!(clone a, { a } + 1);
+}
@@ -0,0 +1,7 @@
+use captures::*;
+
+// Names in scripts without case are reported like lowercase ones
+fn main() {
+    let 数量 = 3;
+    let f = capture_only!(|| 数量 + 1);
+}
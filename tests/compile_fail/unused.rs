@@ -0,0 +1,11 @@
+#![deny(deprecated)]
+
+use captures::*;
+
+// Directives whose variable is never used are reported
+fn main() {
+    let (a, b) = (1, 2);
+    let _ = capture!(clone a, with c = 3, move || b);
+    let _ = capture!(deny_unused, all a, || b);
+    let _ = bind!(ref a, { b });
+}
@@ -0,0 +1,9 @@
+use captures::*;
+
+// A broken `with` directive is skipped as a whole, even if it contains commas
+fn main() {
+    let a = 1;
+    let _ = capture!(with 1 = |x, y| x + y, clone a, move || a);
+    let _ = capture!(with add = |x: i32, y: i32| x +, all a, move || a);
+    let _ = capture!(with 2 = |x, y: i32| x + y, with 3 = |(x, y), Some(z)| z, ref a, move || a);
+}
@@ -0,0 +1,8 @@
+use captures::*;
+
+// Only a `with` directive may end with `;`
+fn main() {
+    let a = 1;
+    let _ = capture!(clone a; move || a);
+    let _ = capture!(with b = 1; ref a; move || a + b);
+}
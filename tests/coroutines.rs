@@ -0,0 +1,65 @@
+// Coroutines are unstable, so this only runs with
+// `RUSTFLAGS="--cfg captures_nightly" cargo +nightly test`
+#![cfg(captures_nightly)]
+#![feature(coroutines, coroutine_trait, stmt_expr_attributes)]
+
+use captures::*;
+
+use std::ops::{Coroutine, CoroutineState};
+use std::pin::Pin;
+use std::rc::Rc;
+
+// Checks that `yield` and resume arguments work under `capture_only!`
+fn resume() {
+    let a = Rc::new(10);
+    let mut co = capture_only!(clone a, #[coroutine] |mut x: i32| {
+        loop {
+            x = yield x + *a;
+        }
+    });
+    let mut co = Pin::new(&mut co);
+    assert!(matches!(co.as_mut().resume(1), CoroutineState::Yielded(11)));
+    assert!(matches!(co.as_mut().resume(2), CoroutineState::Yielded(12)));
+}
+
+// Checks that `static` coroutines may borrow across yields
+fn movable() {
+    let a = vec![1, 2];
+    let mut co = capture!(clone a, #[coroutine] static || {
+        for x in &a {
+            yield *x;
+        }
+        a.len()
+    });
+    let mut co = unsafe { Pin::new_unchecked(&mut co) };
+    assert!(matches!(co.as_mut().resume(()), CoroutineState::Yielded(1)));
+    assert!(matches!(co.as_mut().resume(()), CoroutineState::Yielded(2)));
+    assert!(matches!(
+        co.as_mut().resume(()),
+        CoroutineState::Complete(2)
+    ));
+}
+
+// Checks that `captures-lite` keeps the `#[coroutine]` in front of a `static` coroutine
+fn lite() {
+    let a = vec![1, 2];
+    let mut co = captures_lite::capture!(clone a, #[coroutine] static || {
+        for x in &a {
+            yield *x;
+        }
+        a.len()
+    });
+    let mut co = unsafe { Pin::new_unchecked(&mut co) };
+    assert!(matches!(co.as_mut().resume(()), CoroutineState::Yielded(1)));
+    assert!(matches!(co.as_mut().resume(()), CoroutineState::Yielded(2)));
+    assert!(matches!(
+        co.as_mut().resume(()),
+        CoroutineState::Complete(2)
+    ));
+}
+
+fn main() {
+    resume();
+    movable();
+    lite();
+}
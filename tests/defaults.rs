@@ -0,0 +1,57 @@
+use captures::*;
+
+use std::rc::Rc;
+
+// Checks that defaults apply only to the variables that are used
+fn used_only() {
+    let a = Rc::new(1);
+    let b = Rc::new(2);
+    capture_defaults! {
+        clone a, clone b;
+        let f = capture!(|| *a + 1);
+    }
+    assert_eq!(f(), 2);
+    assert_eq!(Rc::strong_count(&a), 2);
+    assert_eq!(Rc::strong_count(&b), 1);
+}
+
+// Checks that explicit directives and inner defaults take precedence
+fn overrides() {
+    let a = Rc::new(1);
+    let b = 2;
+    capture_defaults! {
+        clone a, with b = 10;
+        let f = capture!(ref a, move || **a + b);
+        capture_defaults! {
+            ref b;
+            let g = capture!(move || *a + *b);
+        }
+    }
+    assert_eq!(f(), 11);
+    assert_eq!(g(), 3);
+}
+
+// Checks that defaults reach into items, other macros, and `capture_ctx!`
+capture_defaults! {
+    clone db;
+
+    fn in_item(db: Rc<i32>) -> impl Fn() -> i32 {
+        let fs = vec![capture_only!(move || *db)];
+        fs.into_iter().next().unwrap()
+    }
+
+    fn in_ctx(ctx: &Ctx) -> impl Fn() -> i32 {
+        capture_ctx!(ctx => || *db)
+    }
+}
+
+struct Ctx {
+    db: Rc<i32>,
+}
+
+fn main() {
+    used_only();
+    overrides();
+    assert_eq!(in_item(Rc::new(1))(), 1);
+    assert_eq!(in_ctx(&Ctx { db: Rc::new(2) })(), 2);
+}
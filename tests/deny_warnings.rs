@@ -0,0 +1,136 @@
+#![deny(warnings)]
+#![deny(unused, nonstandard_style, rust_2018_idioms)]
+
+use captures::*;
+
+use std::ffi::c_void;
+use std::future::Future;
+use std::rc::Rc;
+
+define_captures!(handler_env = clone a, with n = 2);
+
+capture_fn!(struct Counter, with mut count: u32 = 0, clone step: Rc<u32>, |by: u32| -> u32 {
+    *count += by * **step;
+    *count
+});
+
+// The expansions of the directives don't cause warnings in crates that deny them
+fn directives() -> usize {
+    let (a, b, c, mut d) = (Rc::new(1), String::from("b"), vec![1, 2], 0);
+    let mut f = capture!(clone a, clone mut b, ref c, ref mut d, with e = c.len(), move || {
+        b.push('c');
+        *d += 1;
+        *a + b.len() + c.len() + e + *d
+    });
+    let (g, h) = (1, [2, 3]);
+    let mut i = capture_only!(all g, all h, with mut j = 0, move || {
+        j += g;
+        j + h[0]
+    });
+    f() + i()
+}
+
+// Variables that the body mentions only in part, or not at all, are still captured whole
+fn partly_used() -> usize {
+    let (a, _keep) = ((Rc::new(1), 2), Rc::new(3));
+    let f = capture!(clone a, clone _keep, move || a.1);
+    let h = capture!(with _unused = 3, move || 2);
+    let b = (vec![1], 2);
+    let g = capture!(all b, move || b.1);
+    f() + g() + h()
+}
+
+// A variable used by a later `with` is moved into it
+fn moved_by_with() -> usize {
+    let a = String::from("a");
+    let f = capture!(clone a, with b = a + "b", move || b.len());
+    f()
+}
+
+fn flags() -> usize {
+    let a = Rc::new(1);
+    let f = capture!(boxed dyn Fn() -> usize, clone a, move || *a);
+    let g = capture!(explicit_move, clone a, move || *a);
+    let h = capture!(introspect, clone a, move || *a; assert 'static);
+    let b = 1;
+    let i = capture!(all b, || b);
+    f() + g() + h() + i()
+}
+
+fn several() -> usize {
+    let a = Rc::new(1);
+    let (f, g) = capture!(clone a, (move || *a, move |x: usize| *a + x));
+    let h = capture!(use handler_env, move || *a + n);
+    f() + g(1) + h()
+}
+
+// Bindings and attributes in the body that `capture_only!` has to look at
+fn bodies() -> usize {
+    let (a, b) = (Some(1), 2);
+    let f = capture_only!(all a, all b, |p: Option<usize>| {
+        #[cfg(any())]
+        let b = 10;
+        #[allow(clippy::redundant_pattern_matching)]
+        if let Some(a) = p {
+            return a + b;
+        }
+        let Some(c) = a else { return b };
+        let inner = capture_only!(all c, move || match c {
+            0 => 1,
+            c => c * 2,
+        });
+        inner() + b
+    });
+    f(None) + f(Some(1))
+}
+
+fn lint() -> usize {
+    let a = Rc::new(1);
+    let f = capture_lint!(clone a, move || *a);
+    f()
+}
+
+#[captures]
+fn annotated() -> usize {
+    let a = Rc::new(1);
+    let f = #[capture(clone a)]
+    move || *a;
+    let g = #[capture_only(clone a)]
+    move || *a + 1;
+    f() + g()
+}
+
+fn blocks() -> usize {
+    let a = Rc::new(1);
+    let b = bind!(clone a, with c = 2, { *a + c });
+    let mut counter = Counter::new(&Rc::new(2));
+    b + counter.call_mut(1) as usize
+}
+
+fn futures() -> impl Future<Output = usize> {
+    let a = Rc::new(1);
+    capture_async!(clone a, async move { *a })
+}
+
+fn ffi() -> *mut c_void {
+    let a = Rc::new(1);
+    let (data, _call, _destroy) = capture_extern!(clone a, |x: i32| x + *a);
+    data
+}
+
+struct Point {
+    x: usize,
+}
+
+impl Point {
+    fn shifted(&self) -> impl Fn(usize) -> usize + '_ {
+        capture_only!(all self, move |by| self.x + by)
+    }
+}
+
+fn main() {
+    let _ = directives() + partly_used() + moved_by_with() + flags() + several() + bodies();
+    let _ = lint() + annotated() + blocks() + Point { x: 1 }.shifted()(1);
+    drop(futures());
+    let _ = ffi();
+}
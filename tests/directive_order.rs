@@ -0,0 +1,38 @@
+use captures::*;
+
+// Directives are applied from left to right, so a `with` sees the variables as left by the
+// directives before it, and the outer variables otherwise
+fn order() {
+    let x = vec![1, 2, 3];
+    let f = capture!(with n = x.len(), with x = x[1..].to_vec(), move || (n, x.len()));
+    assert_eq!(f(), (3, 2));
+    let f = capture!(with x = x[1..].to_vec(), with n = x.len(), move || (n, x.len()));
+    assert_eq!(f(), (2, 2));
+    let _ = &x;
+}
+
+// `capture_only!` applies them in the same order
+fn order_only() {
+    let x = vec![1, 2, 3];
+    let f = capture_only!(with n = x.len(), with x = x[1..].to_vec(), move || (n, x.len()));
+    assert_eq!(f(), (3, 2));
+    let f = capture_only!(with x = x[1..].to_vec(), with n = x.len(), move || (n, x.len()));
+    assert_eq!(f(), (2, 2));
+    let _ = &x;
+}
+
+// A `with` after `clone x` uses the clone, so the outer variable isn't moved
+fn after_clone() {
+    let x = String::from("a");
+    let f = capture_only!(clone x, with y = x + "b", move || y);
+    assert_eq!(f(), "ab");
+    let f = capture!(clone x, with y = x + "c", move || y);
+    assert_eq!(f(), "ac");
+    assert_eq!(x, "a");
+}
+
+fn main() {
+    order();
+    order_only();
+    after_clone();
+}
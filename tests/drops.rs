@@ -0,0 +1,74 @@
+use captures::*;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Mutex;
+
+type Log = Rc<RefCell<Vec<&'static str>>>;
+
+struct Noisy(&'static str, Log);
+
+impl Drop for Noisy {
+    fn drop(&mut self) {
+        self.1.borrow_mut().push(self.0);
+    }
+}
+
+// The directives only borrow the variables while the closure is created, and the temporaries of
+// a `with` are dropped right after it, like the lock guard here
+fn borrows() {
+    let mut x = vec![1, 2];
+    let m = Mutex::new(5);
+    let f =
+        capture!(clone x, with n = x.len(), with v = *m.lock().unwrap(), move || x.len() + n + v);
+    x.push(3);
+    let _guard = m.lock().unwrap();
+    assert_eq!(f(), 9);
+
+    // A `ref` directive borrows for as long as the closure lives, like a reference captured by hand
+    let mut y = vec![1];
+    {
+        let mut g = capture!(ref mut y, move || y.push(2));
+        g();
+    }
+    y.push(3);
+    assert_eq!(y, [1, 2, 3]);
+}
+
+// The closure owns the values of its directives, so they are dropped with it, even those it only
+// uses a field of, or doesn't use at all, like a guard
+fn drops() {
+    let log: Log = Rc::default();
+    let f = capture!(
+        with _guard = Noisy("guard", log.clone()),
+        with field = Noisy("field", log.clone()),
+        move || field.0.len()
+    );
+    log.borrow_mut().push("created");
+    assert_eq!(f(), 5);
+    drop(f);
+    assert_eq!(*log.borrow(), ["created", "guard", "field"]);
+
+    let log: Log = Rc::default();
+    let fut = capture_only!(with _guard = Noisy("future", log.clone()), async move {});
+    log.borrow_mut().push("created");
+    drop(fut);
+    assert_eq!(*log.borrow(), ["created", "future"]);
+}
+
+// A value that a later `with` uses may be moved there, and is then not captured
+fn moved_by_with() {
+    let log: Log = Rc::default();
+    let x = Noisy("x", log.clone());
+    let f = capture!(with x = x, with y = (x, 1), move || y.1);
+    assert_eq!(f(), 1);
+    assert!(log.borrow().is_empty());
+    drop(f);
+    assert_eq!(*log.borrow(), ["x"]);
+}
+
+fn main() {
+    borrows();
+    drops();
+    moved_by_with();
+}
@@ -0,0 +1,40 @@
+// The `trace` feature adds its logging to every expansion, which the strings don't include
+#![cfg(not(feature = "trace"))]
+
+use captures::*;
+
+const CLONE: &str = expand_str!(capture!(clone a, || *a));
+
+// Checks the strings, which are meant to stay the same across versions of the compiler
+fn strings() {
+    assert_eq!(
+        CLONE,
+        "{ # [ allow ( clippy :: shadow_same , clippy :: shadow_reuse , clippy :: shadow_unrelated ) ] \
+         let a = { fn captured_by_clone < T : :: core :: clone :: Clone > ( value : & T ) -> T \
+         { :: core :: clone :: Clone :: clone ( value ) } captured_by_clone ( & a ) } ; \
+         move | | { * a } }"
+    );
+    assert_eq!(
+        expand_str!(captures::capture_only!(with b = 1, move |x: i32| x + b)),
+        "{ # [ allow ( clippy :: shadow_same , clippy :: shadow_reuse , clippy :: shadow_unrelated ) ] \
+         let b = 1 ; move | x : i32 | { x + b } }"
+    );
+}
+
+// `gen` blocks need the 2024 edition and a nightly compiler to build, but expand like async blocks
+fn gen_blocks() {
+    assert_eq!(
+        expand_str!(capture_only!(with n = 2, gen { yield n; })),
+        "{ # [ allow ( clippy :: shadow_same , clippy :: shadow_reuse , clippy :: shadow_unrelated ) ] \
+         let n = 2 ; gen move { yield n ; } }"
+    );
+    assert_eq!(
+        expand_str!(capture!(all a, async gen move { yield a.0; })),
+        "{ async gen move { let _ = & a ; yield a . 0 ; } }"
+    );
+}
+
+fn main() {
+    strings();
+    gen_blocks();
+}
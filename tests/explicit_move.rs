@@ -0,0 +1,36 @@
+use captures::*;
+
+fn takes_static<T: 'static + FnOnce() -> i32>(f: T) -> i32 {
+    f()
+}
+
+// Written out, the `move` is accepted as usual
+fn written() {
+    let a: i32 = 1;
+    let out = takes_static(capture!(explicit_move, clone a, with b = 2, move || a + b));
+    assert_eq!(out, 3);
+}
+
+// Directives that don't need `move` don't need it with the flag either
+fn not_needed() {
+    let mut a: i32 = 1;
+    let mut f = capture!(explicit_move, all a, || {
+        a += 1;
+        a
+    });
+    assert_eq!(f(), 2);
+    assert_eq!(bind!(explicit_move, clone a, { a }), 2);
+}
+
+// `explain_move` has nothing to report when the `move` is written out
+fn explained() {
+    let a = 1;
+    let f = capture!(explain_move, explicit_move, clone a, move || a);
+    assert_eq!(f(), 1);
+}
+
+fn main() {
+    written();
+    not_needed();
+    explained();
+}
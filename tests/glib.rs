@@ -0,0 +1,56 @@
+// `glib` isn't needed to build the other tests, so its traits are stood in for by these, for
+// `Rc` instead of glib's objects
+#![cfg(feature = "glib")]
+
+use captures::*;
+
+use std::rc::Rc;
+
+mod gtk {
+    pub mod glib {
+        pub mod clone {
+            use std::rc::{Rc, Weak};
+
+            pub trait Downgrade {
+                type Weak;
+                fn downgrade(&self) -> Self::Weak;
+            }
+
+            pub trait Upgrade {
+                type Strong;
+                fn upgrade(&self) -> Option<Self::Strong>;
+            }
+
+            impl<T> Downgrade for Rc<T> {
+                type Weak = Weak<T>;
+                fn downgrade(&self) -> Weak<T> {
+                    Rc::downgrade(self)
+                }
+            }
+
+            impl<T> Upgrade for Weak<T> {
+                type Strong = Rc<T>;
+                fn upgrade(&self) -> Option<Rc<T>> {
+                    Weak::upgrade(self)
+                }
+            }
+        }
+    }
+}
+
+define_captures!(gtk_glib = glib = gtk::glib);
+
+// The `glib` flag gives the path that glib is reached through, like `gtk::glib`
+fn crate_path() {
+    let a = Rc::new(1);
+    let f = capture!(weak a, glib = gtk::glib, default_return = 0, move || *a + 1);
+    assert_eq!(f(), 2);
+    let g = capture!(use gtk_glib, weak a, default_return = 0, move || *a + 2);
+    assert_eq!(g(), 3);
+    drop(a);
+    assert_eq!((f(), g()), (0, 0));
+}
+
+fn main() {
+    crate_path();
+}
@@ -0,0 +1,7 @@
+use captures::*;
+
+fn main() {
+    let a = std::rc::Rc::new(1);
+    let _ = bind!(weak a, { *a });
+    let _ = bind!(weak a, default_return = 0, { *a });
+}
@@ -0,0 +1,6 @@
+use captures::*;
+
+fn main() {
+    let a = std::rc::Rc::new(1);
+    let _ = capture!(glib = gtk::glib, clone a, || *a);
+}
@@ -0,0 +1,39 @@
+use captures::*;
+
+use std::rc::Rc;
+
+define_captures!(shared = clone a, ref b);
+define_captures!(nested = use shared, with c = 3;);
+
+// Checks that the group's directives refer to the variables at the use site
+fn basic() {
+    let a = Rc::new(1);
+    let b = 2;
+    let f = capture!(use shared, move || *a + *b);
+    assert_eq!(f(), 3);
+    assert_eq!(Rc::strong_count(&a), 2);
+}
+
+// Checks that groups can be combined with other directives and with each other
+fn nested_groups() {
+    let a = Rc::new(1);
+    let b = 2;
+    let d = 4;
+    let f = capture!(clone d, use nested, move || *a + *b + c + d);
+    assert_eq!(f(), 10);
+}
+
+// Checks that groups work with the other macros
+fn other_macros() {
+    let a = Rc::new(1);
+    let b = 2;
+    let _fut = capture_async!(use shared, async move { *a + *b });
+    let f = capture_only!(use shared, move || *a + *b);
+    assert_eq!(f(), 3);
+}
+
+fn main() {
+    basic();
+    nested_groups();
+    other_macros();
+}
@@ -39,7 +39,295 @@ fn shadow() {
     assert_eq!(a, 100);
 }
 
+// Parameters are usable in the body, and shadow captures of the same name only within their closure
+fn params() {
+    let a = 1;
+    let f = capture_only!(clone a, |x: i32| {
+        let g = |a: i32| a * 2;
+        g(x) + a
+    });
+    assert_eq!(f(3), 7);
+
+    // After the inner closure, `x` is the captured variable again
+    let x = 10;
+    let iter = vec![1, 2];
+    let h = capture_only!(all x, all iter, || iter.iter().map(|x| x + 1).sum::<i32>() + x);
+    assert_eq!(h(), 15);
+}
+
+// Destructuring parameters bind the same names that the body uses, also in macros and in nested
+// closures whose parameters shadow captures
+fn param_patterns() {
+    macro_rules! double {
+        ($e:expr) => {
+            $e * 2
+        };
+    }
+
+    #[derive(Clone, Copy)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    let a = 1;
+    let b = 10;
+    let f = capture_only!(clone a, all b, |(q, c): (i32, i32), [d, _]: [i32; 2], Point { x, y }: Point| {
+        let e = double!(q) + c - a + 1;
+        assert_eq!(e, d, "{q} {c} {x}");
+        format!("{e}-{x}-{y}-{b}")
+    });
+    assert_eq!(f((2, 3), [7, 0], Point { x: 4, y: 5 }), "7-4-5-10");
+    let g = capture_only!(all a, |b: i32| move |a: i32| a + b);
+    assert_eq!(g(2)(3), 5);
+    let _ = a;
+}
+
+// Functions passed by name aren't mistaken for blocked variables
+fn fn_arguments() {
+    fn parse(s: &str) -> i32 {
+        s.parse().unwrap()
+    }
+
+    let a = ["1", "2"];
+    let f = capture_only!(ref a, move || a.iter().copied().map(parse).max());
+    assert_eq!(f(), Some(2));
+}
+
+// The arguments of std macros are cleaned like the rest of the closure, including the variables
+// used inline in format strings
+fn std_macros() {
+    use std::fmt::Write;
+
+    let (a, width) = (1, 4);
+    let f = capture_only!(all a, all width, move || {
+        let mut s = format!("{a:>width$}");
+        let b = a + 1;
+        write!(s, "{b}{}", a).unwrap();
+        assert_eq!(a, 1, "{a} {b}");
+        assert!(matches!(Some(b), Some(a) | Some(a @ 3) if a == 2));
+        let mut v = vec![a; 2];
+        v.push(a);
+        assert!(v.iter().all(|a| *a == 1));
+        s
+    });
+    assert_eq!(f(), "   121");
+}
+
+// Field init shorthand uses the variable, both for captures and for locals of the closure, and
+// shorthand patterns bind it
+fn field_shorthand() {
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    let x = 1;
+    let f = capture_only!(all x, move || {
+        let y = 2;
+        let p = Point { x, y };
+        let Point { x, y: _ } = p;
+        Point { x, y: x + p.y }
+    });
+    let p = f();
+    assert_eq!((p.x, p.y), (1, 3));
+}
+
+// Items defined in the body keep their own names and locals
+fn items() {
+    let a = 2;
+    let f = capture_only!(clone a, move || {
+        const OFFSET: i32 = 1;
+
+        struct Wrapper(i32);
+
+        impl Wrapper {
+            fn get(&self) -> i32 {
+                let a = self.0;
+                a + OFFSET
+            }
+        }
+
+        fn double(a: i32) -> i32 {
+            let b = a;
+            b * 2
+        }
+
+        macro_rules! triple {
+            ($e:expr) => {{
+                let x = $e;
+                x * 3
+            }};
+        }
+
+        Wrapper(double(triple!(a))).get()
+    });
+    assert_eq!(f(), 13);
+}
+
+// Lifetimes and loop labels keep their hygiene, including in the tokens passed to macros, and
+// those of trusted macros when a local has the same name
+fn labels() {
+    macro_rules! run {
+        ($($t:tt)*) => {
+            $($t)*
+        };
+    }
+    macro_rules! keep {
+        ($($t:tt)*) => {
+            $($t)*
+        };
+    }
+
+    let a = 3;
+    let f = capture_only!(clone a, trust keep!, move || {
+        let outer = 0;
+        let mut n = outer;
+        'outer: loop {
+            run!(for i in 0.. {
+                if i == a {
+                    break 'outer;
+                }
+                n += 1;
+            });
+        }
+        'outer: for _ in 0..2 {
+            let s: &'static str = run!("x");
+            n += s.len();
+            keep!(continue 'outer);
+        }
+        let empty: Vec<&'static str> = run!(Vec::<&'static str>::new());
+        n + keep!(outer) + empty.len()
+    });
+    assert_eq!(f(), 5);
+}
+
+// `let ... else` binds its pattern after the statement, so the initializer and the `else` block
+// still see the variable that the pattern shadows
+fn let_else() {
+    let a = Some(2);
+    let f = capture_only!(all a, move |n: Option<i32>| {
+        let Some(a) = a.map(|a| a * 10) else { return -1 };
+        let Some(b) = n.map(|n| n + a) else { return a };
+        #[allow(unused_variables)]
+        let (Some(c) | Some(c)) = n else { return 0 };
+        a + b
+    });
+    assert_eq!(f(Some(1)), 41);
+    assert_eq!(f(None), 20);
+}
+
+// Paths to items, associated items and generic parameters are never cleaned, even when a variable
+// of the closure has the same name as one of their segments
+#[derive(Debug, Default, PartialEq)]
+struct Unit(i32);
+
+enum Shape {
+    Dot,
+    Line(i32),
+}
+
+impl Unit {
+    const ONE: i32 = 1;
+
+    fn new() -> Self {
+        Unit(Self::ONE)
+    }
+
+    fn paths<T: Default + Into<i32>, const N: usize>(&self) -> i32 {
+        let f = capture_only!(all self, || {
+            let new = Self::new();
+            let default = T::default().into() + <T as Default>::default().into();
+            let dot = match Shape::Line(N as i32) {
+                Shape::Dot => 0,
+                Shape::Line(len) => len,
+            };
+            Self(self.0 + new.0 + Self::ONE + default + dot + <Unit>::ONE).0
+        });
+        f()
+    }
+}
+
+fn paths() {
+    assert_eq!(Unit::new().paths::<u8, 3>(), 7);
+    assert!(matches!(Shape::Dot, Shape::Dot));
+}
+
+// Attributes on statements keep working, and a `let` that `cfg` may remove only shadows the
+// captured variable if it is compiled. The code that `cfg` removes isn't checked for variables.
+fn attributes() {
+    let a = 1;
+    let f = capture_only!(all a, move || {
+        #[allow(unused_variables)]
+        let unused = 2;
+        #[cfg(any())]
+        let a = not_a_variable;
+        #[cfg(any())]
+        not_a_variable.clear();
+        #[rustfmt::skip]
+        let b   = a + 1;
+        #[cfg(all())]
+        let a = b * 10;
+        #[cfg_attr(all(), allow(unused_mut))]
+        let mut c = a;
+        c
+    });
+    assert_eq!(f(), 20);
+}
+
+// `use` items in the body keep working, including glob imports of enum variants, and the names they
+// import aren't mistaken for variables without a directive
+mod shapes {
+    #[derive(Clone, Copy)]
+    pub enum Shape {
+        Dot,
+        Line(i32),
+    }
+
+    #[allow(non_upper_case_globals)]
+    pub const origin: i32 = 1;
+
+    pub fn len(shape: Shape) -> i32 {
+        match shape {
+            Shape::Dot => 0,
+            Shape::Line(len) => len,
+        }
+    }
+}
+
+fn imports() {
+    let s = shapes::Shape::Line(3);
+    let f = capture_only!(all s, move || {
+        let total = {
+            let offset = start;
+            use shapes::origin as start;
+            offset + len(s)
+        };
+        use shapes::len;
+        use shapes::Shape::*;
+        use std::collections::HashMap as Map;
+        let mut lens = Map::new();
+        lens.insert(len(Dot), total);
+        match s {
+            Dot => 0,
+            Line(n) => n + lens[&0],
+        }
+    });
+    assert_eq!(f(), 7);
+}
+
 fn main() {
     context();
     shadow();
+    params();
+    param_patterns();
+    fn_arguments();
+    std_macros();
+    field_shorthand();
+    items();
+    labels();
+    let_else();
+    paths();
+    attributes();
+    imports();
 }
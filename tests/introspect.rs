@@ -0,0 +1,39 @@
+use captures::*;
+
+use std::rc::Rc;
+
+// Checks the recorded directives, and that the closure can still be called
+fn basic() {
+    let a = Rc::new(1);
+    let mut b = 2;
+    let c = 3;
+    let mut f = capture!(introspect, clone a, ref mut b, with mut d = 4, all c, move || {
+        *b += 1;
+        d += 1;
+        *a + c + d
+    });
+    assert_eq!(
+        format!("{:?}", f),
+        "Captured([clone a, ref mut b, with mut d, all c])"
+    );
+    assert_eq!(f.captures()[0].name, "a");
+    assert_eq!((*f)(), 9);
+    drop(f);
+    assert_eq!(b, 3);
+}
+
+// Checks that the flag works together with the other macros and flags
+fn other_forms() {
+    let a = Rc::new(1);
+    let f = capture_only!(introspect, boxed dyn Fn() -> i32, clone a, || *a);
+    assert_eq!(f.captures().len(), 1);
+    let g: Box<dyn Fn() -> i32> = f.into_inner();
+    assert_eq!(g(), 1);
+    let (h, _) = capture!(introspect, with x = 1, (move || x, move || x));
+    assert_eq!(format!("{:?}", h), "Captured([with x])");
+}
+
+fn main() {
+    basic();
+    other_forms();
+}
@@ -0,0 +1,22 @@
+use captures::*;
+
+use std::rc::Rc;
+
+// Variables may have the names of the directives and flags
+fn main() {
+    let clone = Rc::new(1);
+    let all = 2;
+    let with = 3;
+    let debug = 4;
+    let mut name = 5;
+    let mut f = capture!(clone clone, with with = with + 1, ref mut name, move || {
+        *name += 1;
+        *clone + with
+    });
+    assert_eq!(f(), 5);
+    let g = capture_only!(all all, clone debug, ref clone, move || all + debug + **clone);
+    assert_eq!(g(), 7);
+    let h = capture!(with r#with = all, clone r#clone, move || r#with + *clone);
+    assert_eq!(h(), 3);
+    assert_eq!(name, 6);
+}
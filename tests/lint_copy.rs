@@ -0,0 +1,29 @@
+use captures::*;
+use std::rc::Rc;
+
+// Values that aren't `Copy` are cloned without a warning, including those of a type parameter
+// that isn't known to be `Copy`
+fn not_copy<T: Clone + std::fmt::Display>(value: T) {
+    let (a, b) = (Rc::new(1), String::from("b"));
+    let f = capture!(lint_copy, clone a, clone b, clone value, move || {
+        *a as usize + b.len() + value.to_string().len()
+    });
+    assert_eq!(f(), 3);
+}
+
+struct Ctx {
+    name: String,
+}
+
+fn ctx() {
+    let ctx = Ctx {
+        name: "ctx".to_string(),
+    };
+    let f = capture_ctx!(ctx => lint_copy, clone name, move || name.len());
+    assert_eq!(f(), 3);
+}
+
+fn main() {
+    not_copy(5);
+    ctx();
+}
@@ -0,0 +1,79 @@
+// Checks that `captures-lite` expands invocations the same way as `captures`
+use std::rc::Rc;
+
+// Expands the invocation with both crates
+macro_rules! both {
+    ($($input:tt)+) => {
+        (
+            captures::capture!($($input)+),
+            captures_lite::capture!($($input)+),
+        )
+    };
+}
+
+fn takes_static<T: 'static>(t: T) -> T {
+    t
+}
+
+// Without directives the target is left as it is, so these closures only borrow `s`. The
+// `pedantic` feature warns about such invocations of `captures`
+#[allow(deprecated)]
+fn no_directives() {
+    let s = String::from("ab");
+    let (f, g) = both!(|| s.len());
+    assert_eq!(f() + g(), 4);
+    let (h, i) = both!(async { s.len() });
+    drop((h, i));
+    assert_eq!(s, "ab");
+}
+
+// Directives and the `move` flag make the target `move`
+fn made_move() {
+    let a = Rc::new(1);
+    let b = 2;
+    let (f, g) = both!(clone a, || *a + b);
+    assert_eq!(takes_static(f)() + takes_static(g)(), 6);
+    let (h, i) = both!(move, || b);
+    assert_eq!(takes_static(h)() + takes_static(i)(), 4);
+    let (j, k) = both!(with c = 3, async { c });
+    drop((takes_static(j), takes_static(k)));
+    assert_eq!(Rc::strong_count(&a), 1);
+}
+
+// `ref` directives alone leave the target as written, so it has to be `move` already, unless the
+// `move` flag is given
+fn references() {
+    let (a, mut b) = (vec![1], 2);
+    let (f, g) = both!(ref a, move || a.len());
+    assert_eq!(f() + g(), 2);
+    let (h, i) = both!(ref a, async move { a.len() });
+    drop((h, i));
+    let (j, k) = both!(move, ref a, || a.len());
+    assert_eq!(j() + k(), 2);
+    let mut l = captures::capture!(ref mut b, move || *b += 1);
+    l();
+    let mut m = captures_lite::capture!(ref mut b, move || *b += 1);
+    m();
+    assert_eq!((a, b), (vec![1], 4));
+}
+
+// The target may be followed by a comma, also without directives, which `pedantic` warns about
+#[allow(deprecated)]
+fn trailing_comma() {
+    let a = Rc::new(1);
+    let (f, g) = both!(clone a, || *a,);
+    assert_eq!(takes_static(f)() + takes_static(g)(), 2);
+    let (h, i) = both!(ref a, move || **a + 1,);
+    assert_eq!(h() + i(), 4);
+    let (j, k) = both!(|| *a,);
+    assert_eq!(j() + k(), 2);
+    let (l, m) = both!(with b = 1; async { b },);
+    drop((takes_static(l), takes_static(m)));
+}
+
+fn main() {
+    no_directives();
+    made_move();
+    references();
+    trailing_comma();
+}
@@ -0,0 +1,99 @@
+use captures::*;
+
+use std::rc::Rc;
+
+// Variables and bodies passed in from the caller
+macro_rules! cloned {
+    ($v:ident, $body:expr) => {
+        capture!(clone $v, move || $body)
+    };
+}
+
+// The closure as a whole passed in from the caller
+macro_rules! with_closure {
+    ($v:ident, $closure:expr) => {
+        capture!(clone $v, $closure)
+    };
+}
+
+// Directives passed in from the caller, separately from the closure
+macro_rules! directives {
+    ([$($dir:tt)*] $closure:expr) => {
+        capture_only!($($dir)*, $closure)
+    };
+}
+
+// Variables that only exist inside the macro
+macro_rules! internal {
+    ($e:expr) => {{
+        let tmp = Rc::new($e);
+        capture!(clone tmp, move || *tmp + 1)
+    }};
+}
+
+// Closures written by the caller, using a variable that only exists inside the macro
+macro_rules! scoped {
+    ($tmp:ident => $closure:expr) => {{
+        let $tmp = Rc::new(1);
+        capture_only!(clone $tmp, $closure)
+    }};
+}
+
+define_captures!(shared = clone a);
+
+// Groups used inside the macro refer to the macro's own variables, like any other directive
+macro_rules! grouped {
+    ($e:expr) => {{
+        let a = Rc::new($e);
+        capture!(use shared, move || *a)
+    }};
+}
+
+// Invoking the macros through a path, as macros exported from other crates do
+pub use captures::capture as reexported;
+macro_rules! through_crate {
+    ($v:ident) => {
+        $crate::reexported!(clone $v, move || *$v)
+    };
+}
+
+fn fragments() {
+    let a = Rc::new(1);
+    let f = cloned!(a, *a + 1);
+    assert_eq!(f(), 2);
+    let g = with_closure!(a, move || *a);
+    assert_eq!(g(), 1);
+    assert_eq!(Rc::strong_count(&a), 3);
+}
+
+fn macro_directives() {
+    let a = 1;
+    let b = Rc::new(2);
+    let f = directives!([all a, clone b] move || a + *b);
+    assert_eq!(f(), 3);
+}
+
+fn macro_variables() {
+    let f = internal!(1);
+    assert_eq!(f(), 2);
+    let g = scoped!(tmp => move || *tmp);
+    assert_eq!(g(), 1);
+}
+
+fn groups_and_paths() {
+    let f = grouped!(1);
+    assert_eq!(f(), 1);
+    let a = Rc::new(2);
+    let g = directives!([use shared] move || *a);
+    assert_eq!(g(), 2);
+    let h = through_crate!(a);
+    assert_eq!(h(), 2);
+    assert_eq!(Rc::strong_count(&a), 3);
+}
+
+fn main() {
+    fragments();
+    macro_directives();
+    macro_variables();
+    groups_and_paths();
+}
@@ -0,0 +1,39 @@
+use captures::*;
+
+use std::rc::Rc;
+
+macro_rules! adder {
+    ($v:ident) => {
+        move |x: i32| x + *$v
+    };
+}
+
+macro_rules! ready {
+    ($e:expr) => {
+        async move { $e }
+    };
+}
+use ready;
+
+// Checks that the directives apply to the variables passed to the macro
+fn closure() {
+    let v = Rc::new(1);
+    let f = capture!(clone v, adder!(v));
+    assert_eq!(Rc::strong_count(&v), 2);
+    assert_eq!(f(2), 3);
+}
+
+// Checks paths, `ref` directives, and macros in a tuple
+fn other_forms() {
+    let v = 1;
+    let f = capture!(ref v, adder!(v));
+    assert_eq!(f(1), 2);
+    let (f, g) = capture!(with w = Rc::new(2), (adder!(w), move || *w));
+    assert_eq!(f(1) + g(), 5);
+    let _fut = capture_async!(with v = 5, crate::ready!(v));
+}
+
+fn main() {
+    closure();
+    other_forms();
+}
@@ -0,0 +1,54 @@
+use captures::*;
+
+struct Counter {
+    count: i32,
+    step: i32,
+}
+
+#[captures]
+impl Counter {
+    fn count(&self) -> i32 {
+        self.count
+    }
+
+    // `self` can be exempt with `all`, and be used next to the other directives
+    fn next(&self) -> impl Fn() -> i32 + '_ {
+        let step = self.step;
+        capture_only!(all self, clone step, move || self.count() + step)
+    }
+
+    fn reset(&mut self) {
+        let mut f = capture_only!(all self, || self.count = 0);
+        f();
+    }
+
+    // `all self` works the same in annotations, `capture_lint!` and nested macros
+    fn annotated(&self) -> i32 {
+        let f = #[capture_only(all self)]
+        || self.count + 1;
+        let g = capture_lint!(all self, || self.count + 2);
+        let h = capture_only!(all self, || {
+            let inner = capture_only!(all self, || self.count * 2);
+            inner() + Self::one()
+        });
+        f() + g() + h()
+    }
+
+    fn one() -> i32 {
+        1
+    }
+
+    // A method taking `self` by value can move it into the closure
+    fn into_count(self) -> impl FnOnce() -> i32 {
+        capture!(all self, move || self.count)
+    }
+}
+
+fn main() {
+    let mut c = Counter { count: 2, step: 3 };
+    assert_eq!(c.next()(), 5);
+    assert_eq!(c.annotated(), 3 + 4 + 5);
+    c.reset();
+    assert_eq!(c.count(), 0);
+    assert_eq!(c.into_count()(), 0);
+}
@@ -0,0 +1,24 @@
+use captures::*;
+
+use std::rc::Rc;
+
+fn takes_static<T: 'static>(t: T) -> T {
+    t
+}
+
+// The `move` flag makes the closure or async block `move`
+fn main() {
+    let a = Rc::new(1);
+    let b = 2;
+    let f = takes_static(capture!(move, all b, || b));
+    assert_eq!(f(), 2);
+    let g = takes_static(capture!(move, explicit_move, clone a, || *a));
+    assert_eq!(g(), 1);
+    let h = takes_static(capture_only!(move, all b, || b + 1));
+    assert_eq!(h(), 3);
+    let _fut = takes_static(capture!(move, all b, async { b }));
+    let (i, j) = capture!(move, all b, (|| b, move || b));
+    assert_eq!(takes_static(i)() + takes_static(j)(), 4);
+    let k = takes_static(capture!([move, clone a] => || *a));
+    assert_eq!(k(), 1);
+}
@@ -0,0 +1,29 @@
+use captures::*;
+
+use std::rc::Rc;
+
+// Checks that invocations nested in a `capture_only!` see the outer captures and `all` variables
+fn nested() {
+    let a = Rc::new(1);
+    let c = 3;
+    let f = capture_only!(clone a, all c, move || {
+        let g = capture!(clone a, move || *a + c);
+        let h = capture_only!(clone a, all c, move || *a + c);
+        let i = capture!(with d = 4, move || d + c);
+        g() + h() + i()
+    });
+    assert_eq!(f(), 15);
+    assert_eq!(Rc::strong_count(&a), 2);
+}
+
+// Checks that `all` variables can be passed to other macros too
+fn other_macros() {
+    let c = 3;
+    let f = capture_only!(all c, || format!("{}", c));
+    assert_eq!(f(), "3");
+}
+
+fn main() {
+    nested();
+    other_macros();
+}
@@ -0,0 +1,8 @@
+use captures::*;
+
+// With `nightly-diagnostics`, the warnings are compiler warnings with the suggestion as help
+fn main() {
+    let (a, b) = (1, 2);
+    let _ = capture!(all a, with c = 3, move || c);
+    let _ = capture_only!(all a, || a + b);
+}
@@ -0,0 +1,91 @@
+//! The expansions name everything by its full path, so the macros work in modules without a
+//! prelude.
+
+#[no_implicit_prelude]
+mod without_prelude {
+    use ::captures::*;
+    use ::std::boxed::Box;
+    use ::std::convert::From;
+    use ::std::ops::Fn;
+    use ::std::rc::Rc;
+    use ::std::string::String;
+    use ::std::vec::Vec;
+    use ::std::{assert_eq, format, vec};
+
+    capture_fn!(struct Counter, with mut count: u32 = 0, clone step: Rc<u32>, |by: u32| -> u32 {
+        *count += by * **step;
+        *count
+    });
+
+    define_captures!(shared = clone a, with n = 2);
+
+    pub fn directives() {
+        let (a, b, c, mut d) = (Rc::new(1), String::from("b"), vec![1, 2], 0);
+        let mut f = capture!(clone a, clone mut b, ref c, ref mut d, with e = c.len(), move || {
+            b.push('c');
+            *d += 1;
+            *a + b.len() + c.len() + e + *d
+        });
+        assert_eq!(f(), 1 + 2 + 2 + 2 + 1);
+        let (g, h) = (1, (2, 3));
+        let mut i = capture_only!(all g, all h, with mut j = 0, move || {
+            j += g;
+            j + h.0
+        });
+        assert_eq!(i(), 3);
+        let k = capture!(use shared, move || *a + n);
+        assert_eq!(k(), 3);
+        let l = capture_ctx!(a.as_ref() => with a = *a + 1, || a);
+        assert_eq!(l(), 2);
+        assert_eq!(bind!(clone a, { *a }), 1);
+    }
+
+    pub fn flags() {
+        let a = Rc::new(1);
+        let f = capture!(boxed dyn Fn() -> i32, clone a, move || *a);
+        let g = capture!(boxed, clone a, move || *a);
+        let h = capture!(introspect, clone a, move || *a; assert 'static);
+        assert_eq!(f() + g() + h(), 3);
+        assert_eq!(format!("{:?}", h), "Captured([clone a])");
+        let (i, j) = capture!(clone a, (move || *a, move |x: i32| *a + x));
+        assert_eq!(i() + j(1), 3);
+        let _boxed: Box<dyn Fn() -> i32> = f;
+    }
+
+    pub fn others() {
+        let a = Rc::new(1);
+        let mut counter = Counter::new(&Rc::new(2));
+        assert_eq!(counter.call_mut(1), 2);
+        let fut = capture_async!(clone a, async move { *a });
+        let _ = &fut;
+        let v: Vec<i32> = Vec::new();
+        let f = capture_lint!(clone a, with v = v, move || *a + v.len() as i32);
+        assert_eq!(f(), 1);
+        assert_static!(f);
+        assert_send_closure!(capture!(with n = 1, move || n));
+        let (data, call, destroy) = capture_extern!(clone a, |x: i32| x + *a);
+        unsafe {
+            assert_eq!(call(1, data), 2);
+            destroy(data);
+        }
+    }
+
+    #[captures]
+    pub fn annotated() {
+        let a = Rc::new(1);
+        let f = #[capture_only(clone a, with b = 2)]
+        move || *a + b;
+        capture_defaults! {
+            clone a;
+            let g = capture!(move || *a + 1);
+        }
+        assert_eq!(f() + g(), 5);
+    }
+}
+
+fn main() {
+    without_prelude::directives();
+    without_prelude::flags();
+    without_prelude::others();
+    without_prelude::annotated();
+}
@@ -0,0 +1,52 @@
+//! The expansions only name `core`, and `alloc` through a crate they declare themselves, so the
+//! macros work in `#![no_std]` crates.
+#![no_std]
+
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use captures::*;
+use core::future::Future;
+use core::pin::Pin;
+
+fn directives() {
+    let a = Rc::new(1);
+    let mut b = 2;
+    let mut f = capture!(clone a, with c = 3, ref mut b, move || {
+        *b += *a + c;
+    });
+    f();
+    assert_eq!(b, 6);
+    let mut g = capture_only!(clone mut a, all b, move || {
+        a = Rc::new(b);
+        *a
+    });
+    assert_eq!(g(), 6);
+    let h = capture_ctx!(a.as_ref() => with a = *a + 1, || a);
+    assert_eq!(h(), 2);
+    assert_eq!(bind!(clone a, { *a }), 1);
+}
+
+fn boxed() {
+    let a = Rc::new(1);
+    let f: Box<dyn Fn() -> i32> = capture!(boxed dyn Fn() -> i32, clone a, || *a);
+    assert_eq!(f(), 1);
+    let _fut: Pin<Box<dyn Future<Output = i32>>> = capture_async!(clone a, async { *a });
+}
+
+fn extern_callbacks() {
+    let a = Rc::new(1);
+    let (data, call, destroy) = capture_extern!(clone a, |x: i32| x + *a);
+    unsafe {
+        assert_eq!(call(1, data), 2);
+        destroy(data);
+    }
+    assert_eq!(Rc::strong_count(&a), 1);
+}
+
+fn main() {
+    directives();
+    boxed();
+    extern_callbacks();
+}
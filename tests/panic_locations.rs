@@ -0,0 +1,65 @@
+use captures::*;
+
+use std::cell::Cell;
+use std::panic::{self, Location};
+
+thread_local! {
+    static PANICKED_AT: Cell<Option<(u32, u32)>> = Cell::new(None);
+}
+
+#[track_caller]
+fn caller() -> &'static Location<'static> {
+    Location::caller()
+}
+
+// Runs the closure, returning the line and column that it panicked at
+fn panic_location(f: impl FnOnce() + panic::UnwindSafe) -> (u32, u32) {
+    panic::set_hook(Box::new(|info| {
+        let location = info.location().unwrap();
+        PANICKED_AT.with(|at| at.set(Some((location.line(), location.column()))));
+    }));
+    assert!(panic::catch_unwind(f).is_err());
+    let _ = panic::take_hook();
+    PANICKED_AT.with(Cell::take).unwrap()
+}
+
+// `line!()` and `#[track_caller]` functions see the line that they are written on, also when the
+// name of the function is cleaned
+fn lines() {
+    let start = line!();
+    let a = 1;
+    let f = capture_only!(all a, move || {
+        let here = caller();
+        let line = line!();
+        (here.line(), here.column(), line, a)
+    });
+    assert_eq!(f(), (start + 3, 20, start + 4, 1));
+}
+
+// Panics from macros, methods and indexing are reported where they are written
+fn panics(none: Option<i32>) {
+    let start = line!();
+    let v = vec![1];
+    let f = capture_only!(all none, || {
+        assert!(none.is_some());
+    });
+    assert_eq!(panic_location(f), (start + 3, 9));
+    let g = capture_only!(all none, || {
+        none.unwrap();
+    });
+    assert_eq!(panic_location(g), (start + 7, 14));
+    let h = capture_only!(all none, with v = v.clone(), move || {
+        let _ = v[none.unwrap_or(1) as usize];
+    });
+    assert_eq!(panic_location(h), (start + 11, 18));
+    let i = capture_only!(all none, || {
+        let Some(_) = none else { unreachable!() };
+    });
+    assert_eq!(panic_location(i), (start + 15, 35));
+    drop(v);
+}
+
+fn main() {
+    lines();
+    panics(None);
+}
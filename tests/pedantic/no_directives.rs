@@ -0,0 +1,17 @@
+use captures::*;
+
+struct Ctx {
+    a: i32,
+}
+
+// Invocations that leave the closure unchanged are warned about, the others aren't
+fn main() {
+    let a = 1;
+    let ctx = Ctx { a: 1 };
+    let _ = capture!(|| a + 1);
+    let _ = capture_ctx!(ctx => move || ctx.a);
+    let _ = capture!(all a, || a + 1);
+    let _ = capture!(explicit_move, move || a + 1);
+    let _ = capture_only!(|| 1);
+    compile_error!("warnings only");
+}
@@ -0,0 +1,33 @@
+use captures::*;
+
+use std::rc::Rc;
+
+// Directives on raw identifiers, which match the variable whether or not the body writes `r#`
+fn directives() {
+    let r#type = Rc::new(1);
+    let r#foo = 2;
+    let f = capture!(clone r#type, move || *r#type);
+    let g = capture!(with r#match = 3, move || r#match);
+    assert_eq!(f() + g(), 4);
+    assert_eq!(Rc::strong_count(&r#type), 2);
+
+    let h = capture_only!(clone r#type, all foo, move || *r#type + r#foo);
+    let i = capture_only!(all r#foo, ref r#type, move || foo + **r#type);
+    assert_eq!(h() + i(), 6);
+}
+
+// Raw identifiers are exempt from cleaning in `capture_only!`, including in macros
+fn only() {
+    let r#type = vec![1, 2];
+    let r#in = 3;
+    let f = capture_only!(clone r#type, all r#in, move || {
+        assert_eq!(r#type.len(), 2);
+        format!("{}{}", r#type[0], r#in)
+    });
+    assert_eq!(f(), "13");
+}
+
+fn main() {
+    directives();
+    only();
+}
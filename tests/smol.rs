@@ -0,0 +1,19 @@
+#![cfg(feature = "smol")]
+
+use captures::*;
+
+use std::sync::Arc;
+
+// Directives are applied and the task's output is available through the handle
+fn spawn() {
+    smol::block_on(async {
+        let state = Arc::new(5);
+        let task = spawn_smol!(clone state, with offset = 2, async { *state + offset });
+        assert_eq!(task.await, 7);
+        assert_eq!(*state, 5);
+    });
+}
+
+fn main() {
+    spawn();
+}
@@ -0,0 +1,32 @@
+#![cfg(feature = "thread")]
+
+use captures::*;
+
+use std::sync::{mpsc, Arc};
+
+// Directives are applied and the handle is returned
+fn basic() {
+    let (tx, rx) = mpsc::channel();
+    let data = Arc::new(5);
+    let handle = spawn!(clone tx, clone data, || {
+        tx.send(*data + 1).unwrap();
+        *data
+    });
+    assert_eq!(handle.join().unwrap(), 5);
+    assert_eq!(rx.recv().unwrap(), 6);
+    drop(tx);
+}
+
+// The name can be any expression converting into a `String`
+fn named() {
+    let prefix = "worker";
+    let handle = spawn!(name = format!("{}-{}", prefix, 1), || {
+        std::thread::current().name().map(String::from)
+    });
+    assert_eq!(handle.join().unwrap().as_deref(), Some("worker-1"));
+}
+
+fn main() {
+    basic();
+    named();
+}
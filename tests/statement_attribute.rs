@@ -0,0 +1,35 @@
+// Attribute macros on statements are unstable, so this only runs with
+// `RUSTFLAGS="--cfg captures_nightly" cargo +nightly test`
+#![cfg(captures_nightly)]
+#![feature(proc_macro_hygiene)]
+
+use captures::*;
+
+use std::rc::Rc;
+
+fn takes_static<T: 'static + FnOnce() -> i32>(f: T) -> i32 {
+    f()
+}
+
+// The directives are applied to the closure the variable is initialized with
+fn closure() {
+    let a = Rc::new(1);
+    #[captures(clone a, with b = 2)]
+    let f = |x: i32| *a + b + x;
+    assert_eq!(takes_static(move || f(3)), 6);
+    assert_eq!(Rc::strong_count(&a), 1);
+}
+
+// Other attributes on the statement are kept
+fn async_block() {
+    let a = Rc::new(1);
+    #[captures(clone a)]
+    #[allow(unused_variables)]
+    let fut = async { *a };
+    drop(fut);
+}
+
+fn main() {
+    closure();
+    async_block();
+}
@@ -0,0 +1,36 @@
+#![cfg(feature = "tokio")]
+
+use captures::*;
+
+use std::sync::Arc;
+
+fn runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+}
+
+// Directives are applied and the task's output is available through the handle
+fn spawn() {
+    runtime().block_on(async {
+        let state = Arc::new(5);
+        let handle = spawn_task!(clone state, with offset = 2, async { *state + offset });
+        assert_eq!(handle.await.unwrap(), 7);
+        assert_eq!(*state, 5);
+    });
+}
+
+// Same for blocking tasks
+fn blocking() {
+    runtime().block_on(async {
+        let state = Arc::new(5);
+        let handle = spawn_blocking!(clone state, move || *state + 1);
+        assert_eq!(handle.await.unwrap(), 6);
+        assert_eq!(*state, 5);
+    });
+}
+
+fn main() {
+    spawn();
+    blocking();
+}
@@ -0,0 +1,59 @@
+#![cfg(feature = "trace")]
+
+use captures::*;
+
+use std::sync::Mutex;
+
+/// Collects the messages, which is all these tests need
+struct Messages(Mutex<Vec<String>>);
+
+impl log::Log for Messages {
+    fn enabled(&self, _: &log::Metadata<'_>) -> bool {
+        true
+    }
+    fn log(&self, record: &log::Record<'_>) {
+        self.0.lock().unwrap().push(record.args().to_string());
+    }
+    fn flush(&self) {}
+}
+
+#[allow(clippy::incompatible_msrv)]
+static MESSAGES: Messages = Messages(Mutex::new(Vec::new()));
+
+fn take() -> Vec<String> {
+    std::mem::take(&mut *MESSAGES.0.lock().unwrap())
+}
+
+fn main() {
+    log::set_logger(&MESSAGES).unwrap();
+    log::set_max_level(log::LevelFilter::Trace);
+
+    let a = String::from("a");
+    let b = 1;
+    let f = capture!(clone a, with mut b = b, move || {
+        b += 1;
+        a.len() + b
+    });
+    let line = line!() - 4;
+    assert_eq!(
+        take(),
+        [format!(
+            "closure at {}:{} created, capturing clone a, with mut b",
+            file!(),
+            line
+        )]
+    );
+    drop(f);
+    assert_eq!(take(), [format!("closure at {}:{} dropped", file!(), line)]);
+
+    // Closures that aren't `move` only log their creation
+    let f = capture!(all a, || a.len());
+    assert_eq!(f(), 1);
+    assert_eq!(take().len(), 1);
+
+    let fut = capture_async!(clone a, async move { a.len() });
+    drop(fut);
+    let messages = take();
+    assert!(messages[0].starts_with("async block at"));
+    assert!(messages[1].ends_with("dropped"));
+}
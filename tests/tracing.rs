@@ -0,0 +1,83 @@
+#![cfg(feature = "tracing")]
+
+use captures::*;
+
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+use tracing_core::span::Current;
+
+/// Keeps track of the entered spans, which is all these tests need
+#[derive(Default)]
+struct Spans {
+    next: AtomicU64,
+}
+
+thread_local! {
+    static ENTERED: RefCell<Vec<(Id, &'static Metadata<'static>)>> = RefCell::new(Vec::new());
+    static META: RefCell<Vec<&'static Metadata<'static>>> = RefCell::new(Vec::new());
+}
+
+impl Subscriber for Spans {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        META.with(|m| m.borrow_mut().push(span.metadata()));
+        Id::from_u64(self.next.fetch_add(1, Ordering::Relaxed) + 1)
+    }
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+    fn event(&self, _: &Event<'_>) {}
+    fn enter(&self, id: &Id) {
+        let meta = META.with(|m| m.borrow()[id.into_u64() as usize - 1]);
+        ENTERED.with(|e| e.borrow_mut().push((id.clone(), meta)));
+    }
+    fn exit(&self, _: &Id) {
+        ENTERED.with(|e| e.borrow_mut().pop());
+    }
+    fn current_span(&self) -> Current {
+        ENTERED.with(|e| match e.borrow().last() {
+            Some((id, meta)) => Current::new(id.clone(), meta),
+            None => Current::none(),
+        })
+    }
+}
+
+fn current() -> Option<&'static str> {
+    tracing::Span::current().metadata().map(|m| m.name())
+}
+
+// Checks that the span that is current when the closure is created is entered in each call
+fn closures() {
+    let a = Arc::new(1);
+    let f = tracing::info_span!("request").in_scope(|| {
+        capture!(instrument, clone a, || {
+            assert_eq!(current(), Some("request"));
+            *a
+        })
+    });
+    assert_eq!(current(), None);
+    assert_eq!(f() + f(), 2);
+    assert_eq!(current(), None);
+}
+
+// Checks that async blocks are instrumented instead
+fn async_blocks() {
+    let fut = tracing::info_span!("task").in_scope(|| {
+        capture_async!(instrument, with n = 1, async move {
+            assert_eq!(current(), Some("task"));
+            n
+        })
+    });
+    assert_eq!(smol::block_on(fut), 1);
+}
+
+fn main() {
+    tracing::subscriber::with_default(Spans::default(), || {
+        closures();
+        async_blocks();
+    });
+}
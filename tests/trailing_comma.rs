@@ -0,0 +1,22 @@
+use captures::*;
+
+// A comma after the target is allowed, as rustfmt or a code generator may leave one
+fn main() {
+    let a = 1;
+    let f = capture!(clone a, move || a,);
+    assert_eq!(f(), 1);
+    let g = capture_only!(all a, || a + 1,);
+    assert_eq!(g(), 2);
+    let h = capture!(
+        with b = 2,
+        move || {
+            let c = [a, b];
+            c.iter().sum::<i32>()
+        },
+    );
+    assert_eq!(h(), 3);
+    let (i, j) = capture!(clone a, (move || a, move || a + 1),);
+    assert_eq!(i() + j(), 3);
+    let k = capture!(clone a, move || a; assert Send,);
+    assert_eq!(k(), 1);
+}
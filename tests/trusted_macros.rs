@@ -0,0 +1,38 @@
+use captures::*;
+
+mod logging {
+    macro_rules! log_ctx {
+        ($ctx:expr, $($t:tt)*) => {
+            format!("[{}] {}", $ctx, format!($($t)*))
+        };
+    }
+    pub(crate) use log_ctx;
+}
+
+use logging::log_ctx;
+
+// Trusted macros keep the hygiene of their tokens, so they can be passed variables without a
+// directive, like the logging context `ctx`, while still seeing the locals of the closure
+fn trusted() {
+    let ctx = "req";
+    let id = 7;
+    let f = capture_only!(clone id, trust logging::log_ctx!, move || {
+        let next = id + 1;
+        let a = log_ctx!(ctx, "{} {}", id, next);
+        let b = logging::log_ctx!(ctx, "{}", next);
+        a + &b
+    });
+    assert_eq!(f(), "[req] 7 8[req] 8");
+}
+
+// Trusted macros are also allowed with `strict_macros`
+fn strict() {
+    let ctx = 1;
+    let f = capture_only!(strict_macros, trust log_ctx!, || log_ctx!(ctx, "done"));
+    assert_eq!(f(), "[1] done");
+}
+
+fn main() {
+    trusted();
+    strict();
+}
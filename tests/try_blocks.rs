@@ -0,0 +1,35 @@
+// `try` blocks are unstable, so this only runs with
+// `RUSTFLAGS="--cfg captures_nightly" cargo +nightly test`
+#![cfg(captures_nightly)]
+#![feature(try_blocks)]
+
+use captures::*;
+
+// Checks that `?` works inside of `try` blocks under `capture_only!`
+fn question_mark() {
+    let a = String::from("12");
+    let f = capture_only!(clone a, |b: &str| {
+        let r: Result<i32, std::num::ParseIntError> = try { a.parse::<i32>()? + b.parse::<i32>()? };
+        r.ok()
+    });
+    assert_eq!(f("3"), Some(15));
+    assert_eq!(f("x"), None);
+}
+
+// Checks that bindings inside of the `try` block are scoped to it, also when shadowing captures
+fn scoping() {
+    let a = 1;
+    let f = capture_only!(clone a, move || {
+        let r: Option<i32> = try {
+            let a = Some(a + 1)?;
+            a * 10
+        };
+        (r, a)
+    });
+    assert_eq!(f(), (Some(20), 1));
+}
+
+fn main() {
+    question_mark();
+    scoping();
+}
@@ -0,0 +1,47 @@
+use captures::*;
+
+use std::rc::Rc;
+
+// Checks that each closure gets its own clones
+fn closures() {
+    let a = Rc::new(1);
+    let (f, g) = capture!(clone a, (move || *a, move |x: i32| *a + x));
+    assert_eq!(Rc::strong_count(&a), 3);
+    assert_eq!(f() + g(1), 3);
+}
+
+// Checks that `with` directives are evaluated once per closure
+fn fresh() {
+    let mut n = 0;
+    let mut next = || {
+        n += 1;
+        n
+    };
+    let (f, g, h) = capture!(with x = next(), (|| x, || x, || x,));
+    assert_eq!((f(), g(), h()), (1, 2, 3));
+}
+
+// Checks that a single closure in a tuple still gives a tuple, and that async blocks work
+fn other_forms() {
+    let a = Rc::new(1);
+    let (f,) = capture_only!(clone a, (move || *a,));
+    assert_eq!(f(), 1);
+    let (_fut, g) = capture!(clone a, (async { *a }, || *a); assert 'static);
+    assert_eq!(g(), 1);
+}
+
+// A body that ends in a call with a turbofish, or with a closure argument, is not a tuple
+fn not_tuples() {
+    let v = vec![1, 2];
+    let f = capture_only!(clone v, move || v.iter().sum::<i32>());
+    assert_eq!(f(), 3);
+    let g = capture!(clone v, move || v.iter().map::<i32, _>(|x| x * 2).sum::<i32>());
+    assert_eq!(g(), 6);
+}
+
+fn main() {
+    closures();
+    fresh();
+    other_forms();
+    not_tuples();
+}
@@ -0,0 +1,29 @@
+use captures::*;
+
+// Names in any script can be given directives and be used inline in format strings, including
+// those with combining marks, like the virama in `नमस्ते`
+fn scripts() {
+    let 数量 = 3;
+    let नमस्ते = String::from("hi");
+    let größe = vec![1, 2];
+    let f = capture_only!(all 数量, clone नमस्ते, ref größe, move || {
+        format!("{नमस्ते} {数量} {}", größe.len())
+    });
+    assert_eq!(f(), "hi 3 2");
+    let g = capture!(clone नमस्ते, with 合计 = 数量 + 1, move || format!("{नमस्ते}{合计:>width$}", width = 数量));
+    assert_eq!(g(), "hi  4");
+}
+
+// The compiler normalizes identifiers to NFC, so a directive written with a decomposed `é` applies
+// to the variable written with the precomposed one
+fn normalization() {
+    let café = String::from("x");
+    let f = capture_only!(clone café, move || café + "y");
+    assert_eq!(f(), "xy");
+    assert_eq!(café, "x");
+}
+
+fn main() {
+    scripts();
+    normalization();
+}
@@ -0,0 +1,26 @@
+use captures::*;
+
+use std::rc::Rc;
+
+// Directives that are used, or whose name starts with `_`, are fine with `deny_unused`
+fn used() {
+    let (a, b) = (Rc::new(1), 2);
+    let f = capture!(deny_unused, clone a, all b, with _guard = Rc::clone(&a), move || *a + b);
+    assert_eq!(f(), 3);
+    // The closure keeps both its clone and the guard
+    assert_eq!(Rc::strong_count(&a), 3);
+    drop(f);
+    assert_eq!(Rc::strong_count(&a), 1);
+}
+
+// Each closure in a tuple only uses some of the directives
+fn tuples() {
+    let (a, b) = (1, 2);
+    let (f, g) = capture!(deny_unused, all a, all b, (move || a, move || b));
+    assert_eq!(f() + g(), 3);
+}
+
+fn main() {
+    used();
+    tuples();
+}
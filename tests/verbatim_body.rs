@@ -0,0 +1,26 @@
+use captures::*;
+
+use std::rc::Rc;
+
+// The body of a closure in `capture!` is passed on as it is written, so it may use syntax that
+// isn't known to the parser of the macro
+fn main() {
+    let v = Rc::new(5);
+    let f = capture!(clone v, move || {
+        unsafe extern "C" {
+            safe fn abs(x: i32) -> i32;
+        }
+        abs(-*v) + const { 1 }
+    });
+    assert_eq!(f(), 6);
+
+    let g = capture!(clone v, move |n: usize| -> usize {
+        let len = c"hi".to_bytes().len();
+        len + n + *v as usize
+    });
+    assert_eq!(g(1), 8);
+
+    let w = 2;
+    let h = capture!(with w = w + const { 1 }, move || w + const { 10 }; assert Send);
+    assert_eq!(h(), 13);
+}
@@ -0,0 +1,32 @@
+#![cfg(feature = "wasm-bindgen")]
+
+use captures::*;
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use wasm_bindgen::closure::Closure;
+
+// The cast to `dyn FnMut` is inferred for any number of arguments
+fn wrap() {
+    let total = Rc::new(Cell::new(0u32));
+    let _none: Closure<dyn FnMut()> = wasm_closure!(clone total, || total.set(1));
+    let _two: Closure<dyn FnMut(u32, u32) -> u32> = wasm_closure!(clone total, |a: u32, b| {
+        total.set(a + b);
+        a + b
+    });
+}
+
+// `FnOnce` closures are accepted by the `once` variant
+fn once() {
+    let s = String::from("consumed");
+    let _once = wasm_closure_once!(with s = s, move |x: u32| {
+        drop(s);
+        x
+    });
+}
+
+fn main() {
+    wrap();
+    once();
+}
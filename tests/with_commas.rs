@@ -0,0 +1,22 @@
+use captures::*;
+use std::collections::HashMap;
+
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+// Commas in the expression of a `with` directive don't end the directive
+fn main() {
+    let n = 1;
+    let f = capture!(
+        with add = |a: i32, b: i32| a + b,
+        with m = match n { 1 => 2, _ => 3 },
+        with p = Point { x: 1, y: 2 },
+        with map = HashMap::<i32, i32>::new(),
+        move || add(m, p.x + p.y) + map.len() as i32
+    );
+    assert_eq!(f(), 5);
+    let (g, h) = capture!(with mul = |a, b| a * b, (move || mul(2, 3), move || mul(4, 5)));
+    assert_eq!(g() + h(), 26);
+}
@@ -0,0 +1,34 @@
+use captures::*;
+
+use std::rc::Rc;
+
+define_captures!(numbers = with one = 1; with two = { one + 1 };);
+
+struct Config {
+    name: &'static str,
+    retries: u32,
+}
+
+// A `with` directive may end with `;` instead of a comma
+fn main() {
+    let a = Rc::new(1);
+    let n = 2;
+    let f = capture!(
+        with config = Config { name: "a", retries: 3 };
+        with kind = match n {
+            1 => "one",
+            _ => "many",
+        };
+        clone a,
+        move || format!("{} {} {} {}", config.name, config.retries, kind, a)
+    );
+    assert_eq!(f(), "a 3 many 1");
+    let g = capture_only!(with b = { let b = n * 2; b + 1 }; all n, move || b + n);
+    assert_eq!(g(), 7);
+    let (h, i) = capture!(with b = Some(|| 1); (move || b.map_or(0, |b| b()), move || n));
+    assert_eq!(h() + i(), 3);
+    let j = capture!([with b = 1; with c = 2], use numbers, move || b + c + one + two);
+    assert_eq!(j(), 6);
+    let k = capture!(with b = 1; move || b; assert Send);
+    assert_eq!(k(), 1);
+}